@@ -0,0 +1,11 @@
+use std::env;
+
+fn main() {
+    let manifest_directory = env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rustc-link-arg-bins=-T{manifest_directory}/src/linker.ld");
+    println!("cargo:rerun-if-changed=src/linker.ld");
+
+    // The interpreter is linked above the other programs, so that it can be loaded together with
+    // the executable that requests it
+    println!("cargo:rustc-link-arg-bin=interpreter=--defsym=BASE_ADDRESS_OVERRIDE=0x20000000");
+}
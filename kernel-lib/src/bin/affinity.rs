@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use kernel_lib::{exit, fork, getcpu, sched_getaffinity, sched_setaffinity, wait, EINVAL};
+use log::info;
+
+#[no_mangle]
+fn main() -> i32 {
+    let hart_id = getcpu();
+    assert!(hart_id >= 0);
+    info!("running on hart {}", hart_id);
+
+    // The default mask allows every online hart, which includes the current one
+    let mut mask: usize = 0;
+    assert_eq!(sched_getaffinity(0, &mut mask), 8);
+    assert_ne!(mask & (1 << hart_id), 0);
+
+    assert_eq!(sched_setaffinity(0, 1 << hart_id), 0);
+    assert_eq!(
+        sched_setaffinity(0, 0),
+        -EINVAL,
+        "the mask contains no online hart"
+    );
+    assert_eq!(sched_getaffinity(0, &mut mask), 8);
+    assert_eq!(mask, 1 << hart_id);
+
+    // The child inherits the mask of the parent
+    if fork() == 0 {
+        let mut mask: usize = 0;
+        sched_getaffinity(0, &mut mask);
+        assert_eq!(mask, 1 << hart_id);
+        assert_eq!(getcpu(), hart_id);
+        exit(0);
+    }
+
+    let mut exit_code: usize = 0;
+    wait(&mut exit_code);
+    assert_eq!(exit_code, 0);
+    0
+}
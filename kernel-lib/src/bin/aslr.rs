@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::slice;
+
+use kernel_lib::{
+    brk,
+    getauxval,
+    mmap,
+    AT_PAGESZ,
+    AT_RANDOM,
+    MAP_ANONYMOUS,
+    MAP_PRIVATE,
+    PROT_READ,
+    PROT_WRITE,
+};
+use log::info;
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(getauxval(AT_PAGESZ), 4096);
+    let random_address = getauxval(AT_RANDOM);
+    assert_ne!(
+        random_address, 0,
+        "the auxiliary vector has no AT_RANDOM entry"
+    );
+    let random_bytes = unsafe { slice::from_raw_parts(random_address as *const u8, 16) };
+
+    // The addresses change on each run unless the kernel is booted with `aslr=off`
+    let stack_variable = 0;
+    info!("stack: {:#x}", &stack_variable as *const i32 as usize);
+    info!("program break: {:#x}", brk(0));
+    info!(
+        "mmap: {:#x}",
+        mmap(4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS)
+    );
+    info!("AT_RANDOM: {:02x?}", random_bytes);
+    0
+}
@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::slice;
+
+use kernel_lib::{getauxval, AT_BASE, AT_ENTRY, AT_PHDR, AT_PHENT, AT_PHNUM};
+use log::info;
+
+/// The type of a loadable segment in a program header.
+const PT_LOAD: u32 = 1;
+
+#[no_mangle]
+fn main() -> i32 {
+    let program_header_address = getauxval(AT_PHDR);
+    let program_header_size = getauxval(AT_PHENT);
+    let program_header_count = getauxval(AT_PHNUM);
+    assert_ne!(
+        program_header_address, 0,
+        "the auxiliary vector has no AT_PHDR entry"
+    );
+    assert_eq!(program_header_size, 56);
+    assert_eq!(getauxval(AT_BASE), 0, "the executable has no interpreter");
+
+    let program_header_table = unsafe {
+        slice::from_raw_parts(
+            program_header_address as *const u8,
+            program_header_size * program_header_count,
+        )
+    };
+    let load_count = program_header_table
+        .chunks(program_header_size)
+        .filter(|program_header| {
+            u32::from_le_bytes(program_header[..4].try_into().unwrap()) == PT_LOAD
+        })
+        .count();
+    assert_ne!(load_count, 0, "the executable has no loadable segment");
+
+    info!(
+        "{} program headers at {:#x}, {} loadable, entry point {:#x}",
+        program_header_count,
+        program_header_address,
+        load_count,
+        getauxval(AT_ENTRY)
+    );
+    0
+}
@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::{arch::asm, slice};
+
+use kernel_lib::{
+    exec,
+    fork,
+    getrlimit,
+    wait,
+    write,
+    ResourceLimit,
+    EFAULT,
+    ENOENT,
+    ENOSYS,
+    RLIMIT_STACK,
+};
+use log::info;
+
+/// An address in the kernel, which is not accessible from user space.
+const KERNEL_ADDRESS: usize = 0x80200000;
+
+/// A system call ID that the kernel doesn't implement.
+const UNSUPPORTED_SYSCALL: usize = 4096;
+
+fn unsupported_syscall() -> isize {
+    let mut result: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            lateout("a0") result,
+            in("a7") UNSUPPORTED_SYSCALL,
+        );
+    }
+    result
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let kernel_buffer = unsafe { slice::from_raw_parts(KERNEL_ADDRESS as *const u8, 16) };
+    assert_eq!(write(1, kernel_buffer), -EFAULT);
+    info!("write from a kernel address failed with EFAULT");
+
+    let text_address = main as usize;
+    let read_only_limit = unsafe { &mut *(text_address as *mut ResourceLimit) };
+    assert_eq!(getrlimit(RLIMIT_STACK, read_only_limit), -EFAULT);
+    info!("getrlimit to a read-only address failed with EFAULT");
+
+    let kernel_path = unsafe {
+        core::str::from_utf8_unchecked(slice::from_raw_parts(KERNEL_ADDRESS as *const u8, 16))
+    };
+    assert_eq!(exec(kernel_path), -EFAULT);
+    info!("exec with a kernel address failed with EFAULT");
+
+    assert_eq!(exec("nonexistent\0"), -ENOENT);
+    info!("exec of a nonexistent file failed with ENOENT");
+
+    assert_eq!(unsupported_syscall(), -ENOSYS);
+    info!("an unsupported system call failed with ENOSYS");
+
+    let pid = fork();
+    if pid == 0 {
+        return 7;
+    }
+    let kernel_status = unsafe { &mut *(KERNEL_ADDRESS as *mut usize) };
+    assert_eq!(wait(kernel_status), -EFAULT);
+    info!("wait to a kernel address failed with EFAULT");
+
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    info!("the child is still reaped after the failed wait");
+    0
+}
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use kernel_lib::{
+    clock_gettime,
+    exit,
+    fork,
+    get_time,
+    getrusage,
+    times,
+    wait,
+    ProcessTimes,
+    ResourceUsage,
+    TimeSpec,
+    CLOCK_PROCESS_CPUTIME_ID,
+    CLOCK_THREAD_CPUTIME_ID,
+    RUSAGE_CHILDREN,
+    RUSAGE_SELF,
+};
+use log::info;
+
+/// The time that each process spins, in milliseconds.
+const SPIN_TIME: isize = 200;
+
+/// Spins for [SPIN_TIME] milliseconds.
+fn spin() {
+    let start_time = get_time();
+    while get_time() < start_time + SPIN_TIME {}
+}
+
+/// Returns the time of a clock in milliseconds.
+fn clock_millisecond(clock_id: usize) -> usize {
+    let mut time_spec = TimeSpec::default();
+    assert_eq!(clock_gettime(clock_id, &mut time_spec), 0);
+    time_spec.second * 1000 + time_spec.nanosecond / 1_000_000
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    spin();
+    let process_time = clock_millisecond(CLOCK_PROCESS_CPUTIME_ID);
+    let thread_time = clock_millisecond(CLOCK_THREAD_CPUTIME_ID);
+    info!("process: {} ms, thread: {} ms", process_time, thread_time);
+    assert!(thread_time <= process_time);
+    assert!(
+        process_time <= get_time() as usize,
+        "the CPU time exceeds the time since the boot"
+    );
+
+    let mut resource_usage = ResourceUsage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut resource_usage), 0);
+    info!(
+        "user: {}.{:06} s, system: {}.{:06} s",
+        resource_usage.user_time.second,
+        resource_usage.user_time.microsecond,
+        resource_usage.system_time.second,
+        resource_usage.system_time.microsecond
+    );
+
+    // The CPU time of a child process is added to the parent when the child is reaped
+    if fork() == 0 {
+        spin();
+        exit(0);
+    }
+    let mut exit_code: usize = 0;
+    wait(&mut exit_code);
+
+    let mut process_times = ProcessTimes::default();
+    assert!(times(&mut process_times) > 0);
+    let children_time = process_times.children_user_time + process_times.children_system_time;
+    info!("children: {} ticks", children_time);
+    assert!(children_time > 0, "the CPU time of the child is lost");
+
+    let mut children_usage = ResourceUsage::default();
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut children_usage), 0);
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::slice;
+
+use kernel_lib::{getauxval, AT_ENTRY, AT_PHDR, AT_PHENT, AT_PHNUM};
+use log::info;
+
+/// The type of the program header that holds the path of the interpreter.
+const PT_INTERP: u32 = 3;
+
+/// The address where the `interpreter` program is linked, which is set by the build script.
+const INTERPRETER_BASE: usize = 0x20000000;
+
+/// The path of the interpreter, which the kernel looks up among the embedded programs by its last
+/// component.
+#[used]
+#[link_section = ".interp"]
+static INTERPRETER_PATH: [u8; 17] = *b"/lib/interpreter\0";
+
+extern "C" {
+    fn _start();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let program_header_size = getauxval(AT_PHENT);
+    let program_header_table = unsafe {
+        slice::from_raw_parts(
+            getauxval(AT_PHDR) as *const u8,
+            program_header_size * getauxval(AT_PHNUM),
+        )
+    };
+    assert!(
+        program_header_table
+            .chunks(program_header_size)
+            .any(
+                |program_header| u32::from_le_bytes(program_header[..4].try_into().unwrap())
+                    == PT_INTERP
+            ),
+        "the executable has no PT_INTERP program header"
+    );
+
+    // The interpreter jumps to the entry point of the executable, and stays mapped with its ELF
+    // header at its base address
+    assert_eq!(getauxval(AT_ENTRY), _start as usize);
+    let interpreter_header = unsafe { slice::from_raw_parts(INTERPRETER_BASE as *const u8, 4) };
+    assert_eq!(
+        interpreter_header, b"\x7fELF",
+        "the interpreter is not loaded"
+    );
+
+    info!("started by the interpreter at {:#x}", INTERPRETER_BASE);
+    0
+}
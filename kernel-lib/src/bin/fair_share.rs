@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use kernel_lib::{
+    clock_gettime,
+    exit,
+    fork,
+    get_time,
+    setpriority,
+    waitpid,
+    TimeSpec,
+    CLOCK_PROCESS_CPUTIME_ID,
+    PRIO_PROCESS,
+};
+use log::info;
+
+/// The nice values of the two child processes.
+const NICE_LIST: [isize; 2] = [0, 5];
+
+/// The weights of the nice values in the fair scheduler, whose ratio is the expected ratio of the
+/// CPU time of the two child processes.
+const WEIGHT_LIST: [usize; 2] = [1024, 335];
+
+/// The tolerance of the measured ratio, in percent of the expected ratio.
+const TOLERANCE: usize = 25;
+
+/// The time that the child processes spin, in milliseconds.
+const SPIN_TIME: isize = 2000;
+
+/// Returns the CPU time of the current process in milliseconds.
+fn cpu_time() -> usize {
+    let mut time_spec = TimeSpec::default();
+    assert_eq!(clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &mut time_spec), 0);
+    time_spec.second * 1000 + time_spec.nanosecond / 1_000_000
+}
+
+/// Checks that two CPU-bound processes share the CPU in proportion to the weights of their nice
+/// values, which requires the kernel to be booted with `scheduler=fair`.
+#[no_mangle]
+fn main() -> i32 {
+    // The children spin for the same period and exit with their CPU time in milliseconds
+    let start_time = get_time();
+    let mut pid_list = [0; NICE_LIST.len()];
+    for (pid, nice) in pid_list.iter_mut().zip(NICE_LIST) {
+        *pid = fork();
+        if *pid == 0 {
+            assert_eq!(setpriority(PRIO_PROCESS, 0, nice), 0);
+            while get_time() < start_time + SPIN_TIME {}
+            exit(cpu_time() as i32);
+        }
+    }
+
+    let mut cpu_time_list = [0; NICE_LIST.len()];
+    for (cpu_time, pid) in cpu_time_list.iter_mut().zip(pid_list) {
+        assert_eq!(waitpid(pid as usize, cpu_time), pid);
+    }
+    assert!(cpu_time_list[1] > 0, "the task with nice 5 is starved");
+
+    // The measured ratio in percent of the expected ratio
+    let percent = cpu_time_list[0] * WEIGHT_LIST[1] * 100 / (cpu_time_list[1] * WEIGHT_LIST[0]);
+    info!(
+        "nice 0: {} ms, nice 5: {} ms, {}% of the expected ratio",
+        cpu_time_list[0], cpu_time_list[1], percent
+    );
+    assert!(
+        (100 - TOLERANCE..=100 + TOLERANCE).contains(&percent),
+        "the CPU time is not shared in proportion to the weights"
+    );
+    0
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::slice;
+
+use kernel_lib::{mmap, munmap, MAP_ANONYMOUS, MAP_HUGETLB, MAP_PRIVATE, PROT_READ, PROT_WRITE};
+use log::info;
+
+const HUGE_PAGE_SIZE: usize = 4096 * 512;
+
+#[no_mangle]
+fn main() -> i32 {
+    let address = mmap(
+        HUGE_PAGE_SIZE * 2,
+        PROT_READ | PROT_WRITE,
+        MAP_PRIVATE | MAP_ANONYMOUS | MAP_HUGETLB,
+    );
+    assert!(address > 0, "mmap failed with {}", address);
+    assert_eq!(address as usize % HUGE_PAGE_SIZE, 0);
+    info!("mapped 2 huge pages at {:#x}", address);
+
+    let mapping = unsafe { slice::from_raw_parts_mut(address as *mut usize, HUGE_PAGE_SIZE / 4) };
+    for (index, word) in mapping.iter_mut().enumerate() {
+        *word = index;
+    }
+    assert!(mapping
+        .iter()
+        .enumerate()
+        .all(|(index, &word)| word == index));
+
+    assert_eq!(munmap(address as usize, HUGE_PAGE_SIZE * 2), 0);
+    0
+}
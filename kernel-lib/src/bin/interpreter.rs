@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use kernel_lib::{enter_program, getauxval, AT_ENTRY};
+use log::{error, info};
+
+extern "C" {
+    fn _start();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // The entry point in the auxiliary vector is the entry point of the interpreter itself if it is
+    // started directly instead of by an executable
+    let entry_point = getauxval(AT_ENTRY);
+    if entry_point == _start as usize {
+        error!("the interpreter must be requested by an executable with PT_INTERP");
+        return 1;
+    }
+
+    info!("starting the executable at {:#x}", entry_point);
+    enter_program(entry_point)
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::slice;
+
+use kernel_lib::{brk, mmap, munmap, MAP_ANONYMOUS, MAP_PRIVATE, PROT_READ, PROT_WRITE};
+use log::{info, warn};
+
+const MAPPING_SIZE: usize = 4096 * 256;
+const MAPPING_LIMIT: usize = 64;
+
+#[no_mangle]
+fn main() -> i32 {
+    let program_break = brk(0) as usize;
+    let heap_size = 4096 * 4;
+    assert_eq!(
+        brk(program_break + heap_size),
+        (program_break + heap_size) as isize
+    );
+    let heap = unsafe { slice::from_raw_parts_mut(program_break as *mut u8, heap_size) };
+    heap.fill(1);
+    info!(
+        "extended the program break to {:#x}",
+        program_break + heap_size
+    );
+
+    warn!("attempt to exhaust the physical memory");
+    let mut mapping_list = [0; MAPPING_LIMIT];
+    let mut mapping_count = 0;
+    while mapping_count < MAPPING_LIMIT {
+        let address = mmap(
+            MAPPING_SIZE,
+            PROT_READ | PROT_WRITE,
+            MAP_PRIVATE | MAP_ANONYMOUS,
+        );
+        if address < 0 {
+            info!(
+                "mmap failed with {} after {} mappings",
+                address, mapping_count
+            );
+            break;
+        }
+
+        let mapping = unsafe { slice::from_raw_parts_mut(address as *mut u8, MAPPING_SIZE) };
+        mapping.fill(1);
+        mapping_list[mapping_count] = address as usize;
+        mapping_count += 1;
+    }
+
+    for &address in &mapping_list[..mapping_count] {
+        assert_eq!(munmap(address, MAPPING_SIZE), 0);
+    }
+    assert_eq!(brk(program_break), program_break as isize);
+    0
+}
@@ -0,0 +1,88 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use kernel_lib::{
+    clock_gettime,
+    exit,
+    fork,
+    get_time,
+    getpriority,
+    nice,
+    setpriority,
+    waitpid,
+    TimeSpec,
+    CLOCK_PROCESS_CPUTIME_ID,
+    EPERM,
+    PRIO_PROCESS,
+};
+use log::info;
+
+/// The nice values of the child processes, which compete for the CPU at the same time.
+const NICE_LIST: [isize; 3] = [0, 10, 19];
+
+/// The time that the child processes spin, in milliseconds, which spans several priority boosts
+/// of the multilevel feedback queue.
+const SPIN_TIME: isize = 2000;
+
+/// Returns the CPU time of the current process in milliseconds.
+fn cpu_time() -> usize {
+    let mut time_spec = TimeSpec::default();
+    assert_eq!(clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &mut time_spec), 0);
+    time_spec.second * 1000 + time_spec.nanosecond / 1_000_000
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // The children spin for the same period and exit with their CPU time in milliseconds
+    let start_time = get_time();
+    let mut pid_list = [0; NICE_LIST.len()];
+    for (pid, nice) in pid_list.iter_mut().zip(NICE_LIST) {
+        *pid = fork();
+        if *pid == 0 {
+            assert_eq!(setpriority(PRIO_PROCESS, 0, nice), 0);
+            while get_time() < start_time + SPIN_TIME {}
+            exit(cpu_time() as i32);
+        }
+    }
+
+    let mut cpu_time_list = [0; NICE_LIST.len()];
+    for (cpu_time, pid) in cpu_time_list.iter_mut().zip(pid_list) {
+        assert_eq!(waitpid(pid as usize, cpu_time), pid);
+    }
+    for (nice, cpu_time) in NICE_LIST.iter().zip(cpu_time_list) {
+        info!("nice {:>2}: {} ms", nice, cpu_time);
+    }
+
+    // A task with a higher nice value starts each boost in a lower queue and is demoted below the
+    // other tasks sooner, but the boost still gives it some CPU time
+    assert!(
+        cpu_time_list.windows(2).all(|pair| pair[0] > pair[1]),
+        "a task with a higher nice value got at least as much CPU time"
+    );
+    assert!(
+        cpu_time_list[NICE_LIST.len() - 1] > 0,
+        "the task with the highest nice value is starved"
+    );
+
+    assert_eq!(getpriority(PRIO_PROCESS, 0), 0);
+    assert_eq!(nice(5), 5);
+    assert_eq!(
+        setpriority(PRIO_PROCESS, 0, 0),
+        -EPERM,
+        "an unprivileged process raised its priority"
+    );
+    assert_eq!(getpriority(PRIO_PROCESS, 0), 5);
+    assert_eq!(setpriority(PRIO_PROCESS, 0, 100), 0);
+    assert_eq!(
+        getpriority(PRIO_PROCESS, 0),
+        19,
+        "the nice value is not clamped"
+    );
+    assert!(
+        getpriority(PRIO_PROCESS, 4096) > 19,
+        "the process doesn't exist"
+    );
+    0
+}
@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use kernel_lib::{
+    exit,
+    fork,
+    get_time,
+    sched_getscheduler,
+    sched_setattr,
+    sched_setscheduler,
+    sched_yield,
+    wait,
+    SchedulingAttribute,
+    EBUSY,
+    EINVAL,
+    SCHED_DEADLINE,
+    SCHED_OTHER,
+    SCHED_RR,
+};
+use log::info;
+
+/// The time that the real-time parent spins, in milliseconds.
+const SPIN_TIME: isize = 500;
+
+/// One millisecond in nanoseconds.
+const MILLISECOND: u64 = 1_000_000;
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(sched_getscheduler(0), SCHED_OTHER as isize);
+    assert_eq!(sched_setscheduler(0, SCHED_RR, 0), -EINVAL);
+    assert_eq!(sched_setscheduler(0, SCHED_RR, 100), -EINVAL);
+
+    // The deadline class is admitted only if the runtime fits in the bandwidth
+    let mut attribute = SchedulingAttribute {
+        size: 48,
+        policy: SCHED_DEADLINE as u32,
+        runtime: 96 * MILLISECOND,
+        deadline: 100 * MILLISECOND,
+        period: 100 * MILLISECOND,
+        ..Default::default()
+    };
+    assert_eq!(sched_setattr(0, &attribute), -EBUSY);
+    attribute.runtime = 200 * MILLISECOND;
+    assert_eq!(sched_setattr(0, &attribute), -EINVAL);
+    attribute.runtime = 10 * MILLISECOND;
+    assert_eq!(sched_setattr(0, &attribute), 0);
+    assert_eq!(sched_getscheduler(0), SCHED_DEADLINE as isize);
+
+    // The real-time parent runs before the normal child until it blocks
+    assert_eq!(sched_setscheduler(0, SCHED_RR, 10), 0);
+    let start_time = get_time();
+    if fork() == 0 {
+        assert_eq!(sched_getscheduler(0), SCHED_RR as isize);
+        assert_eq!(sched_setscheduler(0, SCHED_OTHER, 0), 0);
+        sched_yield();
+        let delay = get_time() - start_time;
+        info!("the normal child ran {} ms after the fork", delay);
+        exit(if delay >= SPIN_TIME { 0 } else { 1 });
+    }
+
+    while get_time() < start_time + SPIN_TIME {}
+
+    let mut exit_code: usize = 0;
+    wait(&mut exit_code);
+    assert_eq!(
+        exit_code, 0,
+        "the normal child preempted the real-time parent"
+    );
+    0
+}
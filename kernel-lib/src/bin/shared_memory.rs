@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::slice;
+
+use kernel_lib::{
+    exit,
+    fork,
+    munmap,
+    shmat,
+    shmctl,
+    shmdt,
+    shmget,
+    wait,
+    EINVAL,
+    IPC_CREAT,
+    IPC_PRIVATE,
+    IPC_RMID,
+};
+use log::info;
+
+const KEY: usize = 0x5348;
+const WORD_COUNT: usize = 4096;
+
+/// A size larger than the maximum size of a shared memory object.
+const OVERSIZED_SIZE: usize = 1 << 40;
+
+fn attach(id: usize) -> &'static mut [usize] {
+    let address = shmat(id, 0);
+    assert!(address > 0, "shmat failed with {}", address);
+    unsafe { slice::from_raw_parts_mut(address as *mut usize, WORD_COUNT) }
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(shmget(IPC_PRIVATE, OVERSIZED_SIZE, IPC_CREAT), -EINVAL);
+    info!("shmget of an oversized object failed with EINVAL");
+
+    let id = shmget(KEY, WORD_COUNT * 8, IPC_CREAT);
+    assert!(id > 0, "shmget failed with {}", id);
+    let inherited = attach(id as usize);
+    assert_eq!(munmap(inherited.as_ptr() as usize, WORD_COUNT * 8), -EINVAL);
+    info!("munmap of a shared memory mapping failed with EINVAL");
+
+    if fork() == 0 {
+        // The child writes through the mapping inherited from the parent and its own mapping
+        let attached = attach(shmget(KEY, 0, 0) as usize);
+        for (index, word) in inherited.iter_mut().enumerate().take(WORD_COUNT / 2) {
+            *word = index;
+        }
+        for (index, word) in attached.iter_mut().enumerate().skip(WORD_COUNT / 2) {
+            *word = index;
+        }
+        assert_eq!(shmdt(attached.as_ptr() as usize), 0);
+        exit(0);
+    }
+
+    let mut exit_code = 0;
+    wait(&mut exit_code);
+    assert_eq!(exit_code, 0);
+    assert!(inherited
+        .iter()
+        .enumerate()
+        .all(|(index, &word)| word == index));
+    info!("the parent observed the writes of the child");
+
+    assert_eq!(shmctl(id as usize, IPC_RMID), 0);
+    assert_eq!(shmdt(inherited.as_ptr() as usize), 0);
+    0
+}
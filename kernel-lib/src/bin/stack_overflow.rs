@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::hint::black_box;
+
+use kernel_lib::{getrlimit, setrlimit, ResourceLimit, EINVAL, EPERM, RLIMIT_STACK};
+use log::{info, warn};
+
+const STACK_LIMIT: usize = 4096 * 16;
+
+fn recurse(depth: usize) -> usize {
+    if black_box(depth) == usize::MAX {
+        return 0;
+    }
+
+    let frame = black_box([depth as u8; 512]);
+    if depth % 16 == 0 {
+        info!("reached the recursion depth of {}", depth);
+    }
+    recurse(depth + 1) + frame[0] as usize
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut resource_limit = ResourceLimit::default();
+    getrlimit(RLIMIT_STACK, &mut resource_limit);
+    info!(
+        "the default stack limit is {} bytes",
+        resource_limit.current
+    );
+
+    assert_eq!(getrlimit(usize::MAX, &mut resource_limit), -EINVAL);
+
+    let mut invalid_limit = resource_limit;
+    invalid_limit.current = invalid_limit.maximum + 1;
+    assert_eq!(setrlimit(RLIMIT_STACK, &invalid_limit), -EINVAL);
+    let mut raised_limit = resource_limit;
+    raised_limit.maximum += 1;
+    assert_eq!(setrlimit(RLIMIT_STACK, &raised_limit), -EPERM);
+
+    resource_limit.current = STACK_LIMIT;
+    assert_eq!(setrlimit(RLIMIT_STACK, &resource_limit), 0);
+
+    warn!("attempt to overflow a {} bytes stack", STACK_LIMIT);
+    recurse(0) as i32
+}
@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use core::slice;
+
+use kernel_lib::{mmap, MAP_ANONYMOUS, MAP_PRIVATE, PROT_READ, PROT_WRITE};
+use log::info;
+
+const CHUNK_SIZE: usize = 4096 * 16;
+// The chunks take 24 MiB in total, which is more than the memory of the kernel
+const CHUNK_COUNT: usize = 384;
+const WORD_COUNT: usize = CHUNK_SIZE / 8;
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut chunk_list = [0; CHUNK_COUNT];
+    for (chunk_index, chunk) in chunk_list.iter_mut().enumerate() {
+        let address = mmap(
+            CHUNK_SIZE,
+            PROT_READ | PROT_WRITE,
+            MAP_PRIVATE | MAP_ANONYMOUS,
+        );
+        assert!(address > 0, "mmap failed with {}", address);
+        *chunk = address as usize;
+
+        let word_list = unsafe { slice::from_raw_parts_mut(*chunk as *mut usize, WORD_COUNT) };
+        for (word_index, word) in word_list.iter_mut().enumerate() {
+            *word = chunk_index * WORD_COUNT + word_index;
+        }
+    }
+
+    for (chunk_index, chunk) in chunk_list.iter().enumerate() {
+        let word_list = unsafe { slice::from_raw_parts(*chunk as *const usize, WORD_COUNT) };
+        for (word_index, word) in word_list.iter().enumerate() {
+            assert_eq!(*word, chunk_index * WORD_COUNT + word_index);
+        }
+    }
+
+    info!(
+        "{} bytes are written and read back",
+        CHUNK_SIZE * CHUNK_COUNT
+    );
+    0
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use kernel_lib::{get_time, sched_yield};
+use log::info;
+
+const ITERATION_COUNT: usize = 100000;
+
+#[no_mangle]
+fn main() -> i32 {
+    let start_time = get_time();
+    for _ in 0..ITERATION_COUNT {
+        get_time();
+    }
+    let elapsed_time = (get_time() - start_time) as usize;
+    info!(
+        "{} calls to get_time took {} ms ({} ns per call)",
+        ITERATION_COUNT,
+        elapsed_time,
+        elapsed_time * 1000000 / ITERATION_COUNT
+    );
+
+    let start_time = get_time();
+    for _ in 0..ITERATION_COUNT {
+        sched_yield();
+    }
+    let elapsed_time = (get_time() - start_time) as usize;
+    info!(
+        "{} calls to sched_yield took {} ms ({} ns per call)",
+        ITERATION_COUNT,
+        elapsed_time,
+        elapsed_time * 1000000 / ITERATION_COUNT
+    );
+    0
+}
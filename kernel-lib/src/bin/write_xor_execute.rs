@@ -0,0 +1,85 @@
+#![no_std]
+#![no_main]
+
+extern crate kernel_lib;
+
+use kernel_lib::{
+    exit,
+    fork,
+    mmap,
+    mprotect,
+    waitpid,
+    EACCES,
+    MAP_ANONYMOUS,
+    MAP_PRIVATE,
+    PROT_EXEC,
+    PROT_READ,
+    PROT_WRITE,
+};
+use log::info;
+
+const PAGE_SIZE: usize = 4096;
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(
+        mmap(
+            PAGE_SIZE,
+            PROT_READ | PROT_WRITE | PROT_EXEC,
+            MAP_PRIVATE | MAP_ANONYMOUS
+        ),
+        -EACCES
+    );
+
+    let address = mmap(
+        PAGE_SIZE * 3,
+        PROT_READ | PROT_WRITE,
+        MAP_PRIVATE | MAP_ANONYMOUS,
+    );
+    assert!(address > 0, "mmap failed with {}", address);
+    let address = address as usize;
+    let page_list = [
+        address as *mut usize,
+        (address + PAGE_SIZE) as *mut usize,
+        (address + PAGE_SIZE * 2) as *mut usize,
+    ];
+    for (index, page) in page_list.iter().enumerate() {
+        unsafe { page.write_volatile(index) };
+    }
+
+    assert_eq!(
+        mprotect(address, PAGE_SIZE, PROT_READ | PROT_WRITE | PROT_EXEC),
+        -EACCES
+    );
+
+    // The middle page becomes read-only, while the pages around it stay writable
+    assert_eq!(mprotect(address + PAGE_SIZE, PAGE_SIZE, PROT_READ), 0);
+    unsafe {
+        page_list[0].write_volatile(3);
+        page_list[2].write_volatile(5);
+        assert_eq!(page_list[1].read_volatile(), 1);
+    }
+
+    let pid = fork();
+    if pid == 0 {
+        unsafe { page_list[1].write_volatile(4) };
+        exit(0);
+    }
+    let mut exit_code = 0;
+    waitpid(pid as usize, &mut exit_code);
+    assert_ne!(exit_code, 0, "the write to the read-only page succeeded");
+
+    assert_eq!(
+        mprotect(address + PAGE_SIZE, PAGE_SIZE, PROT_READ | PROT_WRITE),
+        0
+    );
+    unsafe {
+        page_list[1].write_volatile(4);
+        assert_eq!(page_list[0].read_volatile(), 3);
+        assert_eq!(page_list[1].read_volatile(), 4);
+        assert_eq!(page_list[2].read_volatile(), 5);
+    }
+
+    info!("the W^X policy is enforced");
+    0
+}
@@ -3,6 +3,7 @@
 #![feature(linkage)]
 #![feature(panic_info_message)]
 #![feature(alloc_error_handler)]
+#![feature(naked_functions)]
 
 pub mod console;
 mod constant;
@@ -11,20 +12,226 @@ mod lang_items;
 mod logging;
 mod syscall;
 
+use core::{
+    arch::asm,
+    mem::size_of,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
 use syscall::{
+    sys_brk,
+    sys_clock_gettime,
     sys_exec,
     sys_exit,
     sys_fork,
     sys_get_time,
+    sys_getcpu,
+    sys_getpriority,
+    sys_getrlimit,
+    sys_getrusage,
+    sys_mmap,
+    sys_mprotect,
+    sys_munmap,
     sys_read,
+    sys_sched_getaffinity,
+    sys_sched_getscheduler,
+    sys_sched_setaffinity,
+    sys_sched_setattr,
+    sys_sched_setscheduler,
     sys_sched_yield,
+    sys_setpriority,
+    sys_setrlimit,
+    sys_shmat,
+    sys_shmctl,
+    sys_shmdt,
+    sys_shmget,
+    sys_times,
     sys_waitpid,
     sys_write,
 };
 
+/// The key that creates a new shared memory object.
+pub const IPC_PRIVATE: usize = 0;
+/// Creates the shared memory object if no object has the key.
+pub const IPC_CREAT: usize = 0o1000;
+/// Fails if the shared memory object to be created already exists.
+pub const IPC_EXCL: usize = 0o2000;
+/// Removes the shared memory object.
+pub const IPC_RMID: usize = 0;
+/// Maps the shared memory object as read-only.
+pub const SHM_RDONLY: usize = 0o10000;
+
+/// The entry that terminates the auxiliary vector.
+pub const AT_NULL: usize = 0;
+/// The entry that holds the address of the program headers of the executable.
+pub const AT_PHDR: usize = 3;
+/// The entry that holds the size of a program header.
+pub const AT_PHENT: usize = 4;
+/// The entry that holds the number of program headers.
+pub const AT_PHNUM: usize = 5;
+/// The entry that holds the page size.
+pub const AT_PAGESZ: usize = 6;
+/// The entry that holds the base address of the interpreter, or `0` without an interpreter.
+pub const AT_BASE: usize = 7;
+/// The entry that holds the entry point of the executable.
+pub const AT_ENTRY: usize = 9;
+/// The entry that holds the address of 16 random bytes.
+pub const AT_RANDOM: usize = 25;
+
+/// The error number returned when the caller lacks the privilege for the operation.
+pub const EPERM: isize = 1;
+
+/// The error number returned when the executable file or the requested object doesn't exist.
+pub const ENOENT: isize = 2;
+
+/// The error number returned when the executable file or its interpreter is not a valid ELF file.
+pub const ENOEXEC: isize = 8;
+
+/// The error number returned when the pages would be both writable and executable.
+pub const EACCES: isize = 13;
+
+/// The error number returned when an address passed to a system call is not accessible.
+pub const EFAULT: isize = 14;
+
+/// The error number returned when the deadline tasks would exceed their bandwidth.
+pub const EBUSY: isize = 16;
+
+/// The error number returned when an argument of a system call is invalid.
+pub const EINVAL: isize = 22;
+
+/// The error number returned when a system-wide limit, such as the total size of the shared
+/// memory objects, would be exceeded.
+pub const ENOSPC: isize = 28;
+
+/// The error number returned when the system call is not implemented.
+pub const ENOSYS: isize = 38;
+
+/// The clock that measures the CPU time of the current process.
+pub const CLOCK_PROCESS_CPUTIME_ID: usize = 2;
+/// The clock that measures the CPU time of the current thread.
+pub const CLOCK_THREAD_CPUTIME_ID: usize = 3;
+
+/// Selects the current process in `getrusage`.
+pub const RUSAGE_SELF: isize = 0;
+/// Selects the reaped child processes of the current process in `getrusage`.
+pub const RUSAGE_CHILDREN: isize = -1;
+
+/// The normal scheduling class.
+pub const SCHED_OTHER: usize = 0;
+/// The real-time class that runs a thread until it blocks or yields.
+pub const SCHED_FIFO: usize = 1;
+/// The real-time class that shares the CPU between the threads with the same priority.
+pub const SCHED_RR: usize = 2;
+/// The class that runs the threads by the earliest deadline first.
+pub const SCHED_DEADLINE: usize = 6;
+
+/// Selects a process by its PID in `setpriority` and `getpriority`.
+pub const PRIO_PROCESS: usize = 0;
+
+/// The resource that limits the size of the user stack.
+pub const RLIMIT_STACK: usize = 3;
+
+/// The pages can be read.
+pub const PROT_READ: usize = 1 << 0;
+/// The pages can be written.
+pub const PROT_WRITE: usize = 1 << 1;
+/// The pages can be executed.
+pub const PROT_EXEC: usize = 1 << 2;
+
+/// The mapping is private to the process.
+pub const MAP_PRIVATE: usize = 1 << 1;
+/// The mapping is not backed by any file.
+pub const MAP_ANONYMOUS: usize = 1 << 5;
+/// The mapping is backed by 2 MiB huge pages.
+pub const MAP_HUGETLB: usize = 1 << 18;
+
+/// The `ResourceLimit` struct represents the soft limit and the hard limit of a resource.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct ResourceLimit {
+    pub current: usize,
+    pub maximum: usize,
+}
+
+/// The `SchedulingAttribute` struct represents the scheduling class of a thread and its
+/// parameters, which are passed to `sched_setattr`. The durations are in nanoseconds.
+#[repr(C)]
+#[derive(Default)]
+pub struct SchedulingAttribute {
+    pub size: u32,
+    pub policy: u32,
+    pub flags: u64,
+    pub nice: i32,
+    pub priority: u32,
+    pub runtime: u64,
+    pub deadline: u64,
+    pub period: u64,
+}
+
+/// The `TimeSpec` struct represents a duration in seconds and nanoseconds.
+#[repr(C)]
+#[derive(Default)]
+pub struct TimeSpec {
+    pub second: usize,
+    pub nanosecond: usize,
+}
+
+/// The `TimeVal` struct represents a duration in seconds and microseconds.
+#[repr(C)]
+#[derive(Default)]
+pub struct TimeVal {
+    pub second: usize,
+    pub microsecond: usize,
+}
+
+/// The `ProcessTimes` struct represents the CPU time of a process and its reaped child processes in
+/// clock ticks, which are 10 milliseconds.
+#[repr(C)]
+#[derive(Default)]
+pub struct ProcessTimes {
+    pub user_time: usize,
+    pub system_time: usize,
+    pub children_user_time: usize,
+    pub children_system_time: usize,
+}
+
+/// The `ResourceUsage` struct represents the resource usage of a process, where only the CPU time
+/// is measured.
+#[repr(C)]
+#[derive(Default)]
+pub struct ResourceUsage {
+    pub user_time: TimeVal,
+    pub system_time: TimeVal,
+    pub other: [usize; 14],
+}
+
+/// The initial stack pointer, which points to `argc`.
+static STACK_POINTER: AtomicPtr<usize> = AtomicPtr::new(core::ptr::null_mut());
+
+/// The auxiliary vector that the kernel writes on the initial user stack.
+static AUXILIARY_VECTOR: AtomicPtr<usize> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Passes the initial stack pointer to `start`, which points to `argc`.
+#[naked]
 #[no_mangle]
 #[link_section = ".text.init"]
-pub extern "C" fn _start() -> ! {
+unsafe extern "C" fn _start() -> ! {
+    asm!("mv a0, sp", "tail {start}", start = sym start, options(noreturn))
+}
+
+extern "C" fn start(stack_pointer: *mut usize) -> ! {
+    // The auxiliary vector follows `argc`, the null-terminated `argv`, and the null-terminated
+    // `envp`
+    STACK_POINTER.store(stack_pointer, Ordering::Relaxed);
+    unsafe {
+        let argument_count = *stack_pointer;
+        let mut pointer = stack_pointer.add(argument_count + 2);
+        while *pointer != 0 {
+            pointer = pointer.add(1);
+        }
+        AUXILIARY_VECTOR.store(pointer.add(1), Ordering::Relaxed);
+    }
+
     logging::init();
     heap_allocator::init();
 
@@ -32,6 +239,35 @@ pub extern "C" fn _start() -> ! {
     panic!("failed to invoke `exit`")
 }
 
+/// Returns the value of the entry of a specific type in the auxiliary vector, or `0` if the vector
+/// has no such entry.
+pub fn getauxval(entry_type: usize) -> usize {
+    let mut pointer = AUXILIARY_VECTOR.load(Ordering::Relaxed);
+    unsafe {
+        while *pointer != AT_NULL {
+            if *pointer == entry_type {
+                return *pointer.add(1);
+            }
+            pointer = pointer.add(2);
+        }
+    }
+    0
+}
+
+/// Jumps to `entry_point` with the initial stack pointer, which an interpreter uses to start the
+/// executable that requests it.
+pub fn enter_program(entry_point: usize) -> ! {
+    unsafe {
+        asm!(
+            "mv sp, {stack_pointer}",
+            "jr {entry_point}",
+            stack_pointer = in(reg) STACK_POINTER.load(Ordering::Relaxed),
+            entry_point = in(reg) entry_point,
+            options(noreturn)
+        )
+    }
+}
+
 #[linkage = "weak"]
 #[no_mangle]
 fn main() -> i32 {
@@ -54,10 +290,91 @@ pub fn sched_yield() -> isize {
     sys_sched_yield()
 }
 
+/// Sets the nice value of a process, where `who` is the PID of the process or `0` for the current
+/// process. A lower nice value gives the process a higher priority.
+pub fn setpriority(which: usize, who: usize, nice: isize) -> isize {
+    sys_setpriority(which, who, nice)
+}
+
+/// Returns the nice value of a process, where `who` is the PID of the process or `0` for the
+/// current process. The kernel returns `20 - nice` to keep the result positive, so a failed system
+/// call gives a value above the highest nice value `19`.
+pub fn getpriority(which: usize, who: usize) -> isize {
+    20 - sys_getpriority(which, who)
+}
+
+/// Adds `increment` to the nice value of the current process, and returns the new nice value.
+pub fn nice(increment: isize) -> isize {
+    setpriority(PRIO_PROCESS, 0, getpriority(PRIO_PROCESS, 0) + increment);
+    getpriority(PRIO_PROCESS, 0)
+}
+
+/// Sets the scheduling class of a process to `SCHED_OTHER`, `SCHED_FIFO`, or `SCHED_RR`, where
+/// `pid` is `0` for the current process. A real-time class needs a priority from `1` to `99`.
+pub fn sched_setscheduler(pid: usize, policy: usize, priority: i32) -> isize {
+    sys_sched_setscheduler(pid, policy, &priority as *const i32)
+}
+
+/// Returns the scheduling class of a process, where `pid` is `0` for the current process.
+pub fn sched_getscheduler(pid: usize) -> isize {
+    sys_sched_getscheduler(pid)
+}
+
+/// Sets the scheduling class of a process and its parameters, where `pid` is `0` for the current
+/// process. Returns `-EBUSY` if a deadline class can't be admitted.
+pub fn sched_setattr(pid: usize, attribute: &SchedulingAttribute) -> isize {
+    sys_sched_setattr(pid, attribute as *const SchedulingAttribute, 0)
+}
+
+/// Sets the affinity mask of a process, where `pid` is `0` for the current process and bit `i` of
+/// `mask` allows the process to run on the hart with ID `i`. Returns `-EINVAL` if the mask
+/// contains no online hart.
+pub fn sched_setaffinity(pid: usize, mask: usize) -> isize {
+    sys_sched_setaffinity(pid, size_of::<usize>(), &mask as *const usize)
+}
+
+/// Writes the affinity mask of a process to `mask`, where `pid` is `0` for the current process.
+pub fn sched_getaffinity(pid: usize, mask: &mut usize) -> isize {
+    sys_sched_getaffinity(pid, size_of::<usize>(), mask as *mut usize)
+}
+
+/// Returns the ID of the hart that the current thread is running on.
+pub fn getcpu() -> isize {
+    let mut cpu: u32 = 0;
+    match sys_getcpu(&mut cpu as *mut u32, core::ptr::null_mut()) {
+        0 => cpu as isize,
+        error => error,
+    }
+}
+
+pub fn getrlimit(resource: usize, resource_limit: &mut ResourceLimit) -> isize {
+    sys_getrlimit(resource, resource_limit as *mut ResourceLimit)
+}
+
+pub fn setrlimit(resource: usize, resource_limit: &ResourceLimit) -> isize {
+    sys_setrlimit(resource, resource_limit as *const ResourceLimit)
+}
+
 pub fn get_time() -> isize {
     sys_get_time()
 }
 
+/// Writes the time of a clock to `time_spec`.
+pub fn clock_gettime(clock_id: usize, time_spec: &mut TimeSpec) -> isize {
+    sys_clock_gettime(clock_id, time_spec as *mut TimeSpec)
+}
+
+/// Writes the CPU time of the current process and its reaped child processes to `process_times`,
+/// and returns the time since the boot in clock ticks.
+pub fn times(process_times: &mut ProcessTimes) -> isize {
+    sys_times(process_times as *mut ProcessTimes)
+}
+
+/// Writes the CPU time of the current process or its reaped child processes to `resource_usage`.
+pub fn getrusage(who: isize, resource_usage: &mut ResourceUsage) -> isize {
+    sys_getrusage(who, resource_usage as *mut ResourceUsage)
+}
+
 pub fn fork() -> isize {
     sys_fork()
 }
@@ -73,3 +390,43 @@ pub fn wait(exit_code: &mut usize) -> isize {
 pub fn waitpid(pid: usize, exit_code: &mut usize) -> isize {
     sys_waitpid(pid as isize, exit_code as *mut usize)
 }
+
+/// Sets the program break to `address`, and returns the new program break. Returns the current
+/// program break if `address` is `0`.
+pub fn brk(address: usize) -> isize {
+    sys_brk(address)
+}
+
+/// Maps `length` bytes of anonymous memory, and returns the start address of the mapping.
+pub fn mmap(length: usize, protection: usize, flags: usize) -> isize {
+    sys_mmap(0, length, protection, flags, -1, 0)
+}
+
+pub fn munmap(address: usize, length: usize) -> isize {
+    sys_munmap(address, length)
+}
+
+/// Changes the protection of the pages from `address` to `address + length`. The pages can't be
+/// both writable and executable.
+pub fn mprotect(address: usize, length: usize, protection: usize) -> isize {
+    sys_mprotect(address, length, protection)
+}
+
+/// Returns the ID of the shared memory object with a specific key, which is created with
+/// `IPC_CREAT` if no object has the key.
+pub fn shmget(key: usize, size: usize, flags: usize) -> isize {
+    sys_shmget(key, size, flags)
+}
+
+pub fn shmctl(id: usize, command: usize) -> isize {
+    sys_shmctl(id, command)
+}
+
+/// Maps the shared memory object with a specific ID, and returns the start address of the mapping.
+pub fn shmat(id: usize, flags: usize) -> isize {
+    sys_shmat(id, 0, flags)
+}
+
+pub fn shmdt(address: usize) -> isize {
+    sys_shmdt(address)
+}
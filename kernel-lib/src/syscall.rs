@@ -1,13 +1,36 @@
 use core::arch::asm;
 
+use crate::{ProcessTimes, ResourceLimit, ResourceUsage, SchedulingAttribute, TimeSpec};
+
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_SCHED_SETSCHEDULER: usize = 119;
+const SYSCALL_SCHED_GETSCHEDULER: usize = 120;
+const SYSCALL_SCHED_SETAFFINITY: usize = 122;
+const SYSCALL_SCHED_GETAFFINITY: usize = 123;
 const SYSCALL_SCHED_YIELD: usize = 128;
+const SYSCALL_SETPRIORITY: usize = 140;
+const SYSCALL_GETPRIORITY: usize = 141;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_GETRLIMIT: usize = 163;
+const SYSCALL_SETRLIMIT: usize = 164;
+const SYSCALL_GETRUSAGE: usize = 165;
+const SYSCALL_GETCPU: usize = 168;
 const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_SHMGET: usize = 194;
+const SYSCALL_SHMCTL: usize = 195;
+const SYSCALL_SHMAT: usize = 196;
+const SYSCALL_SHMDT: usize = 197;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_SCHED_SETATTR: usize = 274;
 
 fn syscall(id: usize, args: [usize; 3]) -> isize {
     let mut result: isize;
@@ -23,6 +46,23 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     result
 }
 
+fn syscall_6(id: usize, args: [usize; 6]) -> isize {
+    let mut result: isize;
+    unsafe {
+        asm!(
+          "ecall",
+          inlateout("a0") args[0] => result,
+          in("a1") args[1],
+          in("a2") args[2],
+          in("a3") args[3],
+          in("a4") args[4],
+          in("a5") args[5],
+          in("a7") id,
+        );
+    }
+    result
+}
+
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     syscall(
         SYSCALL_READ,
@@ -38,10 +78,65 @@ pub fn sys_exit(exit_code: i32) -> isize {
     syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0])
 }
 
+pub fn sys_sched_setscheduler(pid: usize, policy: usize, priority: *const i32) -> isize {
+    syscall(SYSCALL_SCHED_SETSCHEDULER, [pid, policy, priority as usize])
+}
+
+pub fn sys_sched_getscheduler(pid: usize) -> isize {
+    syscall(SYSCALL_SCHED_GETSCHEDULER, [pid, 0, 0])
+}
+
+pub fn sys_sched_setattr(pid: usize, attribute: *const SchedulingAttribute, flags: usize) -> isize {
+    syscall(SYSCALL_SCHED_SETATTR, [pid, attribute as usize, flags])
+}
+
+pub fn sys_sched_setaffinity(pid: usize, size: usize, mask: *const usize) -> isize {
+    syscall(SYSCALL_SCHED_SETAFFINITY, [pid, size, mask as usize])
+}
+
+pub fn sys_sched_getaffinity(pid: usize, size: usize, mask: *mut usize) -> isize {
+    syscall(SYSCALL_SCHED_GETAFFINITY, [pid, size, mask as usize])
+}
+
+pub fn sys_getcpu(cpu: *mut u32, node: *mut u32) -> isize {
+    syscall(SYSCALL_GETCPU, [cpu as usize, node as usize, 0])
+}
+
 pub fn sys_sched_yield() -> isize {
     syscall(SYSCALL_SCHED_YIELD, [0, 0, 0])
 }
 
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> isize {
+    syscall(SYSCALL_SETPRIORITY, [which, who, nice as usize])
+}
+
+pub fn sys_getpriority(which: usize, who: usize) -> isize {
+    syscall(SYSCALL_GETPRIORITY, [which, who, 0])
+}
+
+pub fn sys_getrlimit(resource: usize, resource_limit: *mut ResourceLimit) -> isize {
+    syscall(SYSCALL_GETRLIMIT, [resource, resource_limit as usize, 0])
+}
+
+pub fn sys_setrlimit(resource: usize, resource_limit: *const ResourceLimit) -> isize {
+    syscall(SYSCALL_SETRLIMIT, [resource, resource_limit as usize, 0])
+}
+
+pub fn sys_clock_gettime(clock_id: usize, time_spec: *mut TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [clock_id, time_spec as usize, 0])
+}
+
+pub fn sys_times(process_times: *mut ProcessTimes) -> isize {
+    syscall(SYSCALL_TIMES, [process_times as usize, 0, 0])
+}
+
+pub fn sys_getrusage(who: isize, resource_usage: *mut ResourceUsage) -> isize {
+    syscall(
+        SYSCALL_GETRUSAGE,
+        [who as usize, resource_usage as usize, 0],
+    )
+}
+
 pub fn sys_get_time() -> isize {
     syscall(SYSCALL_GET_TIME, [0, 0, 0])
 }
@@ -57,3 +152,45 @@ pub fn sys_waitpid(pid: isize, exit_code: *mut usize) -> isize {
 pub fn sys_exec(path: &str) -> isize {
     syscall(SYSCALL_EXEC, [path.as_ptr() as usize, 0, 0])
 }
+
+pub fn sys_shmget(key: usize, size: usize, flags: usize) -> isize {
+    syscall(SYSCALL_SHMGET, [key, size, flags])
+}
+
+pub fn sys_shmctl(id: usize, command: usize) -> isize {
+    syscall(SYSCALL_SHMCTL, [id, command, 0])
+}
+
+pub fn sys_shmat(id: usize, address: usize, flags: usize) -> isize {
+    syscall(SYSCALL_SHMAT, [id, address, flags])
+}
+
+pub fn sys_shmdt(address: usize) -> isize {
+    syscall(SYSCALL_SHMDT, [address, 0, 0])
+}
+
+pub fn sys_brk(address: usize) -> isize {
+    syscall(SYSCALL_BRK, [address, 0, 0])
+}
+
+pub fn sys_mmap(
+    address: usize,
+    length: usize,
+    protection: usize,
+    flags: usize,
+    fd: isize,
+    offset: usize,
+) -> isize {
+    syscall_6(
+        SYSCALL_MMAP,
+        [address, length, protection, flags, fd as usize, offset],
+    )
+}
+
+pub fn sys_munmap(address: usize, length: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [address, length, 0])
+}
+
+pub fn sys_mprotect(address: usize, length: usize, protection: usize) -> isize {
+    syscall(SYSCALL_MPROTECT, [address, length, protection])
+}
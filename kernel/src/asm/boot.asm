@@ -5,6 +5,12 @@ _start:
   call rust_main
 
   .section .bss.stack
+  .globl trap_stack_bottom
+trap_stack_bottom:
+  .space 4096 * 4
+  .globl trap_stack_top
+trap_stack_top:
+  .globl boot_stack_bottom
 boot_stack_bottom:
   .space 4096 * 16
   .globl boot_stack_top
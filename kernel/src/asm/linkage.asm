@@ -5,7 +5,7 @@
     .global _bin_name
 
 _bin_count:
-    .quad 7
+    .quad 24
 
 _bin_address:
     .quad bin_0_start
@@ -22,22 +22,73 @@ _bin_address:
     .quad bin_5_end
     .quad bin_6_start
     .quad bin_6_end
+    .quad bin_7_start
+    .quad bin_7_end
+    .quad bin_8_start
+    .quad bin_8_end
+    .quad bin_9_start
+    .quad bin_9_end
+    .quad bin_10_start
+    .quad bin_10_end
+    .quad bin_11_start
+    .quad bin_11_end
+    .quad bin_12_start
+    .quad bin_12_end
+    .quad bin_13_start
+    .quad bin_13_end
+    .quad bin_14_start
+    .quad bin_14_end
+    .quad bin_15_start
+    .quad bin_15_end
+    .quad bin_16_start
+    .quad bin_16_end
+    .quad bin_17_start
+    .quad bin_17_end
+    .quad bin_18_start
+    .quad bin_18_end
+    .quad bin_19_start
+    .quad bin_19_end
+    .quad bin_20_start
+    .quad bin_20_end
+    .quad bin_21_start
+    .quad bin_21_end
+    .quad bin_22_start
+    .quad bin_22_end
+    .quad bin_23_start
+    .quad bin_23_end
 
 _bin_name:
+    .string "affinity"
+    .string "aslr"
+    .string "auxiliary_vector"
+    .string "bad_pointer"
+    .string "cpu_time"
+    .string "dynamic_executable"
+    .string "fair_share"
     .string "fork"
     .string "hello_world"
+    .string "huge_page"
     .string "init"
+    .string "interpreter"
+    .string "out_of_memory"
     .string "page_fault"
+    .string "priority"
     .string "privileged_instruction"
+    .string "realtime"
+    .string "shared_memory"
     .string "shell"
     .string "sleep"
+    .string "stack_overflow"
+    .string "swap"
+    .string "syscall_benchmark"
+    .string "write_xor_execute"
 
     .section .data
     .global bin_0_start
     .global bin_0_end
     .align 3
 bin_0_start:
-    .incbin "target/riscv64gc-unknown-none-elf/debug/fork"
+    .incbin "target/riscv64gc-unknown-none-elf/debug/affinity"
 bin_0_end:
 
     .section .data
@@ -45,7 +96,7 @@ bin_0_end:
     .global bin_1_end
     .align 3
 bin_1_start:
-    .incbin "target/riscv64gc-unknown-none-elf/debug/hello_world"
+    .incbin "target/riscv64gc-unknown-none-elf/debug/aslr"
 bin_1_end:
 
     .section .data
@@ -53,7 +104,7 @@ bin_1_end:
     .global bin_2_end
     .align 3
 bin_2_start:
-    .incbin "target/riscv64gc-unknown-none-elf/debug/init"
+    .incbin "target/riscv64gc-unknown-none-elf/debug/auxiliary_vector"
 bin_2_end:
 
     .section .data
@@ -61,7 +112,7 @@ bin_2_end:
     .global bin_3_end
     .align 3
 bin_3_start:
-    .incbin "target/riscv64gc-unknown-none-elf/debug/page_fault"
+    .incbin "target/riscv64gc-unknown-none-elf/debug/bad_pointer"
 bin_3_end:
 
     .section .data
@@ -69,7 +120,7 @@ bin_3_end:
     .global bin_4_end
     .align 3
 bin_4_start:
-    .incbin "target/riscv64gc-unknown-none-elf/debug/privileged_instruction"
+    .incbin "target/riscv64gc-unknown-none-elf/debug/cpu_time"
 bin_4_end:
 
     .section .data
@@ -77,7 +128,7 @@ bin_4_end:
     .global bin_5_end
     .align 3
 bin_5_start:
-    .incbin "target/riscv64gc-unknown-none-elf/debug/shell"
+    .incbin "target/riscv64gc-unknown-none-elf/debug/dynamic_executable"
 bin_5_end:
 
     .section .data
@@ -85,5 +136,141 @@ bin_5_end:
     .global bin_6_end
     .align 3
 bin_6_start:
-    .incbin "target/riscv64gc-unknown-none-elf/debug/sleep"
+    .incbin "target/riscv64gc-unknown-none-elf/debug/fair_share"
 bin_6_end:
+
+    .section .data
+    .global bin_7_start
+    .global bin_7_end
+    .align 3
+bin_7_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/fork"
+bin_7_end:
+
+    .section .data
+    .global bin_8_start
+    .global bin_8_end
+    .align 3
+bin_8_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/hello_world"
+bin_8_end:
+
+    .section .data
+    .global bin_9_start
+    .global bin_9_end
+    .align 3
+bin_9_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/huge_page"
+bin_9_end:
+
+    .section .data
+    .global bin_10_start
+    .global bin_10_end
+    .align 3
+bin_10_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/init"
+bin_10_end:
+
+    .section .data
+    .global bin_11_start
+    .global bin_11_end
+    .align 3
+bin_11_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/interpreter"
+bin_11_end:
+
+    .section .data
+    .global bin_12_start
+    .global bin_12_end
+    .align 3
+bin_12_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/out_of_memory"
+bin_12_end:
+
+    .section .data
+    .global bin_13_start
+    .global bin_13_end
+    .align 3
+bin_13_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/page_fault"
+bin_13_end:
+
+    .section .data
+    .global bin_14_start
+    .global bin_14_end
+    .align 3
+bin_14_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/priority"
+bin_14_end:
+
+    .section .data
+    .global bin_15_start
+    .global bin_15_end
+    .align 3
+bin_15_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/privileged_instruction"
+bin_15_end:
+
+    .section .data
+    .global bin_16_start
+    .global bin_16_end
+    .align 3
+bin_16_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/realtime"
+bin_16_end:
+
+    .section .data
+    .global bin_17_start
+    .global bin_17_end
+    .align 3
+bin_17_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/shared_memory"
+bin_17_end:
+
+    .section .data
+    .global bin_18_start
+    .global bin_18_end
+    .align 3
+bin_18_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/shell"
+bin_18_end:
+
+    .section .data
+    .global bin_19_start
+    .global bin_19_end
+    .align 3
+bin_19_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/sleep"
+bin_19_end:
+
+    .section .data
+    .global bin_20_start
+    .global bin_20_end
+    .align 3
+bin_20_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/stack_overflow"
+bin_20_end:
+
+    .section .data
+    .global bin_21_start
+    .global bin_21_end
+    .align 3
+bin_21_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/swap"
+bin_21_end:
+
+    .section .data
+    .global bin_22_start
+    .global bin_22_end
+    .align 3
+bin_22_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/syscall_benchmark"
+bin_22_end:
+
+    .section .data
+    .global bin_23_start
+    .global bin_23_end
+    .align 3
+bin_23_start:
+    .incbin "target/riscv64gc-unknown-none-elf/debug/write_xor_execute"
+bin_23_end:
@@ -0,0 +1,146 @@
+//! The `boot_args` module reads the kernel command line, which the bootloader passes in the
+//! `bootargs` property of the `/chosen` node of the device tree, such as the `-append` option of
+//! QEMU. The command line is a list of `key=value` options separated by spaces, such as
+//! `scheduler=fair`.
+//!
+//! The device tree lies in the memory that the frame allocator manages, so the command line is
+//! copied to a static buffer before the memory is reused.
+
+use alloc::string::{String, ToString};
+use core::{slice, str};
+
+use crate::sync::Mutex;
+
+/// The maximum length of the command line, beyond which the options are ignored.
+const BOOT_ARGS_LIMIT: usize = 256;
+
+/// The magic number at the start of a flattened device tree.
+const FDT_MAGIC: u32 = 0xd00dfeed;
+
+/// The tokens of the structure block of a flattened device tree.
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+
+/// The `BootArgs` struct holds a copy of the command line.
+struct BootArgs {
+    buffer: [u8; BOOT_ARGS_LIMIT],
+    length: usize,
+}
+
+static BOOT_ARGS: Mutex<BootArgs> = Mutex::new(BootArgs {
+    buffer: [0; BOOT_ARGS_LIMIT],
+    length: 0,
+});
+
+/// The `DeviceTree` struct represents a flattened device tree in memory, whose fields are stored
+/// in big-endian.
+struct DeviceTree {
+    bytes: &'static [u8],
+}
+
+impl DeviceTree {
+    /// Returns the [DeviceTree] at `address`, or `None` if no valid device tree is there.
+    fn new(address: usize) -> Option<Self> {
+        if address == 0 || address % 4 != 0 {
+            return None;
+        }
+
+        let header = unsafe { slice::from_raw_parts(address as *const u8, 8) };
+        let device_tree = Self { bytes: header };
+        if device_tree.read_u32(0)? != FDT_MAGIC {
+            return None;
+        }
+
+        let total_size = device_tree.read_u32(4)? as usize;
+        Some(Self {
+            bytes: unsafe { slice::from_raw_parts(address as *const u8, total_size) },
+        })
+    }
+
+    fn read_u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes.get(offset..offset.checked_add(4)?)?;
+        Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Returns the null-terminated string at `offset`.
+    fn read_str(&self, offset: usize) -> Option<&'static [u8]> {
+        let bytes = self.bytes.get(offset..)?;
+        let length = bytes.iter().position(|byte| *byte == 0)?;
+        Some(&bytes[..length])
+    }
+
+    /// Returns the value of a property of a node directly below the root node.
+    fn property(&self, node_name: &[u8], property_name: &[u8]) -> Option<&'static [u8]> {
+        let structure_offset = self.read_u32(8)? as usize;
+        let strings_offset = self.read_u32(12)? as usize;
+
+        let mut offset = structure_offset;
+        let mut depth: usize = 0;
+        let mut in_node = false;
+        loop {
+            let token = self.read_u32(offset)?;
+            offset += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name = self.read_str(offset)?;
+                    offset = align_up(offset + name.len() + 1);
+                    depth += 1;
+                    // A node name might be followed by its unit address, such as `chosen@0`
+                    in_node =
+                        depth == 2 && name.split(|byte| *byte == b'@').next() == Some(node_name);
+                }
+                FDT_END_NODE => {
+                    depth = depth.checked_sub(1)?;
+                    in_node = false;
+                }
+                FDT_PROP => {
+                    let length = self.read_u32(offset)? as usize;
+                    let name_offset = self.read_u32(offset + 4)? as usize;
+                    let value_offset = offset + 8;
+                    offset = align_up(value_offset + length);
+                    if in_node && self.read_str(strings_offset + name_offset)? == property_name {
+                        return self.bytes.get(value_offset..value_offset + length);
+                    }
+                }
+                FDT_NOP => (),
+                // The `FDT_END` token or an unknown token ends the search
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// Rounds `offset` up to the alignment of the tokens.
+fn align_up(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// Copies the command line from the device tree at `device_tree_address`, which is passed in `a1`
+/// by the SBI implementation. The command line is left empty if there is no valid device tree.
+pub fn init(device_tree_address: usize) {
+    let Some(bootargs) = DeviceTree::new(device_tree_address)
+        .and_then(|device_tree| device_tree.property(b"chosen", b"bootargs"))
+    else {
+        return;
+    };
+
+    let bootargs = bootargs.split(|byte| *byte == 0).next().unwrap_or(bootargs);
+    let length = bootargs.len().min(BOOT_ARGS_LIMIT);
+    let mut boot_args = BOOT_ARGS.lock();
+    boot_args.buffer[..length].copy_from_slice(&bootargs[..length]);
+    boot_args.length = length;
+}
+
+/// Returns the value of the option with a specific key in the command line, or `None` if the
+/// option is not given.
+pub fn get(key: &str) -> Option<String> {
+    let boot_args = BOOT_ARGS.lock();
+    let command_line = str::from_utf8(&boot_args.buffer[..boot_args.length]).ok()?;
+    command_line
+        .split_ascii_whitespace()
+        .filter_map(|option| option.split_once('='))
+        .find(|(option_key, _)| *option_key == key)
+        .map(|(_, value)| value.to_string())
+}
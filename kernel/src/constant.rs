@@ -1,20 +1,71 @@
 //! The `constant` module defines several parameters for the kernel.
 
-/// The size of the kernel heap, in bytes.
-pub const KERNEL_HEAP_SIZE: usize = 4096 * 768;
+/// The initial size of the kernel heap, in bytes.
+pub const KERNEL_HEAP_SIZE: usize = 4096 * 64;
 
-/// The size of the user stack, in bytes.
+/// The initial size of the user stack, in bytes.
 pub const USER_STACK_SIZE: usize = 4096 * 2;
 
+/// The size of the address range reserved for the user stack of each thread, in bytes.
+/// The user stack grows on demand until it reaches this size or the `RLIMIT_STACK` of the process.
+pub const USER_STACK_LIMIT: usize = 4096 * 2048;
+
+/// The base address of the anonymous memory mappings, which is also the upper limit of the program
+/// break.
+pub const USER_MMAP_BASE: usize = 0x1000000000;
+
+/// The base address of position-independent executables before the randomization.
+pub const USER_PIE_BASE: usize = 0x40000000;
+
+/// The range of the random offset added to the base address of position-independent executables,
+/// in bytes.
+pub const ASLR_PIE_RANGE: usize = 1 << 30;
+
+/// The range of the random offset added to the start of the program break, in bytes.
+pub const ASLR_BRK_RANGE: usize = 1 << 25;
+
+/// The range of the random offset added to the base address of the anonymous memory mappings, in
+/// bytes.
+pub const ASLR_MMAP_RANGE: usize = 1 << 32;
+
+/// The range of the random offset subtracted from the base address of the user stacks, in bytes.
+pub const ASLR_STACK_RANGE: usize = 1 << 32;
+
+/// The maximum size of a shared memory object, in bytes.
+pub const SHMMAX: usize = 4096 * 256;
+
+/// The maximum number of pages of all the shared memory objects.
+pub const SHMALL: usize = 1024;
+
 /// The size of a page in memory, in bytes.
 pub const PAGE_SIZE: usize = 4096;
 
 /// The number of bits needed to represent a page size.
 pub const PAGE_SIZE_BIT: usize = 12;
 
+/// The start address of the physical memory.
+pub const MEM_START: usize = 0x80000000;
+
 /// The memory limit for the kernel, in bytes.
 pub const MEM_LIMIT: usize = 0x81000000;
 
+/// The base address of the virtio-mmio devices on the QEMU `virt` machine.
+pub const VIRTIO_MMIO_BASE: usize = 0x10001000;
+
+/// The size of the registers of each virtio-mmio device, in bytes.
+pub const VIRTIO_MMIO_SIZE: usize = 0x1000;
+
+/// The number of virtio-mmio devices on the QEMU `virt` machine.
+pub const VIRTIO_MMIO_COUNT: usize = 8;
+
+/// The number of free frames below which the kernel swaps out pages before returning to user
+/// space.
+pub const SWAP_LOW_WATERMARK: usize = 64;
+
+/// The number of free frames that the kernel swaps out pages to reach once the number of free
+/// frames drops below [SWAP_LOW_WATERMARK].
+pub const SWAP_HIGH_WATERMARK: usize = 128;
+
 /// The address of the trampoline page, which is used for switching between user and kernel space.
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 
@@ -23,3 +74,13 @@ pub const TRAP_CONTEXT_BASE: usize = usize::MAX - 256 * PAGE_SIZE + 1;
 
 /// The clock frequency of the system, in Hz.
 pub const CLOCK_FREQ: usize = 12500000;
+
+/// The maximum number of harts that the kernel supports.
+pub const MAX_HART_COUNT: usize = 8;
+
+/// The base address of the contexts of the platform-level interrupt controller, where each hart
+/// has a machine-mode context followed by a supervisor-mode context.
+pub const PLIC_CONTEXT_BASE: usize = 0x0c200000;
+
+/// The size of a context of the platform-level interrupt controller.
+pub const PLIC_CONTEXT_SIZE: usize = 0x1000;
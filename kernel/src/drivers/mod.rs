@@ -0,0 +1,7 @@
+//! The `drivers` module provides the drivers of the devices on the QEMU `virt` machine.
+
+mod plic;
+mod virtio_block;
+
+pub use plic::{handle_external_interrupt, init};
+pub use virtio_block::{IoError, VirtioBlock, SECTOR_SIZE};
@@ -0,0 +1,63 @@
+//! The `plic` module provides the driver of the platform-level interrupt controller (PLIC), which
+//! routes the interrupts of the devices to the harts.
+
+use core::ptr;
+
+use log::warn;
+
+use crate::{
+    constant::{PLIC_CONTEXT_BASE, PLIC_CONTEXT_SIZE},
+    hart,
+};
+
+/// The offset of the priority threshold register in a context.
+const THRESHOLD_OFFSET: usize = 0;
+
+/// The offset of the claim/complete register in a context.
+const CLAIM_OFFSET: usize = 4;
+
+/// Returns the address of a register of the supervisor-mode context of the current hart.
+fn context_register(offset: usize) -> *mut u32 {
+    let context = 2 * hart::hart_id() + 1;
+    (PLIC_CONTEXT_BASE + context * PLIC_CONTEXT_SIZE + offset) as *mut u32
+}
+
+/// Returns the address of the claim/complete register of the supervisor-mode context of the
+/// current hart.
+fn claim_register() -> *mut u32 {
+    context_register(CLAIM_OFFSET)
+}
+
+/// Sets the priority threshold of the supervisor-mode context of the current hart to `0`, so that
+/// every source with a nonzero priority can interrupt the hart once it is enabled. The priority of
+/// a source is left to the driver that enables it.
+pub fn init() {
+    unsafe {
+        ptr::write_volatile(context_register(THRESHOLD_OFFSET), 0);
+    }
+}
+
+/// Claims the pending interrupt with the highest priority, and returns its source ID, or `None` if
+/// no interrupt is pending.
+fn claim() -> Option<u32> {
+    match unsafe { ptr::read_volatile(claim_register()) } {
+        0 => None,
+        source => Some(source),
+    }
+}
+
+/// Signals that the interrupt from `source` has been handled.
+fn complete(source: u32) {
+    unsafe {
+        ptr::write_volatile(claim_register(), source);
+    }
+}
+
+/// Handles the pending external interrupts. No device driver enables its interrupt source yet, so
+/// an interrupt is reported and completed so that the source can interrupt again.
+pub fn handle_external_interrupt() {
+    while let Some(source) = claim() {
+        warn!("unexpected external interrupt from source {}", source);
+        complete(source);
+    }
+}
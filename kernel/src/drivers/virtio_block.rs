@@ -0,0 +1,291 @@
+//! The `virtio_block` module provides a driver for the virtio block devices on the virtio-mmio
+//! transport, which supports both the legacy and the modern interface.
+//!
+//! The driver submits one request at a time and polls the used ring until the device completes
+//! the request, so it doesn't depend on external interrupts. The buffers of the requests must be
+//! physically contiguous, such as the frames that are identically mapped in the kernel space.
+
+use core::{arch::asm, mem::size_of, ptr};
+
+use crate::{
+    constant::{PAGE_SIZE, VIRTIO_MMIO_BASE, VIRTIO_MMIO_COUNT, VIRTIO_MMIO_SIZE},
+    mem::{allocate_kernel_frames, FrameTracker, PhysicalAddress},
+};
+
+/// The size of a sector of the block device, in bytes.
+pub const SECTOR_SIZE: usize = 512;
+
+const MAGIC_VALUE: usize = 0x000;
+const VERSION: usize = 0x004;
+const DEVICE_ID: usize = 0x008;
+const DEVICE_FEATURES: usize = 0x010;
+const DEVICE_FEATURES_SEL: usize = 0x014;
+const DRIVER_FEATURES: usize = 0x020;
+const DRIVER_FEATURES_SEL: usize = 0x024;
+const GUEST_PAGE_SIZE: usize = 0x028;
+const QUEUE_SEL: usize = 0x030;
+const QUEUE_NUM_MAX: usize = 0x034;
+const QUEUE_NUM: usize = 0x038;
+const QUEUE_ALIGN: usize = 0x03c;
+const QUEUE_PFN: usize = 0x040;
+const QUEUE_READY: usize = 0x044;
+const QUEUE_NOTIFY: usize = 0x050;
+const STATUS: usize = 0x070;
+const QUEUE_DESC_LOW: usize = 0x080;
+const QUEUE_DESC_HIGH: usize = 0x084;
+const QUEUE_DRIVER_LOW: usize = 0x090;
+const QUEUE_DRIVER_HIGH: usize = 0x094;
+const QUEUE_DEVICE_LOW: usize = 0x0a0;
+const QUEUE_DEVICE_HIGH: usize = 0x0a4;
+const CONFIG: usize = 0x100;
+
+const MAGIC: u32 = 0x74726976;
+const BLOCK_DEVICE_ID: u32 = 2;
+
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+const STATUS_FEATURES_OK: u32 = 8;
+
+/// The `VIRTIO_F_VERSION_1` feature, which is bit 0 of the second word of the features.
+const FEATURE_VERSION_1: u32 = 1;
+
+const DESCRIPTOR_NEXT: u16 = 1;
+const DESCRIPTOR_WRITE: u16 = 2;
+
+const REQUEST_IN: u32 = 0;
+const REQUEST_OUT: u32 = 1;
+
+/// The number of descriptors in the virtqueue. Each request uses a chain of 3 descriptors.
+const QUEUE_SIZE: usize = 8;
+
+/// The offsets of the parts of the virtqueue and the request in the frames of the virtqueue.
+/// The used ring is aligned to a page as the legacy interface requires.
+const DESCRIPTOR_OFFSET: usize = 0;
+const AVAILABLE_OFFSET: usize = QUEUE_SIZE * size_of::<Descriptor>();
+const USED_OFFSET: usize = PAGE_SIZE;
+const HEADER_OFFSET: usize = PAGE_SIZE + PAGE_SIZE / 2;
+const STATUS_OFFSET: usize = HEADER_OFFSET + size_of::<RequestHeader>();
+
+/// The `IoError` struct is an error that indicates that the device fails to complete a request.
+#[derive(Debug)]
+pub struct IoError;
+
+#[repr(C)]
+struct Descriptor {
+    address: u64,
+    length: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct RequestHeader {
+    request_type: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+/// The `VirtioBlock` struct represents a virtio block device, whose registers start at `base`.
+/// The virtqueue, the header, and the status of the request are stored in the `queue` frames.
+pub struct VirtioBlock {
+    base: usize,
+    queue: FrameTracker,
+    sector_count: usize,
+    available_index: u16,
+}
+
+impl VirtioBlock {
+    /// Returns the first virtio block device on the virtio-mmio transport, or `None` if no block
+    /// device is attached.
+    pub fn probe() -> Option<Self> {
+        (0..VIRTIO_MMIO_COUNT)
+            .map(|index| VIRTIO_MMIO_BASE + index * VIRTIO_MMIO_SIZE)
+            .find_map(Self::new)
+    }
+
+    /// Initializes the block device whose registers start at `base`, which follows the
+    /// initialization sequence in the virtio specification.
+    fn new(base: usize) -> Option<Self> {
+        let mut device = Self {
+            base,
+            queue: allocate_kernel_frames(1)?,
+            sector_count: 0,
+            available_index: 0,
+        };
+
+        let version = device.read_register(VERSION);
+        if device.read_register(MAGIC_VALUE) != MAGIC
+            || device.read_register(DEVICE_ID) != BLOCK_DEVICE_ID
+            || !(1..=2).contains(&version)
+        {
+            return None;
+        }
+
+        device.write_register(STATUS, 0);
+        let mut status = STATUS_ACKNOWLEDGE | STATUS_DRIVER;
+        device.write_register(STATUS, status);
+
+        // No feature of the block device is required, and the modern interface requires the
+        // `VIRTIO_F_VERSION_1` feature
+        device.write_register(DRIVER_FEATURES_SEL, 0);
+        device.write_register(DRIVER_FEATURES, 0);
+        if version == 2 {
+            device.write_register(DEVICE_FEATURES_SEL, 1);
+            if device.read_register(DEVICE_FEATURES) & FEATURE_VERSION_1 == 0 {
+                return None;
+            }
+            device.write_register(DRIVER_FEATURES_SEL, 1);
+            device.write_register(DRIVER_FEATURES, FEATURE_VERSION_1);
+
+            status |= STATUS_FEATURES_OK;
+            device.write_register(STATUS, status);
+            if device.read_register(STATUS) & STATUS_FEATURES_OK == 0 {
+                return None;
+            }
+        }
+
+        device.write_register(QUEUE_SEL, 0);
+        if (device.read_register(QUEUE_NUM_MAX) as usize) < QUEUE_SIZE {
+            return None;
+        }
+        device.write_register(QUEUE_NUM, QUEUE_SIZE as u32);
+
+        let queue_address = usize::from(PhysicalAddress::from(device.queue.frame_number()));
+        if version == 1 {
+            device.write_register(GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+            device.write_register(QUEUE_ALIGN, PAGE_SIZE as u32);
+            device.write_register(QUEUE_PFN, (queue_address / PAGE_SIZE) as u32);
+        } else {
+            for (low, high, offset) in [
+                (QUEUE_DESC_LOW, QUEUE_DESC_HIGH, DESCRIPTOR_OFFSET),
+                (QUEUE_DRIVER_LOW, QUEUE_DRIVER_HIGH, AVAILABLE_OFFSET),
+                (QUEUE_DEVICE_LOW, QUEUE_DEVICE_HIGH, USED_OFFSET),
+            ] {
+                let address = queue_address + offset;
+                device.write_register(low, address as u32);
+                device.write_register(high, (address >> 32) as u32);
+            }
+            device.write_register(QUEUE_READY, 1);
+        }
+
+        status |= STATUS_DRIVER_OK;
+        device.write_register(STATUS, status);
+
+        let capacity_low = device.read_register(CONFIG) as usize;
+        let capacity_high = device.read_register(CONFIG + 4) as usize;
+        device.sector_count = capacity_high << 32 | capacity_low;
+        Some(device)
+    }
+
+    /// Returns the number of sectors of the block device.
+    pub fn sector_count(&self) -> usize {
+        self.sector_count
+    }
+
+    /// Reads the sectors that start at `sector` to `buffer`, whose length must be a multiple of
+    /// [SECTOR_SIZE].
+    pub fn read(&mut self, sector: usize, buffer: &mut [u8]) -> Result<(), IoError> {
+        self.submit(
+            REQUEST_IN,
+            sector,
+            buffer.as_mut_ptr() as usize,
+            buffer.len(),
+        )
+    }
+
+    /// Writes `buffer` to the sectors that start at `sector`, whose length must be a multiple of
+    /// [SECTOR_SIZE].
+    pub fn write(&mut self, sector: usize, buffer: &[u8]) -> Result<(), IoError> {
+        self.submit(REQUEST_OUT, sector, buffer.as_ptr() as usize, buffer.len())
+    }
+
+    /// Submits a request with a chain of the header, the buffer, and the status, and waits for the
+    /// device to complete the request.
+    fn submit(
+        &mut self,
+        request_type: u32,
+        sector: usize,
+        buffer_address: usize,
+        length: usize,
+    ) -> Result<(), IoError> {
+        assert_eq!(length % SECTOR_SIZE, 0);
+        if sector + length / SECTOR_SIZE > self.sector_count {
+            return Err(IoError);
+        }
+
+        let queue_address = usize::from(PhysicalAddress::from(self.queue.frame_number()));
+        unsafe {
+            ptr::write_volatile(
+                (queue_address + HEADER_OFFSET) as *mut RequestHeader,
+                RequestHeader {
+                    request_type,
+                    reserved: 0,
+                    sector: sector as u64,
+                },
+            );
+            ptr::write_volatile((queue_address + STATUS_OFFSET) as *mut u8, u8::MAX);
+
+            let buffer_flags = if request_type == REQUEST_IN {
+                DESCRIPTOR_NEXT | DESCRIPTOR_WRITE
+            } else {
+                DESCRIPTOR_NEXT
+            };
+            let descriptor_list = (queue_address + DESCRIPTOR_OFFSET) as *mut Descriptor;
+            for (index, descriptor) in [
+                (
+                    queue_address + HEADER_OFFSET,
+                    size_of::<RequestHeader>(),
+                    DESCRIPTOR_NEXT,
+                ),
+                (buffer_address, length, buffer_flags),
+                (queue_address + STATUS_OFFSET, 1, DESCRIPTOR_WRITE),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let (address, length, flags) = descriptor;
+                ptr::write_volatile(
+                    descriptor_list.add(index),
+                    Descriptor {
+                        address: address as u64,
+                        length: length as u32,
+                        flags,
+                        next: index as u16 + 1,
+                    },
+                );
+            }
+
+            // The available ring consists of `flags`, `index`, and the ring of descriptor heads
+            let available_ring = (queue_address + AVAILABLE_OFFSET) as *mut u16;
+            let ring_index = self.available_index as usize % QUEUE_SIZE;
+            ptr::write_volatile(available_ring.add(2 + ring_index), 0);
+            asm!("fence");
+            self.available_index = self.available_index.wrapping_add(1);
+            ptr::write_volatile(available_ring.add(1), self.available_index);
+            asm!("fence");
+            self.write_register(QUEUE_NOTIFY, 0);
+
+            // The used ring consists of `flags`, `index`, and the ring of used elements
+            let used_ring = (queue_address + USED_OFFSET) as *const u16;
+            while ptr::read_volatile(used_ring.add(1)) != self.available_index {
+                core::hint::spin_loop();
+            }
+            asm!("fence");
+
+            if ptr::read_volatile((queue_address + STATUS_OFFSET) as *const u8) == 0 {
+                Ok(())
+            } else {
+                Err(IoError)
+            }
+        }
+    }
+
+    fn read_register(&self, offset: usize) -> u32 {
+        unsafe { ptr::read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write_register(&self, offset: usize, value: u32) {
+        unsafe { ptr::write_volatile((self.base + offset) as *mut u32, value) }
+    }
+}
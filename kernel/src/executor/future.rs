@@ -12,15 +12,19 @@ use riscv::register::{
     scause,
     scause::{Exception, Interrupt},
     stval,
+    time,
 };
 
 use crate::{
     constant::TRAMPOLINE,
+    drivers,
     executor,
     executor::TrapContext,
-    mem::VirtualAddress,
+    hart,
+    mem::{OutOfMemory, PageFaultError, VirtualAddress},
+    random,
     syscall::SystemCall,
-    task::Thread,
+    task::{self, Signal, StackGrowth, Thread},
     timer,
 };
 
@@ -49,27 +53,65 @@ async fn thread_loop(thread: Arc<Thread>) {
     };
 
     loop {
+        let process = thread.process();
+        let mut process_state = process.state().lock();
+        if process_state.is_killed() {
+            // The user segments that were kept for the threads in a system call are released once
+            // every thread has returned from it
+            if !process.is_in_system_call() {
+                process_state.release_killed();
+            }
+            drop(process_state);
+            thread.exit(Signal::Kill.exit_code());
+            break;
+        }
+        drop(process_state);
+        drop(process);
+
+        // Accounts the timer ticks taken in the kernel, which is a preemption point of the thread
+        if hart::take_pending_tick() && executor::tick(thread.task_info()) {
+            yield_now().await;
+        }
+
+        // Swaps out pages in the background once the number of free frames runs low
+        task::wake_reclaimer();
+
         let trap_context = thread.state().lock().user_trap_context_mut();
-        _enter_user_space(trap_context, thread.satp());
+        let user_space_entry_time = time::read();
+        executor::disable_kernel_trap();
+        _enter_user_space(trap_context, thread.activate());
 
+        // The trap registers are read before a trap taken in the kernel can overwrite them
         let scause = scause::read();
         let stval = stval::read();
+        executor::enable_kernel_trap();
+        thread
+            .task_info()
+            .add_user_time(time::read() - user_space_entry_time);
+
         let control_flow = match scause.cause() {
             scause::Trap::Exception(Exception::UserEnvCall) => {
-                SystemCall::new(&thread).execute().await
+                // The OOM killer keeps the user segments of the process while a system call might
+                // access them
+                thread.process().enter_system_call();
+                let control_flow = SystemCall::new(&thread).execute().await;
+                thread.process().leave_system_call();
+                control_flow
             }
+            scause::Trap::Exception(Exception::StorePageFault) => swap_in(&thread, stval)
+                .unwrap_or_else(|| match thread.clone_frame(VirtualAddress::from(stval)) {
+                    Ok(true) => ControlFlow::Continue,
+                    Ok(false) => grow_user_stack(&thread, stval),
+                    Err(OutOfMemory) => reclaim_for_fault("copy-on-write", stval),
+                }),
             scause::Trap::Exception(Exception::LoadPageFault) => {
-                error!("page fault at {:#x}", stval);
-                ControlFlow::Exit(1)
+                swap_in(&thread, stval).unwrap_or_else(|| grow_user_stack(&thread, stval))
             }
-            scause::Trap::Exception(Exception::StorePageFault) => {
-                if thread.clone_frame(VirtualAddress::from(stval)) {
-                    ControlFlow::Continue
-                } else {
-                    error!("page fault at {:#x}", stval);
+            scause::Trap::Exception(Exception::InstructionPageFault) => swap_in(&thread, stval)
+                .unwrap_or_else(|| {
+                    error!("instruction page fault at {:#x}", stval);
                     ControlFlow::Exit(1)
-                }
-            }
+                }),
             scause::Trap::Exception(Exception::IllegalInstruction) => {
                 error!("illegal instruction");
                 ControlFlow::Exit(1)
@@ -78,9 +120,18 @@ async fn thread_loop(thread: Arc<Thread>) {
                 error!("misaligned instruction");
                 ControlFlow::Exit(1)
             }
-            scause::Trap::Interrupt(Interrupt::SupervisorTimer) => {
+            scause::Trap::Interrupt(Interrupt::SupervisorTimer) => hart::handle_interrupt(|| {
+                random::add_entropy(time::read());
                 timer::set_trigger();
-                ControlFlow::Yield
+                if executor::tick(thread.task_info()) {
+                    ControlFlow::Yield
+                } else {
+                    ControlFlow::Continue
+                }
+            }),
+            scause::Trap::Interrupt(Interrupt::SupervisorExternal) => {
+                hart::handle_interrupt(drivers::handle_external_interrupt);
+                ControlFlow::Continue
             }
             _ => {
                 panic!("unsupported trap {:?}", scause.cause())
@@ -98,10 +149,52 @@ async fn thread_loop(thread: Arc<Thread>) {
     }
 }
 
+/// Handles a page fault at `stval` by reading the page back from the swap area. Returns `None` if
+/// the page has not been swapped out. The thread is killed with `SIGBUS` if the swap device fails
+/// to read the page.
+fn swap_in(thread: &Thread, stval: usize) -> Option<ControlFlow> {
+    match thread.swap_in(VirtualAddress::from(stval)) {
+        Ok(true) => Some(ControlFlow::Continue),
+        Ok(false) => None,
+        Err(PageFaultError::OutOfMemory) => Some(reclaim_for_fault("swap-in", stval)),
+        Err(PageFaultError::Io) => {
+            error!("failed to read the page at {:#x} from the swap area", stval);
+            Some(ControlFlow::Exit(Signal::Bus.exit_code()))
+        }
+    }
+}
+
+/// Handles a page fault at `stval` by extending the user stack of the thread.
+fn grow_user_stack(thread: &Thread, stval: usize) -> ControlFlow {
+    match thread.grow_user_stack(VirtualAddress::from(stval)) {
+        StackGrowth::Grown => ControlFlow::Continue,
+        StackGrowth::Overflow => {
+            error!("stack overflow at {:#x}", stval);
+            ControlFlow::Exit(Signal::SegmentationFault.exit_code())
+        }
+        StackGrowth::Outside => {
+            error!("page fault at {:#x}", stval);
+            ControlFlow::Exit(1)
+        }
+        StackGrowth::OutOfMemory => reclaim_for_fault("stack growth", stval),
+    }
+}
+
+/// Handles a page fault at `stval` that has run out of frames on `operation`. The fault is retried
+/// if the OOM killer reclaims frames, which might kill the current process, and the thread is
+/// killed otherwise.
+fn reclaim_for_fault(operation: &str, stval: usize) -> ControlFlow {
+    if task::reclaim_for_fault() {
+        return ControlFlow::Continue;
+    }
+
+    error!("out of memory on {} at {:#x}", operation, stval);
+    ControlFlow::Exit(Signal::Kill.exit_code())
+}
+
 pub fn spawn_thread(thread: Arc<Thread>) {
-    let (runnable, task) = executor::spawn(thread_loop(thread));
-    runnable.schedule();
-    task.detach();
+    let task_info = thread.task_info().clone();
+    executor::spawn(thread_loop(thread), task_info).detach();
 }
 
 #[naked]
@@ -160,8 +253,8 @@ unsafe extern "C" fn _enter_kernel_space() {
         // Reads the stack pointer from `trap_context.kernel_stack`
         "ld sp, 34 * 8(sp)",
         // Writes the address of the page table of the kernel to satp
+        // The TLB is not flushed because the entries of the kernel are tagged with its own ASID
         "csrw satp, t3",
-        "sfence.vma",
         // Reads the return address, global pointer, thread pointer from the kernel stack
         "ld ra, 0 * 8(sp)",
         "ld gp, 1 * 8(sp)",
@@ -212,8 +305,8 @@ unsafe extern "C" fn _enter_user_space(trap_context: &mut TrapContext, user_satp
         "sd s11, 14 * 8(sp)",
         // Writes the address of the page table of the process to satp
         // and read the address of the page table of the kernel to a1
+        // The TLB is not flushed because the entries of the process are tagged with its own ASID
         "csrrw a1, satp, a1",
-        "sfence.vma",
         // Stores the stack pointer to `trap_context.kernel_stack`
         // and move the stack pointer to `trap_context`
         "sd sp, 34 * 8(a0)",
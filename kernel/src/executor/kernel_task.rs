@@ -0,0 +1,163 @@
+//! The `kernel_task` module provides the kernel tasks, which are long-running asynchronous tasks
+//! that run in the kernel on behalf of no process, such as the page reclaimer.
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use async_task::Task;
+
+use crate::{
+    executor::{self, TaskInfo},
+    sync::Mutex,
+};
+
+/// The `Cancellation` struct is shared between a kernel task and its [JoinHandle], which wakes the
+/// task when it is cancelled.
+struct Cancellation {
+    cancelled: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Cancellation {
+    fn new() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            waker: Mutex::new(None),
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    fn register(&self, waker: &Waker) {
+        *self.waker.lock() = Some(waker.clone());
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        let waker = self.waker.lock().take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// The `KernelTask` struct represents an entry of the [KERNEL_TASK_MAP].
+struct KernelTask {
+    name: String,
+}
+
+/// The kernel tasks that haven't completed or been cancelled, indexed by the IDs of their
+/// [TaskInfo].
+static KERNEL_TASK_MAP: Mutex<BTreeMap<usize, KernelTask>> = Mutex::new(BTreeMap::new());
+
+/// Returns the IDs and the names of the kernel tasks that haven't completed or been cancelled.
+pub fn kernel_task_list() -> Vec<(usize, String)> {
+    KERNEL_TASK_MAP
+        .lock()
+        .iter()
+        .map(|(&id, kernel_task)| (id, kernel_task.name.clone()))
+        .collect()
+}
+
+/// The `KernelTaskFuture` struct wraps the future of a kernel task, which completes with `None`
+/// once the task is cancelled. The task is removed from the [KERNEL_TASK_MAP] when the future is
+/// dropped, either because it has completed or because it has been cancelled.
+struct KernelTaskFuture<F: Future> {
+    id: usize,
+    future: Pin<Box<F>>,
+    cancellation: Arc<Cancellation>,
+}
+
+impl<F: Future> Future for KernelTaskFuture<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        // The waker is registered before checking the flag so that a cancellation is never missed
+        self.cancellation.register(context.waker());
+        if self.cancellation.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        self.future.as_mut().poll(context).map(Some)
+    }
+}
+
+impl<F: Future> Drop for KernelTaskFuture<F> {
+    fn drop(&mut self) {
+        KERNEL_TASK_MAP.lock().remove(&self.id);
+    }
+}
+
+/// The `JoinHandle` struct is a handle to a kernel task, which can be awaited to join the task.
+/// The output is `None` if the task has been cancelled. Dropping the handle detaches the task,
+/// which keeps running in the background.
+pub struct JoinHandle<T> {
+    task: Option<Task<Option<T>, Arc<TaskInfo>>>,
+    cancellation: Arc<Cancellation>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Cancels the task, whose future is dropped the next time it is scheduled. The task is woken
+    /// if it is waiting for an event.
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let task = self.get_mut().task.as_mut().unwrap();
+        Pin::new(task).poll(context)
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.detach();
+        }
+    }
+}
+
+/// Spawns a kernel task with the given name that runs `future`, and registers it in the
+/// [KERNEL_TASK_MAP] until it completes or is cancelled.
+pub fn spawn_kernel_task<F>(name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let task_info = Arc::new(TaskInfo::new(None));
+    let id = task_info.id();
+    let cancellation = Arc::new(Cancellation::new());
+
+    KERNEL_TASK_MAP.lock().insert(
+        id,
+        KernelTask {
+            name: name.to_string(),
+        },
+    );
+    let future = KernelTaskFuture {
+        id,
+        future: Box::pin(future),
+        cancellation: cancellation.clone(),
+    };
+
+    JoinHandle {
+        task: Some(executor::spawn(future, task_info)),
+        cancellation,
+    }
+}
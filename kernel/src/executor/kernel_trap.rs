@@ -0,0 +1,285 @@
+//! The `kernel_trap` module handles the exceptions and interrupts taken while the kernel is
+//! running in supervisor mode. The `stvec` register points to [_kernel_trap] while the kernel is
+//! running, and to the `_enter_kernel_space` function in the trampoline while a user thread is
+//! running, which is switched by [enable_kernel_trap] and [disable_kernel_trap].
+//!
+//! A timer interrupt can't preempt the running task in the middle of a poll, so the tick is
+//! deferred to the next preemption point. The only preemption point is the top of the loop of a
+//! user thread, before it returns to user space, so the kernel is not preemptible: a long system
+//! call or kernel task keeps the hart until it returns or awaits, and the ticks taken meanwhile
+//! are accounted as a single tick. A fault at an instruction listed in the fixup table, such
+//! as the copy of user memory, resumes at its fixup address. Any other fault is a bug in the
+//! kernel, which panics with a dump of the registers.
+//!
+//! A trap taken while the kernel stack has less than a page left for the [KernelTrapFrame] and
+//! [kernel_trap_handler], or while the stack pointer lies outside the kernel stack, can't save its
+//! context on the kernel stack. The trap switches to a separate trap stack instead and panics with
+//! the stack pointer, which makes a kernel stack overflow diagnosable instead of corrupting the
+//! memory below the stack.
+
+use core::{arch::asm, fmt, slice};
+
+use riscv::register::{
+    scause::{self, Exception, Interrupt, Trap},
+    sepc,
+    sie,
+    sstatus,
+    stval,
+    stvec,
+    time,
+    utvec::TrapMode,
+};
+
+use crate::{constant::TRAMPOLINE, drivers, hart, random, timer};
+
+/// The ABI names of the general-purpose registers.
+const REGISTER_NAME_LIST: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// The `KernelTrapFrame` struct holds the context of the kernel when it takes a trap, which is
+/// saved on the kernel stack by [_kernel_trap].
+#[repr(C)]
+struct KernelTrapFrame {
+    register: [usize; 32],
+    sstatus: usize,
+    sepc: usize,
+}
+
+impl fmt::Display for KernelTrapFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "sepc: {:#018x} sstatus: {:#018x}",
+            self.sepc, self.sstatus
+        )?;
+        for (index, (name, value)) in REGISTER_NAME_LIST
+            .iter()
+            .zip(self.register.iter())
+            .enumerate()
+        {
+            write!(f, "{:>4}: {:#018x}", name, value)?;
+            if index % 4 == 3 {
+                writeln!(f)?;
+            } else {
+                write!(f, " ")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `FixupEntry` struct represents an entry of the fixup table, which is placed in the
+/// `.fixup_table` section by the functions that might fault on purpose.
+#[repr(C)]
+struct FixupEntry {
+    instruction: usize,
+    fixup: usize,
+}
+
+/// Returns the fixup address of the instruction at `address`, or `None` if the instruction is not
+/// expected to fault.
+fn search_fixup(address: usize) -> Option<usize> {
+    extern "C" {
+        /// The `fixup_table_start` is a symbol declared in the `src/linker.ld`,
+        /// which represent the start address of the fixup table.
+        fn fixup_table_start();
+        /// The `fixup_table_end` is a symbol declared in the `src/linker.ld`,
+        /// which represent the end address of the fixup table.
+        fn fixup_table_end();
+    }
+
+    let entry_count = (fixup_table_end as usize - fixup_table_start as usize)
+        / core::mem::size_of::<FixupEntry>();
+    let fixup_table =
+        unsafe { slice::from_raw_parts(fixup_table_start as *const FixupEntry, entry_count) };
+    fixup_table
+        .iter()
+        .find(|entry| entry.instruction == address)
+        .map(|entry| entry.fixup)
+}
+
+/// Points the `stvec` to [_kernel_trap] and enables the interrupts in supervisor mode, which is
+/// called when the kernel starts running and after a user thread traps into the kernel.
+pub fn enable_kernel_trap() {
+    unsafe {
+        stvec::write(_kernel_trap as usize, TrapMode::Direct);
+        sstatus::set_sie();
+    }
+}
+
+/// Disables the interrupts in supervisor mode and points the `stvec` to the `_enter_kernel_space`
+/// function in the trampoline, which is called before returning to user space. The interrupts are
+/// disabled first, since a trap taken in the kernel can't be handled by the trampoline.
+pub fn disable_kernel_trap() {
+    unsafe {
+        sstatus::clear_sie();
+        stvec::write(TRAMPOLINE, TrapMode::Direct);
+    }
+}
+
+/// Configures the interrupt controller, enables the external interrupts, and starts handling the
+/// traps taken in the kernel.
+pub fn init() {
+    drivers::init();
+    unsafe {
+        sie::set_sext();
+    }
+    enable_kernel_trap();
+}
+
+/// Handles a trap taken in the kernel, whose context is saved in `frame`.
+extern "C" fn kernel_trap_handler(frame: &mut KernelTrapFrame) {
+    let scause = scause::read();
+    let stval = stval::read();
+    match scause.cause() {
+        Trap::Interrupt(Interrupt::SupervisorTimer) => hart::handle_interrupt(|| {
+            random::add_entropy(time::read());
+            timer::set_trigger();
+            hart::defer_tick();
+        }),
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            hart::handle_interrupt(drivers::handle_external_interrupt)
+        }
+        cause @ Trap::Exception(
+            Exception::LoadFault
+            | Exception::StoreFault
+            | Exception::LoadPageFault
+            | Exception::StorePageFault,
+        ) => match search_fixup(frame.sepc) {
+            Some(fixup) => frame.sepc = fixup,
+            None => unhandled_trap(cause, stval, frame),
+        },
+        cause => unhandled_trap(cause, stval, frame),
+    }
+}
+
+/// Panics on a trap that the kernel doesn't expect, with a dump of the registers.
+fn unhandled_trap(cause: Trap, stval: usize, frame: &KernelTrapFrame) -> ! {
+    panic!(
+        "unhandled trap {:?} in the kernel, stval: {:#x}\n{}",
+        cause, stval, frame
+    )
+}
+
+/// Panics on a trap taken while the kernel stack has overflowed, which runs on the trap stack.
+/// `stack_pointer` is the stack pointer when the trap was taken.
+extern "C" fn kernel_stack_overflow(stack_pointer: usize) -> ! {
+    panic!(
+        "kernel stack overflow, sp: {:#x}, trap {:?} at sepc: {:#x}, stval: {:#x}",
+        stack_pointer,
+        scause::read().cause(),
+        sepc::read(),
+        stval::read()
+    )
+}
+
+/// The entry of the traps taken in the kernel, which saves the context of the kernel in a
+/// [KernelTrapFrame] on the kernel stack, calls [kernel_trap_handler], and restores the context.
+#[naked]
+#[repr(align(4))]
+unsafe extern "C" fn _kernel_trap() {
+    asm!(
+        // Checks that the stack pointer lies within the kernel stack and leaves a page for the
+        // trap, where t0 is saved to sscratch, which is only used while a user thread is running
+        "csrw sscratch, t0",
+        "la t0, boot_stack_bottom + 4096",
+        "bltu sp, t0, 1f",
+        "la t0, boot_stack_top",
+        "bgtu sp, t0, 1f",
+        "csrr t0, sscratch",
+        // Allocates a `KernelTrapFrame` on the kernel stack
+        "addi sp, sp, -34 * 8",
+        // Stores the registers to `frame.register`, where x0 is stored as zero for the dump
+        "sd zero, 0 * 8(sp)",
+        "sd ra, 1 * 8(sp)",
+        "sd gp, 3 * 8(sp)",
+        "sd tp, 4 * 8(sp)",
+        "sd t0, 5 * 8(sp)",
+        "sd t1, 6 * 8(sp)",
+        "sd t2, 7 * 8(sp)",
+        "sd s0, 8 * 8(sp)",
+        "sd s1, 9 * 8(sp)",
+        "sd a0, 10 * 8(sp)",
+        "sd a1, 11 * 8(sp)",
+        "sd a2, 12 * 8(sp)",
+        "sd a3, 13 * 8(sp)",
+        "sd a4, 14 * 8(sp)",
+        "sd a5, 15 * 8(sp)",
+        "sd a6, 16 * 8(sp)",
+        "sd a7, 17 * 8(sp)",
+        "sd s2, 18 * 8(sp)",
+        "sd s3, 19 * 8(sp)",
+        "sd s4, 20 * 8(sp)",
+        "sd s5, 21 * 8(sp)",
+        "sd s6, 22 * 8(sp)",
+        "sd s7, 23 * 8(sp)",
+        "sd s8, 24 * 8(sp)",
+        "sd s9, 25 * 8(sp)",
+        "sd s10, 26 * 8(sp)",
+        "sd s11, 27 * 8(sp)",
+        "sd t3, 28 * 8(sp)",
+        "sd t4, 29 * 8(sp)",
+        "sd t5, 30 * 8(sp)",
+        "sd t6, 31 * 8(sp)",
+        // Stores the stack pointer before the trap to `frame.register`
+        "addi t0, sp, 34 * 8",
+        "sd t0, 2 * 8(sp)",
+        // Stores sstatus and sepc to `frame.sstatus` and `frame.sepc`
+        "csrr t0, sstatus",
+        "sd t0, 32 * 8(sp)",
+        "csrr t0, sepc",
+        "sd t0, 33 * 8(sp)",
+        "mv a0, sp",
+        "call {kernel_trap_handler}",
+        // Restores sstatus and sepc, where sepc might be changed to a fixup address
+        "ld t0, 32 * 8(sp)",
+        "csrw sstatus, t0",
+        "ld t0, 33 * 8(sp)",
+        "csrw sepc, t0",
+        // Restores the registers from `frame.register`
+        "ld ra, 1 * 8(sp)",
+        "ld gp, 3 * 8(sp)",
+        "ld tp, 4 * 8(sp)",
+        "ld t0, 5 * 8(sp)",
+        "ld t1, 6 * 8(sp)",
+        "ld t2, 7 * 8(sp)",
+        "ld s0, 8 * 8(sp)",
+        "ld s1, 9 * 8(sp)",
+        "ld a0, 10 * 8(sp)",
+        "ld a1, 11 * 8(sp)",
+        "ld a2, 12 * 8(sp)",
+        "ld a3, 13 * 8(sp)",
+        "ld a4, 14 * 8(sp)",
+        "ld a5, 15 * 8(sp)",
+        "ld a6, 16 * 8(sp)",
+        "ld a7, 17 * 8(sp)",
+        "ld s2, 18 * 8(sp)",
+        "ld s3, 19 * 8(sp)",
+        "ld s4, 20 * 8(sp)",
+        "ld s5, 21 * 8(sp)",
+        "ld s6, 22 * 8(sp)",
+        "ld s7, 23 * 8(sp)",
+        "ld s8, 24 * 8(sp)",
+        "ld s9, 25 * 8(sp)",
+        "ld s10, 26 * 8(sp)",
+        "ld s11, 27 * 8(sp)",
+        "ld t3, 28 * 8(sp)",
+        "ld t4, 29 * 8(sp)",
+        "ld t5, 30 * 8(sp)",
+        "ld t6, 31 * 8(sp)",
+        // Deallocates the `KernelTrapFrame`
+        "addi sp, sp, 34 * 8",
+        "sret",
+        // Switches to the trap stack and panics, since the kernel stack has overflowed
+        "1:",
+        "mv a0, sp",
+        "la sp, trap_stack_top",
+        "call {kernel_stack_overflow}",
+        kernel_trap_handler = sym kernel_trap_handler,
+        kernel_stack_overflow = sym kernel_stack_overflow,
+        options(noreturn)
+    )
+}
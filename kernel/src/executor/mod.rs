@@ -1,57 +1,130 @@
 //! The `executor` module provides an executor that schedules and runs both the kernel threads and
 //! the user threads.
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, sync::Arc};
 use core::future::Future;
 
-use async_task::{Runnable, Task};
+use async_task::{Builder, Task, WithInfo};
 use lazy_static::lazy_static;
-use riscv::register::{stvec, utvec::TrapMode};
+use log::warn;
+use riscv::{asm::wfi, register::time};
 
 use crate::{
-    constant::TRAMPOLINE,
-    executor::scheduler::{Scheduler, TaskQueue},
-    sync::Mutex,
+    boot_args,
+    executor::scheduler::{
+        ClassScheduler,
+        FairScheduler,
+        MultilevelFeedbackQueue,
+        Scheduler,
+        TaskQueue,
+    },
+    sync::IrqMutex,
+    task,
 };
 
 mod context;
 mod future;
+mod kernel_task;
+mod kernel_trap;
 mod scheduler;
 
 pub use context::TrapContext;
 pub use future::{spawn_thread, yield_now, ControlFlow};
+pub use kernel_task::{kernel_task_list, spawn_kernel_task, JoinHandle};
+pub use kernel_trap::{disable_kernel_trap, enable_kernel_trap, init};
+pub use scheduler::{
+    set_scheduling_attribute,
+    SchedulingAttribute,
+    SchedulingPolicy,
+    TaskInfo,
+    NICE_MAX,
+    NICE_MIN,
+};
 
-/// Initializes the `stvec` to the address of the `_enter_kernel_space` function, which is located
-/// at the beginning of the [TRAMPOLINE] page.
-pub fn init() {
-    unsafe {
-        stvec::write(TRAMPOLINE, TrapMode::Direct);
+/// Returns the scheduler of the normal tasks chosen at boot, which is the multilevel feedback queue
+/// unless the kernel is booted with `scheduler=fifo` or `scheduler=fair` in its command line.
+fn create_normal_scheduler() -> Box<dyn Scheduler> {
+    match boot_args::get("scheduler").as_deref() {
+        Some("fifo") => Box::new(TaskQueue::new()),
+        Some("fair") => Box::new(FairScheduler::new()),
+        Some("mlfq") | None => Box::new(MultilevelFeedbackQueue::new()),
+        Some(scheduler) => {
+            warn!(
+                "unknown scheduler {}, using the multilevel feedback queue",
+                scheduler
+            );
+            Box::new(MultilevelFeedbackQueue::new())
+        }
     }
 }
 
 lazy_static! {
-    static ref SCHEDULER: Mutex<Box<dyn Scheduler>> = Mutex::new(Box::new(TaskQueue::new()));
+    /// The scheduler is an [IrqMutex] since the timer interrupt handler accounts the tick to it.
+    static ref SCHEDULER: IrqMutex<ClassScheduler> =
+        IrqMutex::new(ClassScheduler::new(create_normal_scheduler()));
 }
 
-fn spawn<F>(future: F) -> (Runnable, Task<F::Output>)
+/// Spawns a task that runs `future` with the scheduling metadata `task_info`, and schedules it.
+fn spawn<F>(future: F, task_info: Arc<TaskInfo>) -> Task<F::Output, Arc<TaskInfo>>
 where
     F: Future + Send + 'static,
     F::Output: Send + 'static,
 {
-    async_task::spawn(future, |runnable| {
-        SCHEDULER.lock().schedule(runnable);
-    })
+    let (runnable, task) = Builder::new().metadata(task_info).spawn(
+        |_| future,
+        WithInfo(|runnable, schedule_info| {
+            SCHEDULER.lock().schedule(runnable, schedule_info);
+        }),
+    );
+    runnable.schedule();
+    task
+}
+
+/// Accounts a timer tick to the running task, and returns `true` if the task should yield the CPU.
+pub fn tick(task_info: &TaskInfo) -> bool {
+    SCHEDULER.lock().tick(task_info)
 }
 
 /// Runs an event loop that executes all the tasks in the `TASK_QUEUE` until there are no more task
-/// left.
+/// left. While only throttled deadline tasks are left, the hart waits for interrupts with the
+/// scheduler unlocked until the first of them is released, so that the timer interrupt and the
+/// tasks woken meanwhile are not delayed.
 pub fn run_until_complete() {
     loop {
         let task = SCHEDULER.lock().task();
-        if let Some(task) = task {
-            task.run();
-        } else {
-            break;
-        }
+        let Some(task) = task else {
+            let next_release_time = SCHEDULER.lock().next_release_time();
+            match next_release_time {
+                // The timer interrupt wakes the hart at least once per tick, after which the
+                // released tasks are taken from the scheduler
+                Some(next_release_time) => {
+                    if time::read() < next_release_time {
+                        unsafe {
+                            wfi();
+                        }
+                    }
+                    continue;
+                }
+                None => break,
+            }
+        };
+
+        // A kernel task runs on behalf of no process
+        task::set_current_pid(task.metadata().owner().map(|(pid, _)| pid));
+
+        let task_info = task.metadata().clone();
+        task_info.start_poll();
+        task.run();
+        let elapsed_time = task_info.end_poll();
+        SCHEDULER.lock().account(&task_info, elapsed_time);
+    }
+}
+
+/// Runs the tasks that are still scheduled, such as the cancelled kernel tasks, and reports the
+/// kernel tasks that are left behind, which are waiting for an event that will never occur.
+pub fn shutdown() {
+    run_until_complete();
+    for (id, name) in kernel_task_list() {
+        warn!("kernel task {} ({}) is still running", id, name);
     }
 }
@@ -0,0 +1,280 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use async_task::{Runnable, ScheduleInfo};
+use riscv::register::time;
+
+use crate::executor::scheduler::{
+    pop_runnable,
+    Scheduler,
+    SchedulingAttribute,
+    SchedulingPolicy,
+    TaskInfo,
+    REALTIME_PRIORITY_MAX,
+};
+
+/// The number of timer ticks in the time slice of a round-robin task.
+const ROUND_ROBIN_TIME_SLICE: usize = 10;
+
+/// The unit of the bandwidth, which represents the whole CPU.
+pub(super) const BANDWIDTH_UNIT: usize = 1_000_000;
+
+/// The largest total bandwidth of the deadline tasks, which leaves 5% of the CPU to the other
+/// tasks.
+const BANDWIDTH_LIMIT: usize = BANDWIDTH_UNIT / 100 * 95;
+
+/// The total bandwidth of the deadline tasks, in units of [BANDWIDTH_UNIT].
+static DEADLINE_BANDWIDTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the bandwidth of a task that no longer exists to the deadline tasks.
+pub(super) fn release_bandwidth(attribute: &SchedulingAttribute) {
+    DEADLINE_BANDWIDTH.fetch_sub(attribute.bandwidth(), Ordering::Relaxed);
+}
+
+/// Changes the scheduling class of a group of tasks, such as the threads of a process, which takes
+/// effect when each task is scheduled again. Returns `false` and leaves every task unchanged if
+/// the total bandwidth of the deadline tasks would exceed [BANDWIDTH_LIMIT]. The caller serializes
+/// the changes to the same tasks, such as by holding the lock of their process.
+pub fn set_scheduling_attribute(
+    task_info_list: &[&TaskInfo],
+    attribute: SchedulingAttribute,
+) -> bool {
+    let current_bandwidth: usize = task_info_list
+        .iter()
+        .map(|task_info| task_info.attribute().bandwidth())
+        .sum();
+    let Some(new_bandwidth) = attribute.bandwidth().checked_mul(task_info_list.len()) else {
+        return false;
+    };
+    let is_admitted = DEADLINE_BANDWIDTH
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total_bandwidth| {
+            (total_bandwidth - current_bandwidth)
+                .checked_add(new_bandwidth)
+                .filter(|total_bandwidth| *total_bandwidth <= BANDWIDTH_LIMIT)
+        })
+        .is_ok();
+    if !is_admitted {
+        return false;
+    }
+
+    for task_info in task_info_list {
+        *task_info.attribute.lock() = attribute;
+
+        // The first period of a deadline task starts when it is scheduled
+        task_info.replenish(0);
+    }
+    true
+}
+
+/// The `ClassScheduler` struct represents the root of the scheduler hierarchy, which always runs
+/// the ready task of the highest class:
+///
+/// - The deadline tasks run by the earliest deadline first. A task that uses up its runtime is
+///   throttled until its next period starts, and then runs again with a full runtime and the
+///   deadline of the new period. If only throttled tasks are left, no task is returned, and the
+///   executor waits for the first of them to be released with [ClassScheduler::next_release_time].
+/// - The real-time tasks run by their priority. A round-robin task yields to the tasks with the
+///   same priority after [ROUND_ROBIN_TIME_SLICE] timer ticks, while a FIFO task runs until it
+///   blocks or yields.
+/// - The normal tasks are ordered by the `normal` scheduler.
+///
+/// A task is preempted on the next timer tick if a task of a higher class or with a higher
+/// priority becomes ready.
+pub struct ClassScheduler {
+    deadline_queue: BTreeMap<(usize, usize), Runnable<Arc<TaskInfo>>>,
+    /// The deadline tasks that have used up their runtime, which are ordered by their release time
+    /// and then by their ID.
+    throttled_queue: BTreeMap<(usize, usize), Runnable<Arc<TaskInfo>>>,
+    realtime_queue_list: Vec<VecDeque<Runnable<Arc<TaskInfo>>>>,
+    normal: Box<dyn Scheduler>,
+    poll_start: usize,
+}
+
+impl ClassScheduler {
+    pub fn new(normal: Box<dyn Scheduler>) -> Self {
+        Self {
+            deadline_queue: BTreeMap::new(),
+            throttled_queue: BTreeMap::new(),
+            realtime_queue_list: (0..=REALTIME_PRIORITY_MAX)
+                .map(|_| VecDeque::new())
+                .collect(),
+            normal,
+            poll_start: 0,
+        }
+    }
+
+    /// Returns the key of a deadline task in the queue, which orders the tasks by their absolute
+    /// deadline and then by their ID.
+    fn deadline_key(task_info: &TaskInfo) -> (usize, usize) {
+        (task_info.absolute_deadline(), task_info.id())
+    }
+
+    /// Returns the key of a throttled deadline task in the queue, which orders the tasks by their
+    /// release time and then by their ID.
+    fn throttled_key(task_info: &TaskInfo) -> (usize, usize) {
+        (task_info.release_time(), task_info.id())
+    }
+
+    /// Moves the throttled deadline tasks whose next period has started by `now` back to the
+    /// deadline queue, with a full runtime and the deadline of the new period.
+    fn release_throttled(&mut self, now: usize) {
+        while let Some(entry) = self.throttled_queue.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+
+            let ((release_time, _), runnable) = entry.remove_entry();
+            let task_info = runnable.metadata();
+            task_info.replenish(release_time + task_info.attribute().deadline());
+            self.deadline_queue
+                .insert(Self::deadline_key(task_info), runnable);
+        }
+    }
+
+    /// Returns the time when the first throttled deadline task is released, in clock cycles, or
+    /// `None` if no task is throttled.
+    pub fn next_release_time(&self) -> Option<usize> {
+        self.throttled_queue
+            .first_key_value()
+            .map(|(&(release_time, _), _)| release_time)
+    }
+
+    /// Removes the deadline task with the earliest deadline among the tasks that can run on the
+    /// current hart.
+    fn pop_deadline_task(&mut self) -> Option<Runnable<Arc<TaskInfo>>> {
+        let key = self
+            .deadline_queue
+            .iter()
+            .find(|(_, runnable)| runnable.metadata().can_run_here())
+            .map(|(key, _)| *key)?;
+        self.deadline_queue.remove(&key)
+    }
+
+    /// Returns `true` if a real-time task with a priority above `priority` is ready.
+    fn has_realtime_task_above(&self, priority: usize) -> bool {
+        self.realtime_queue_list[priority + 1..]
+            .iter()
+            .any(|queue| !queue.is_empty())
+    }
+}
+
+impl Scheduler for ClassScheduler {
+    fn schedule(&mut self, runnable: Runnable<Arc<TaskInfo>>, schedule_info: ScheduleInfo) {
+        let task_info = runnable.metadata();
+        let attribute = task_info.attribute();
+        match attribute.policy() {
+            SchedulingPolicy::Deadline => {
+                let now = time::read();
+                if task_info.release_time() > now {
+                    self.throttled_queue
+                        .insert(Self::throttled_key(task_info), runnable);
+                    return;
+                }
+
+                // A throttled task that is scheduled after its release time starts a new period
+                if task_info.remaining_runtime() == 0
+                    || !schedule_info.woken_while_running && task_info.absolute_deadline() <= now
+                {
+                    task_info.replenish(now + attribute.deadline());
+                }
+                self.deadline_queue
+                    .insert(Self::deadline_key(task_info), runnable);
+            }
+            SchedulingPolicy::Fifo | SchedulingPolicy::RoundRobin => {
+                task_info.renew();
+                self.realtime_queue_list[attribute.priority()].push_back(runnable);
+            }
+            SchedulingPolicy::Normal => self.normal.schedule(runnable, schedule_info),
+        }
+    }
+
+    fn task(&mut self) -> Option<Runnable<Arc<TaskInfo>>> {
+        self.release_throttled(time::read());
+        if let Some(runnable) = self.pop_deadline_task() {
+            self.poll_start = time::read();
+            return Some(runnable);
+        }
+
+        if let Some(runnable) = self
+            .realtime_queue_list
+            .iter_mut()
+            .rev()
+            .find_map(pop_runnable)
+        {
+            self.poll_start = time::read();
+            return Some(runnable);
+        }
+
+        let runnable = self.normal.task()?;
+        self.poll_start = time::read();
+        Some(runnable)
+    }
+
+    fn tick(&mut self, task_info: &TaskInfo) -> bool {
+        // A released deadline task preempts the tasks of the lower classes and the deadline tasks
+        // with a later deadline
+        self.release_throttled(time::read());
+        let attribute = task_info.attribute();
+        match attribute.policy() {
+            SchedulingPolicy::Deadline => {
+                time::read() - self.poll_start >= task_info.remaining_runtime()
+                    || self.deadline_queue.first_key_value().is_some_and(
+                        |((absolute_deadline, _), _)| {
+                            *absolute_deadline < task_info.absolute_deadline()
+                        },
+                    )
+            }
+            SchedulingPolicy::Fifo | SchedulingPolicy::RoundRobin => {
+                if !self.deadline_queue.is_empty()
+                    || self.has_realtime_task_above(attribute.priority())
+                {
+                    return true;
+                }
+
+                attribute.policy() == SchedulingPolicy::RoundRobin
+                    && task_info.add_tick() >= ROUND_ROBIN_TIME_SLICE
+                    && !self.realtime_queue_list[attribute.priority()].is_empty()
+            }
+            SchedulingPolicy::Normal => {
+                // The normal scheduler still accounts the tick when a higher class preempts the
+                // task
+                let is_expired = self.normal.tick(task_info);
+                is_expired || !self.deadline_queue.is_empty() || self.has_realtime_task_above(0)
+            }
+        }
+    }
+
+    fn account(&mut self, task_info: &TaskInfo, elapsed_time: usize) {
+        let attribute = task_info.attribute();
+        match attribute.policy() {
+            SchedulingPolicy::Deadline => {
+                let remaining_runtime = task_info.remaining_runtime().saturating_sub(elapsed_time);
+                if remaining_runtime > 0 {
+                    task_info.set_remaining_runtime(remaining_runtime);
+                    return;
+                }
+
+                // The task has used up its runtime, so it is throttled until its next period
+                // starts, and moved to the throttled queue if it is already in the deadline queue.
+                // A period that has already ended is not caught up with, so the next period starts
+                // now.
+                let runnable = self.deadline_queue.remove(&Self::deadline_key(task_info));
+                let period_start = task_info
+                    .absolute_deadline()
+                    .saturating_sub(attribute.deadline());
+                task_info.throttle((period_start + attribute.period()).max(time::read()));
+                if let Some(runnable) = runnable {
+                    self.throttled_queue
+                        .insert(Self::throttled_key(task_info), runnable);
+                }
+            }
+            SchedulingPolicy::Fifo | SchedulingPolicy::RoundRobin => (),
+            SchedulingPolicy::Normal => self.normal.account(task_info, elapsed_time),
+        }
+    }
+}
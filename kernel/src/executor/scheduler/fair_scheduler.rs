@@ -0,0 +1,129 @@
+use alloc::{collections::BTreeMap, sync::Arc};
+
+use async_task::{Runnable, ScheduleInfo};
+use riscv::register::time;
+
+use crate::{
+    constant::CLOCK_FREQ,
+    executor::scheduler::{Scheduler, TaskInfo, NICE_MIN},
+};
+
+/// The weights of the nice values from `-20` to `19`, which follow the definitions in Linux.
+/// Each nice value gets about 1.25 times the CPU time of the next one.
+const WEIGHT_LIST: [usize; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// The weight of the nice value `0`.
+const DEFAULT_WEIGHT: usize = 1024;
+
+/// The period in clock cycles in which every ready task should run once.
+const TARGET_LATENCY: usize = CLOCK_FREQ / 1000 * 40;
+
+/// The shortest time slice in clock cycles.
+const MIN_GRANULARITY: usize = CLOCK_FREQ / 1000 * 10;
+
+/// Returns the weight of a task, which is given by its nice value.
+fn weight(task_info: &TaskInfo) -> usize {
+    WEIGHT_LIST[(task_info.nice() - NICE_MIN) as usize]
+}
+
+/// The `Entity` struct represents a task in the queue, together with its weight when it was added.
+struct Entity {
+    weight: usize,
+    runnable: Runnable<Arc<TaskInfo>>,
+}
+
+/// The `FairScheduler` struct represents a scheduler that shares the CPU between the tasks in
+/// proportion to their weights, like the completely fair scheduler of Linux. Each task has a
+/// virtual runtime, which grows slower for a task with a higher weight, and the task with the
+/// lowest virtual runtime runs next.
+///
+/// A task that blocks for a long time doesn't keep its low virtual runtime, because it is placed at
+/// most [TARGET_LATENCY] behind the lowest virtual runtime when it wakes up.
+pub struct FairScheduler {
+    queue: BTreeMap<(usize, usize), Entity>,
+    total_weight: usize,
+    min_virtual_runtime: usize,
+    poll_start: usize,
+}
+
+impl FairScheduler {
+    pub fn new() -> Self {
+        Self {
+            queue: BTreeMap::new(),
+            total_weight: 0,
+            min_virtual_runtime: 0,
+            poll_start: 0,
+        }
+    }
+
+    /// Returns the key of a task in the queue, which orders the tasks by their virtual runtime and
+    /// then by their ID.
+    fn key(task_info: &TaskInfo) -> (usize, usize) {
+        (task_info.virtual_runtime(), task_info.id())
+    }
+
+    /// Returns the time slice of a task in clock cycles, which is its share of [TARGET_LATENCY].
+    fn time_slice(&self, task_info: &TaskInfo) -> usize {
+        let weight = weight(task_info);
+        (TARGET_LATENCY * weight / (self.total_weight + weight)).max(MIN_GRANULARITY)
+    }
+
+    /// Advances the lowest virtual runtime to the lowest virtual runtime among the ready tasks and
+    /// `task_info`, which never decreases.
+    fn update_min_virtual_runtime(&mut self, task_info: &TaskInfo) {
+        let virtual_runtime = match self.queue.first_key_value() {
+            Some(((virtual_runtime, _), _)) => task_info.virtual_runtime().min(*virtual_runtime),
+            None => task_info.virtual_runtime(),
+        };
+        self.min_virtual_runtime = self.min_virtual_runtime.max(virtual_runtime);
+    }
+}
+
+impl Scheduler for FairScheduler {
+    fn schedule(&mut self, runnable: Runnable<Arc<TaskInfo>>, schedule_info: ScheduleInfo) {
+        let task_info = runnable.metadata();
+        task_info.renew();
+        if !schedule_info.woken_while_running {
+            let virtual_runtime = self.min_virtual_runtime.saturating_sub(TARGET_LATENCY);
+            task_info.set_virtual_runtime(task_info.virtual_runtime().max(virtual_runtime));
+        }
+
+        let weight = weight(task_info);
+        self.total_weight += weight;
+        self.queue
+            .insert(Self::key(task_info), Entity { weight, runnable });
+    }
+
+    fn task(&mut self) -> Option<Runnable<Arc<TaskInfo>>> {
+        let key = self
+            .queue
+            .iter()
+            .find(|(_, entity)| entity.runnable.metadata().can_run_here())
+            .map(|(key, _)| *key)?;
+        let Entity { weight, runnable } = self.queue.remove(&key)?;
+        self.total_weight -= weight;
+        self.poll_start = time::read();
+        Some(runnable)
+    }
+
+    fn tick(&mut self, task_info: &TaskInfo) -> bool {
+        !self.queue.is_empty() && time::read() - self.poll_start >= self.time_slice(task_info)
+    }
+
+    fn account(&mut self, task_info: &TaskInfo, elapsed_time: usize) {
+        // The task is already in the queue if it yielded during the poll, so it is moved to the key
+        // of its new virtual runtime
+        let entry = self.queue.remove(&Self::key(task_info));
+        task_info.set_virtual_runtime(
+            task_info.virtual_runtime() + elapsed_time * DEFAULT_WEIGHT / weight(task_info),
+        );
+        if let Some(entry) = entry {
+            self.queue.insert(Self::key(task_info), entry);
+        }
+        self.update_min_virtual_runtime(task_info);
+    }
+}
@@ -0,0 +1,437 @@
+//! The `scheduler` module provides the scheduling policies of the executor. Each task carries a
+//! [TaskInfo] as the metadata of its [Runnable], which the [Scheduler] reads to decide which task
+//! runs next and how long it runs.
+//!
+//! The schedulers form a hierarchy: the [ClassScheduler] runs the deadline tasks first, then the
+//! real-time tasks, and passes the normal tasks to the [Scheduler] chosen at boot.
+
+use alloc::{collections::VecDeque, sync::Arc};
+use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+
+use async_task::{Runnable, ScheduleInfo};
+use riscv::register::time;
+
+use crate::{
+    constant::CLOCK_FREQ,
+    hart,
+    sync::IrqMutex,
+    task::{Pid, Tid},
+};
+
+mod class_scheduler;
+mod fair_scheduler;
+mod multilevel_feedback_queue;
+mod task_queue;
+
+use class_scheduler::BANDWIDTH_UNIT;
+pub use class_scheduler::{set_scheduling_attribute, ClassScheduler};
+pub use fair_scheduler::FairScheduler;
+pub use multilevel_feedback_queue::MultilevelFeedbackQueue;
+pub use task_queue::TaskQueue;
+
+/// The lowest nice value, which gives a thread the highest priority.
+pub const NICE_MIN: isize = -20;
+/// The highest nice value, which gives a thread the lowest priority.
+pub const NICE_MAX: isize = 19;
+
+/// The lowest priority of a real-time task.
+pub const REALTIME_PRIORITY_MIN: usize = 1;
+/// The highest priority of a real-time task.
+pub const REALTIME_PRIORITY_MAX: usize = 99;
+
+/// The `SchedulingPolicy` enum represents the scheduling classes of the tasks, whose values follow
+/// the definitions in Linux.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SchedulingPolicy {
+    /// The task is ordered by the scheduler chosen at boot.
+    Normal = 0,
+    /// The real-time task runs until it blocks, yields, or is preempted by a task with a higher
+    /// priority.
+    Fifo = 1,
+    /// The real-time task runs like [SchedulingPolicy::Fifo], but yields to the tasks with the
+    /// same priority when its time slice ends.
+    RoundRobin = 2,
+    /// The task runs by the earliest deadline first, and is given a runtime in each period.
+    Deadline = 6,
+}
+
+impl TryFrom<usize> for SchedulingPolicy {
+    type Error = ();
+
+    fn try_from(policy: usize) -> Result<Self, Self::Error> {
+        match policy {
+            0 => Ok(Self::Normal),
+            1 => Ok(Self::Fifo),
+            2 => Ok(Self::RoundRobin),
+            6 => Ok(Self::Deadline),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The `SchedulingAttribute` struct represents the scheduling class of a task and its parameters.
+/// The runtime, the deadline, and the period of a deadline task are in clock cycles.
+#[derive(Copy, Clone)]
+pub struct SchedulingAttribute {
+    policy: SchedulingPolicy,
+    priority: usize,
+    runtime: usize,
+    deadline: usize,
+    period: usize,
+    bandwidth: usize,
+}
+
+impl SchedulingAttribute {
+    /// Creates a [SchedulingAttribute], where the runtime, the deadline, and the period are in
+    /// nanoseconds. Returns `None` if a real-time task has a priority out of range, a normal or
+    /// deadline task has a nonzero priority, or a deadline task doesn't satisfy
+    /// `0 < runtime <= deadline <= period` once they are converted to clock cycles, which also
+    /// rejects a runtime that is shorter than a clock cycle and the durations that overflow. The
+    /// period defaults to the deadline if it is `0`.
+    pub fn new(
+        policy: SchedulingPolicy,
+        priority: usize,
+        runtime: usize,
+        deadline: usize,
+        period: usize,
+    ) -> Option<Self> {
+        match policy {
+            SchedulingPolicy::Normal if priority == 0 => Some(Self::default()),
+            SchedulingPolicy::Fifo | SchedulingPolicy::RoundRobin
+                if (REALTIME_PRIORITY_MIN..=REALTIME_PRIORITY_MAX).contains(&priority) =>
+            {
+                Some(Self {
+                    policy,
+                    priority,
+                    ..Self::default()
+                })
+            }
+            SchedulingPolicy::Deadline if priority == 0 => {
+                let period = if period == 0 { deadline } else { period };
+                let runtime = nanosecond_to_cycle(runtime)?;
+                let deadline = nanosecond_to_cycle(deadline)?;
+                let period = nanosecond_to_cycle(period)?;
+                if runtime == 0 || runtime > deadline || deadline > period {
+                    return None;
+                }
+
+                Some(Self {
+                    policy,
+                    priority,
+                    runtime,
+                    deadline,
+                    period,
+                    bandwidth: runtime.checked_mul(BANDWIDTH_UNIT)?.checked_div(period)?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn policy(&self) -> SchedulingPolicy {
+        self.policy
+    }
+
+    /// Returns the priority of a real-time task, or `0` for the other tasks.
+    pub fn priority(&self) -> usize {
+        self.priority
+    }
+
+    pub fn runtime(&self) -> usize {
+        self.runtime
+    }
+
+    pub fn deadline(&self) -> usize {
+        self.deadline
+    }
+
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    /// Returns the share of the CPU that a task reserves in units of [BANDWIDTH_UNIT], which is
+    /// `runtime / period` for a deadline task and `0` for the other tasks.
+    pub fn bandwidth(&self) -> usize {
+        self.bandwidth
+    }
+}
+
+impl Default for SchedulingAttribute {
+    fn default() -> Self {
+        Self {
+            policy: SchedulingPolicy::Normal,
+            priority: 0,
+            runtime: 0,
+            deadline: 0,
+            period: 0,
+            bandwidth: 0,
+        }
+    }
+}
+
+/// Converts a duration in nanoseconds to clock cycles, which is rounded down. Returns `None` if the
+/// conversion overflows.
+fn nanosecond_to_cycle(nanosecond: usize) -> Option<usize> {
+    nanosecond
+        .checked_mul(CLOCK_FREQ / 1000)?
+        .checked_div(1_000_000)
+}
+
+/// The `Scheduler` trait represents a scheduling policy, which orders the tasks that are ready to
+/// run.
+pub trait Scheduler {
+    /// Adds a task that is ready to run. `schedule_info` tells whether the task was woken while it
+    /// was running, which means that it yielded instead of blocking.
+    fn schedule(&mut self, runnable: Runnable<Arc<TaskInfo>>, schedule_info: ScheduleInfo);
+
+    /// Removes the task that runs next among the tasks whose affinity mask contains the current
+    /// hart. The other tasks stay in the queue for the harts that they can run on.
+    fn task(&mut self) -> Option<Runnable<Arc<TaskInfo>>>;
+
+    /// Accounts a timer tick to the running task, and returns `true` if the task should yield
+    /// the CPU.
+    fn tick(&mut self, task_info: &TaskInfo) -> bool;
+
+    /// Accounts the time that a task has run in a poll, in clock cycles.
+    fn account(&mut self, _task_info: &TaskInfo, _elapsed_time: usize) {}
+}
+
+/// Removes the first task in `queue` that can run on the current hart.
+fn pop_runnable(queue: &mut VecDeque<Runnable<Arc<TaskInfo>>>) -> Option<Runnable<Arc<TaskInfo>>> {
+    let index = queue
+        .iter()
+        .position(|runnable| runnable.metadata().can_run_here())?;
+    queue.remove(index)
+}
+
+/// The ID of the next task.
+static NEXT_TASK_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The `TaskInfo` struct represents the scheduling metadata of a task, which is shared between
+/// the [Runnable] of the task and the thread that owns it.
+pub struct TaskInfo {
+    id: usize,
+    owner: Option<(Pid, Tid)>,
+    nice: AtomicIsize,
+    level: AtomicUsize,
+    tick_count: AtomicUsize,
+    expired: AtomicBool,
+    virtual_runtime: AtomicUsize,
+    attribute: IrqMutex<SchedulingAttribute>,
+    absolute_deadline: AtomicUsize,
+    remaining_runtime: AtomicUsize,
+    release_time: AtomicUsize,
+    polling: AtomicBool,
+    poll_start: AtomicUsize,
+    runtime: AtomicUsize,
+    user_time: AtomicUsize,
+    affinity: AtomicUsize,
+}
+
+impl TaskInfo {
+    /// Creates a [TaskInfo] for a task owned by the thread with a specific [Pid] and [Tid], or
+    /// by the kernel if `owner` is `None`.
+    pub fn new(owner: Option<(Pid, Tid)>) -> Self {
+        Self {
+            id: NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed),
+            owner,
+            nice: AtomicIsize::new(0),
+            level: AtomicUsize::new(0),
+            tick_count: AtomicUsize::new(0),
+            expired: AtomicBool::new(false),
+            virtual_runtime: AtomicUsize::new(0),
+            attribute: IrqMutex::new(SchedulingAttribute::default()),
+            absolute_deadline: AtomicUsize::new(0),
+            remaining_runtime: AtomicUsize::new(0),
+            release_time: AtomicUsize::new(0),
+            polling: AtomicBool::new(false),
+            poll_start: AtomicUsize::new(0),
+            runtime: AtomicUsize::new(0),
+            user_time: AtomicUsize::new(0),
+            affinity: AtomicUsize::new(hart::online_hart_mask()),
+        }
+    }
+
+    /// Copies the nice value, the affinity mask, and the scheduling class of the parent task. A
+    /// deadline task passes the normal class to its child instead, so that the child doesn't take
+    /// the bandwidth of the parent.
+    pub fn inherit(&self, parent: &TaskInfo) {
+        self.set_nice(parent.nice());
+        self.set_affinity(parent.affinity());
+        let attribute = parent.attribute();
+        if attribute.policy() != SchedulingPolicy::Deadline {
+            *self.attribute.lock() = attribute;
+        }
+    }
+
+    /// Returns the ID of the task, which is unique among all tasks.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns the [Pid] and the [Tid] of the thread that owns the task, or `None` if the task is
+    /// owned by the kernel.
+    pub fn owner(&self) -> Option<(Pid, Tid)> {
+        self.owner
+    }
+
+    pub fn nice(&self) -> isize {
+        self.nice.load(Ordering::Relaxed)
+    }
+
+    /// Sets the nice value, which is clamped between [NICE_MIN] and [NICE_MAX].
+    pub fn set_nice(&self, nice: isize) {
+        self.nice
+            .store(nice.clamp(NICE_MIN, NICE_MAX), Ordering::Relaxed);
+    }
+
+    /// Returns the affinity mask of the task, where bit `i` is set if the task can run on the hart
+    /// with ID `i`.
+    pub fn affinity(&self) -> usize {
+        self.affinity.load(Ordering::Relaxed)
+    }
+
+    /// Sets the affinity mask of the task, which is restricted to the online harts. Returns `false`
+    /// if the mask contains no online hart, since the task could never be run. Since only the boot
+    /// hart is online, the mask is accepted but has no effect on where the task runs.
+    pub fn set_affinity(&self, affinity: usize) -> bool {
+        let affinity = affinity & hart::online_hart_mask();
+        if affinity == 0 {
+            return false;
+        }
+        self.affinity.store(affinity, Ordering::Relaxed);
+        true
+    }
+
+    /// Returns `true` if the affinity mask of the task contains the current hart.
+    pub fn can_run_here(&self) -> bool {
+        self.affinity() & (1 << hart::hart_id()) != 0
+    }
+
+    /// Returns the priority level that the scheduler has assigned to the task, where `0` is the
+    /// highest level.
+    pub fn level(&self) -> usize {
+        self.level.load(Ordering::Relaxed)
+    }
+
+    pub fn set_level(&self, level: usize) {
+        self.level.store(level, Ordering::Relaxed);
+    }
+
+    /// Increments the number of timer ticks that the task has run in its time slice, and returns
+    /// the new number.
+    pub fn add_tick(&self) -> usize {
+        self.tick_count.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Marks that the task has used up its time slice.
+    pub fn expire(&self) {
+        self.expired.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the virtual runtime of the task, which is the time that the task has run in clock
+    /// cycles, weighted by its nice value.
+    pub fn virtual_runtime(&self) -> usize {
+        self.virtual_runtime.load(Ordering::Relaxed)
+    }
+
+    pub fn set_virtual_runtime(&self, virtual_runtime: usize) {
+        self.virtual_runtime
+            .store(virtual_runtime, Ordering::Relaxed);
+    }
+
+    pub fn attribute(&self) -> SchedulingAttribute {
+        *self.attribute.lock()
+    }
+
+    /// Returns the absolute deadline of the current period of a deadline task, in clock cycles.
+    pub fn absolute_deadline(&self) -> usize {
+        self.absolute_deadline.load(Ordering::Relaxed)
+    }
+
+    /// Returns the runtime left in the current period of a deadline task, in clock cycles.
+    pub fn remaining_runtime(&self) -> usize {
+        self.remaining_runtime.load(Ordering::Relaxed)
+    }
+
+    pub fn set_remaining_runtime(&self, remaining_runtime: usize) {
+        self.remaining_runtime
+            .store(remaining_runtime, Ordering::Relaxed);
+    }
+
+    /// Starts a period of a deadline task that ends at `absolute_deadline`, which has the full
+    /// runtime.
+    pub fn replenish(&self, absolute_deadline: usize) {
+        self.absolute_deadline
+            .store(absolute_deadline, Ordering::Relaxed);
+        self.set_remaining_runtime(self.attribute().runtime());
+        self.release_time.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the time when a deadline task that has used up its runtime is allowed to run again,
+    /// in clock cycles, or `0` if the task is not throttled.
+    pub fn release_time(&self) -> usize {
+        self.release_time.load(Ordering::Relaxed)
+    }
+
+    /// Throttles a deadline task that has used up its runtime until `release_time`, when its next
+    /// period starts.
+    pub fn throttle(&self, release_time: usize) {
+        self.set_remaining_runtime(0);
+        self.release_time.store(release_time, Ordering::Relaxed);
+    }
+
+    /// Records that the executor starts to poll the task.
+    pub fn start_poll(&self) {
+        self.poll_start.store(time::read(), Ordering::Relaxed);
+        self.polling.store(true, Ordering::Relaxed);
+    }
+
+    /// Records that the executor has finished polling the task, and returns the time that the poll
+    /// took in clock cycles.
+    pub fn end_poll(&self) -> usize {
+        self.polling.store(false, Ordering::Relaxed);
+        let elapsed_time = time::read() - self.poll_start.load(Ordering::Relaxed);
+        self.runtime.fetch_add(elapsed_time, Ordering::Relaxed);
+        elapsed_time
+    }
+
+    /// Returns the time that the executor has spent polling the task in clock cycles, including the
+    /// current poll.
+    pub fn runtime(&self) -> usize {
+        let runtime = self.runtime.load(Ordering::Relaxed);
+        if self.polling.load(Ordering::Relaxed) {
+            runtime + (time::read() - self.poll_start.load(Ordering::Relaxed))
+        } else {
+            runtime
+        }
+    }
+
+    /// Returns the part of the runtime that a user thread has spent in user mode, in clock cycles.
+    pub fn user_time(&self) -> usize {
+        self.user_time.load(Ordering::Relaxed)
+    }
+
+    pub fn add_user_time(&self, user_time: usize) {
+        self.user_time.fetch_add(user_time, Ordering::Relaxed);
+    }
+
+    /// Starts a new time slice, and returns `true` if the task used up the previous one.
+    pub fn renew(&self) -> bool {
+        self.tick_count.store(0, Ordering::Relaxed);
+        self.expired.swap(false, Ordering::Relaxed)
+    }
+
+    /// Starts a new time slice and returns `true` if the task has used up its time slice.
+    /// Otherwise, the task keeps the ticks that it has run, so that a task that yields or blocks
+    /// before its time slice ends is charged for the partial time slice.
+    pub fn renew_expired(&self) -> bool {
+        self.expired.load(Ordering::Relaxed) && self.renew()
+    }
+}
+
+impl Drop for TaskInfo {
+    fn drop(&mut self) {
+        class_scheduler::release_bandwidth(&self.attribute());
+    }
+}
@@ -0,0 +1,103 @@
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+
+use async_task::{Runnable, ScheduleInfo};
+
+use crate::executor::scheduler::{pop_runnable, Scheduler, TaskInfo};
+
+/// The number of priority levels.
+const LEVEL_COUNT: usize = 8;
+
+/// The number of nice values that lower the priority by one level.
+const NICE_PER_LEVEL: isize = 5;
+
+/// The number of timer ticks between two priority boosts, which move every task back to the
+/// highest level so that the CPU-bound tasks are not starved.
+const BOOST_INTERVAL: usize = 100;
+
+/// The `MultilevelFeedbackQueue` struct represents a scheduler with a queue for each priority
+/// level, which always runs the tasks in the highest nonempty queue. A task that uses up its time
+/// slice is demoted by one level, and a task that blocks before its time slice ends is promoted by
+/// one level, so that the interactive tasks run before the CPU-bound tasks. The time slice doubles
+/// on each lower level. The ticks are charged across the runs of a task until its time slice is
+/// used up, so a task can't keep its level by yielding or blocking just before the slice ends.
+///
+/// The nice value shifts the queue of a task by one level for every [NICE_PER_LEVEL] nice values
+/// away from `0`.
+pub struct MultilevelFeedbackQueue {
+    queue_list: Vec<VecDeque<Runnable<Arc<TaskInfo>>>>,
+    tick_count: usize,
+}
+
+impl MultilevelFeedbackQueue {
+    pub fn new() -> Self {
+        Self {
+            queue_list: (0..LEVEL_COUNT).map(|_| VecDeque::new()).collect(),
+            tick_count: 0,
+        }
+    }
+
+    /// Returns the index of the queue of a task, which combines the level of the task with its
+    /// nice value.
+    fn queue_index(task_info: &TaskInfo) -> usize {
+        let nice_offset = task_info.nice() / NICE_PER_LEVEL;
+        (task_info.level() as isize + nice_offset).clamp(0, LEVEL_COUNT as isize - 1) as usize
+    }
+
+    /// Returns the number of timer ticks in the time slice of a task.
+    fn time_slice(task_info: &TaskInfo) -> usize {
+        1 << Self::queue_index(task_info)
+    }
+
+    /// Moves every task back to the highest level with a new time slice.
+    fn boost(&mut self, task_info: &TaskInfo) {
+        task_info.set_level(0);
+        task_info.renew();
+        for runnable in self.queue_list.iter().flatten() {
+            runnable.metadata().renew();
+        }
+        for queue_index in 1..LEVEL_COUNT {
+            while let Some(runnable) = self.queue_list[queue_index].pop_front() {
+                runnable.metadata().set_level(0);
+                let queue_index = Self::queue_index(runnable.metadata());
+                self.queue_list[queue_index].push_back(runnable);
+            }
+        }
+    }
+}
+
+impl Scheduler for MultilevelFeedbackQueue {
+    fn schedule(&mut self, runnable: Runnable<Arc<TaskInfo>>, schedule_info: ScheduleInfo) {
+        let task_info = runnable.metadata();
+        let level = task_info.level();
+        if task_info.renew_expired() {
+            task_info.set_level((level + 1).min(LEVEL_COUNT - 1));
+        } else if !schedule_info.woken_while_running {
+            task_info.set_level(level.saturating_sub(1));
+        }
+
+        let queue_index = Self::queue_index(task_info);
+        self.queue_list[queue_index].push_back(runnable);
+    }
+
+    fn task(&mut self) -> Option<Runnable<Arc<TaskInfo>>> {
+        self.queue_list.iter_mut().find_map(pop_runnable)
+    }
+
+    fn tick(&mut self, task_info: &TaskInfo) -> bool {
+        self.tick_count += 1;
+        if self.tick_count % BOOST_INTERVAL == 0 {
+            self.boost(task_info);
+        }
+
+        if task_info.add_tick() >= Self::time_slice(task_info) {
+            task_info.expire();
+            return true;
+        }
+
+        // A task that becomes ready in a higher queue preempts the running task
+        let queue_index = Self::queue_index(task_info);
+        self.queue_list[..queue_index]
+            .iter()
+            .any(|queue| !queue.is_empty())
+    }
+}
@@ -0,0 +1,34 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use async_task::{Runnable, ScheduleInfo};
+
+use crate::executor::scheduler::{pop_runnable, Scheduler, TaskInfo};
+
+/// The `TaskQueue` struct represents a queue of [Runnable] tasks, which are either kernel threads
+/// or user threads. The tasks run in the order that they become ready, and each task yields on
+/// every timer tick.
+pub struct TaskQueue {
+    queue: VecDeque<Runnable<Arc<TaskInfo>>>,
+}
+
+impl TaskQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Scheduler for TaskQueue {
+    fn schedule(&mut self, runnable: Runnable<Arc<TaskInfo>>, _schedule_info: ScheduleInfo) {
+        self.queue.push_back(runnable)
+    }
+
+    fn task(&mut self) -> Option<Runnable<Arc<TaskInfo>>> {
+        pop_runnable(&mut self.queue)
+    }
+
+    fn tick(&mut self, _task_info: &TaskInfo) -> bool {
+        true
+    }
+}
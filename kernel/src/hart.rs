@@ -0,0 +1,124 @@
+//! The `hart` module records the hardware threads (harts) that the kernel runs on, and the state of
+//! the interrupts on each hart. The kernel only runs on the hart that boots it, whose ID is passed
+//! by the SBI implementation.
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use riscv::register::sstatus;
+
+use crate::constant::MAX_HART_COUNT;
+
+static BOOT_HART_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The `Hart` struct represents the interrupt state of a hart.
+struct Hart {
+    /// The number of nested sections that have disabled the interrupts.
+    interrupt_disable_depth: AtomicUsize,
+    /// Whether the interrupts were enabled before the outermost section disabled them.
+    interrupt_enabled: AtomicBool,
+    /// The number of nested interrupt handlers that are running.
+    interrupt_depth: AtomicUsize,
+    /// Whether a timer tick has been taken in the kernel and not yet accounted to a thread.
+    pending_tick: AtomicBool,
+}
+
+impl Hart {
+    const fn new() -> Self {
+        Self {
+            interrupt_disable_depth: AtomicUsize::new(0),
+            interrupt_enabled: AtomicBool::new(false),
+            interrupt_depth: AtomicUsize::new(0),
+            pending_tick: AtomicBool::new(false),
+        }
+    }
+}
+
+static HART_LIST: [Hart; MAX_HART_COUNT] = [const { Hart::new() }; MAX_HART_COUNT];
+
+/// Records the ID of the boot hart.
+pub fn init(hart_id: usize) {
+    assert!(
+        hart_id < MAX_HART_COUNT,
+        "the hart ID {} is too large",
+        hart_id
+    );
+    BOOT_HART_ID.store(hart_id, Ordering::Relaxed);
+}
+
+/// Returns the ID of the hart that the kernel is running on.
+pub fn hart_id() -> usize {
+    BOOT_HART_ID.load(Ordering::Relaxed)
+}
+
+/// Returns the mask of the online harts, where bit `i` is set if the hart with ID `i` is online.
+/// The secondary harts are never started, so the mask only contains the boot hart.
+pub fn online_hart_mask() -> usize {
+    1 << hart_id()
+}
+
+fn current_hart() -> &'static Hart {
+    &HART_LIST[hart_id()]
+}
+
+/// Disables the interrupts on the current hart until the matching call to [restore_interrupt].
+/// The calls can be nested, and the interrupts are only enabled again when the outermost section
+/// ends, if they were enabled before it.
+pub fn disable_interrupt() {
+    let interrupt_enabled = sstatus::read().sie();
+    unsafe {
+        sstatus::clear_sie();
+    }
+
+    let hart = current_hart();
+    if hart.interrupt_disable_depth.fetch_add(1, Ordering::Relaxed) == 0 {
+        hart.interrupt_enabled
+            .store(interrupt_enabled, Ordering::Relaxed);
+    }
+}
+
+/// Ends a section started by [disable_interrupt], and enables the interrupts if it is the
+/// outermost section and the interrupts were enabled before it.
+pub fn restore_interrupt() {
+    debug_assert!(
+        !sstatus::read().sie(),
+        "the interrupts are enabled in a section that disables them"
+    );
+
+    let hart = current_hart();
+    let depth = hart.interrupt_disable_depth.fetch_sub(1, Ordering::Relaxed);
+    debug_assert!(
+        depth > 0,
+        "the interrupts are restored more times than disabled"
+    );
+    if depth == 1 && hart.interrupt_enabled.load(Ordering::Relaxed) {
+        unsafe {
+            sstatus::set_sie();
+        }
+    }
+}
+
+/// Runs `handler` in the interrupt context of the current hart, where the locks that can be taken
+/// by the code it interrupts must be an [IrqMutex](crate::sync::IrqMutex).
+pub fn handle_interrupt<T>(handler: impl FnOnce() -> T) -> T {
+    let hart = current_hart();
+    hart.interrupt_depth.fetch_add(1, Ordering::Relaxed);
+    let result = handler();
+    hart.interrupt_depth.fetch_sub(1, Ordering::Relaxed);
+    result
+}
+
+/// Returns `true` if the current hart is running an interrupt handler.
+pub fn is_in_interrupt() -> bool {
+    current_hart().interrupt_depth.load(Ordering::Relaxed) > 0
+}
+
+/// Records a timer tick taken in the kernel, which can't preempt the running task, so it is
+/// accounted at the next preemption point.
+pub fn defer_tick() {
+    current_hart().pending_tick.store(true, Ordering::Relaxed);
+}
+
+/// Returns `true` if a timer tick has been taken in the kernel since the last call.
+pub fn take_pending_tick() -> bool {
+    current_hart().pending_tick.swap(false, Ordering::Relaxed)
+}
@@ -12,17 +12,23 @@
 #![feature(alloc_error_handler)]
 #![feature(panic_info_message)]
 #![feature(naked_functions)]
+#![feature(inline_const)]
+#![feature(fn_align)]
 
 extern crate alloc;
 
+mod boot_args;
 #[macro_use]
 mod console;
 mod constant;
+mod drivers;
 mod executor;
 mod file;
+mod hart;
 mod lang_items;
 mod logging;
 mod mem;
+mod random;
 mod sbi;
 mod sync;
 mod syscall;
@@ -36,13 +42,17 @@ use log::info;
 global_asm!(include_str!("asm/boot.asm"));
 global_asm!(include_str!("asm/linkage.asm"));
 
-/// Initializes the thread executor and spawns the `INIT_PROCESS`.
+/// Initializes the thread executor and spawns the `INIT_PROCESS`. The ID of the boot hart and the
+/// address of the device tree are passed in `a0` and `a1` by the SBI implementation.
 #[no_mangle]
-pub fn rust_main() {
+pub fn rust_main(hart_id: usize, device_tree_address: usize) {
     clear_bss();
+    hart::init(hart_id);
+    boot_args::init(device_tree_address);
     logging::init();
 
     mem::init();
+    random::init();
 
     timer::enable_timer_interrupt();
     timer::set_trigger();
@@ -51,9 +61,13 @@ pub fn rust_main() {
     file::print_bin_name();
 
     task::init();
+    sync::spawn_self_test();
     executor::init();
     executor::run_until_complete();
+    task::stop_reclaimer();
+    executor::shutdown();
 
+    mem::print_heap_statistics();
     sbi::shutdown();
 }
 
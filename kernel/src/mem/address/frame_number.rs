@@ -13,7 +13,7 @@ use crate::{
 const FRAME_NUMBER_SIZE: usize = 44;
 
 /// The `FrameNumber` struct represents the number of a 44-bit page frame defined in the Sv39
-/// page table format.
+/// and Sv48 page table formats.
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct FrameNumber {
     pub bits: usize,
@@ -1,4 +1,4 @@
-//! The `address` module defines various structs for the Sv39 page table specification.
+//! The `address` module defines various structs for the Sv39 and Sv48 page table specifications.
 
 mod frame_number;
 mod page_number;
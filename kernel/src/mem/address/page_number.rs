@@ -1,8 +1,15 @@
 use core::ops::Sub;
 
-use crate::{executor::TrapContext, mem::VirtualAddress};
-
-const PAGE_NUMBER_SIZE: usize = 27;
+use crate::{
+    executor::TrapContext,
+    mem::{page_table::paging_mode, VirtualAddress},
+};
+
+/// Returns the mask of the page numbers, whose width depends on the number of levels of the page
+/// table.
+fn page_number_mask() -> usize {
+    (1 << (9 * paging_mode().level_count())) - 1
+}
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct PageNumber {
@@ -10,18 +17,16 @@ pub struct PageNumber {
 }
 
 impl PageNumber {
-    pub fn index(&self) -> [usize; 3] {
-        let mask = (1 << 9) - 1;
-        [
-            (self.bits >> 18) & mask,
-            (self.bits >> 9) & mask,
-            self.bits & mask,
-        ]
+    /// Returns the index of the page table entry at a specific level of the page table, where the
+    /// root page table is at level 0.
+    pub fn index(&self, level: usize) -> usize {
+        let shift = 9 * (paging_mode().level_count() - 1 - level);
+        (self.bits >> shift) & ((1 << 9) - 1)
     }
 
     pub fn offset(&mut self, rhs: usize) -> Self {
         PageNumber {
-            bits: (self.bits + rhs) & ((1 << PAGE_NUMBER_SIZE) - 1),
+            bits: (self.bits + rhs) & page_number_mask(),
         }
     }
 
@@ -39,7 +44,7 @@ impl PageNumber {
 impl From<usize> for PageNumber {
     fn from(value: usize) -> Self {
         Self {
-            bits: value & ((1 << PAGE_NUMBER_SIZE) - 1),
+            bits: value & page_number_mask(),
         }
     }
 }
@@ -81,10 +86,6 @@ impl PageRange {
         PageRangeIterator::new(self.start, self.end)
     }
 
-    pub fn len(&self) -> usize {
-        self.end - self.start
-    }
-
     pub fn start(&self) -> PageNumber {
         self.start
     }
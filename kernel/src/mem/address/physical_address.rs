@@ -8,7 +8,7 @@ use crate::{
 const PHYSICAL_ADDRESS_SIZE: usize = 56;
 
 /// The `PhysicalAddress` struct represents a 56-bit physical address defined in the Sv39
-/// page table format.
+/// and Sv48 page table formats.
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct PhysicalAddress {
     bits: usize,
@@ -2,13 +2,11 @@ use core::ops::{Add, AddAssign, Sub};
 
 use crate::{
     constant::{PAGE_SIZE, PAGE_SIZE_BIT},
-    mem::PageNumber,
+    mem::{page_table::paging_mode, PageNumber},
 };
 
-const VIRTUAL_ADDRESS_SIZE: usize = 39;
-
 /// The `VirtualAddress` struct represents a 39-bit virtual address defined in the Sv39
-/// page table format.
+/// page table format, or a 48-bit virtual address defined in the Sv48 page table format.
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct VirtualAddress {
     bits: usize,
@@ -71,9 +69,10 @@ impl Sub<usize> for VirtualAddress {
 
 impl From<usize> for VirtualAddress {
     fn from(value: usize) -> Self {
+        let virtual_address_width = paging_mode().virtual_address_width();
         assert!(
-            (value >> VIRTUAL_ADDRESS_SIZE) == 0
-                || (value >> VIRTUAL_ADDRESS_SIZE) == (1 << 25) - 1
+            (value >> virtual_address_width) == 0
+                || (value >> virtual_address_width) == (1 << (64 - virtual_address_width)) - 1
         );
         Self { bits: value }
     }
@@ -87,10 +86,11 @@ impl From<VirtualAddress> for usize {
 
 impl From<PageNumber> for VirtualAddress {
     fn from(value: PageNumber) -> Self {
+        let virtual_address_width = paging_mode().virtual_address_width();
         let mut bits = usize::from(value) << PAGE_SIZE_BIT;
 
-        if (bits >> (VIRTUAL_ADDRESS_SIZE - 1)) == 1 {
-            bits |= !((1 << VIRTUAL_ADDRESS_SIZE) - 1);
+        if (bits >> (virtual_address_width - 1)) == 1 {
+            bits |= !((1 << virtual_address_width) - 1);
         }
         Self { bits }
     }
@@ -0,0 +1,178 @@
+//! The `asid` module provides an allocator of the address space identifiers (ASIDs), which tag the
+//! TLB entries of each address space so that switching between address spaces doesn't flush the
+//! TLB.
+//!
+//! The kernel uses the reserved ASID 0, and only the trampoline, which every address space maps at
+//! the same address, is marked as global. The ASIDs of the user address spaces are assigned lazily
+//! when the address spaces are activated. When the ASIDs run out, the allocator starts a new
+//! generation and flushes the whole TLB, and each address space gets a new ASID when it is
+//! activated again.
+
+use alloc::vec::Vec;
+use core::arch::asm;
+
+use riscv::register::satp;
+
+use crate::sync::Mutex;
+
+/// The offset of the ASID field in the `satp` register.
+pub const SATP_ASID_OFFSET: usize = 44;
+
+/// The maximum width of the ASID field in the `satp` register, in bits.
+const ASID_WIDTH: usize = 16;
+
+/// The `Asid` struct represents an ASID assigned in a specific generation.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Asid {
+    generation: usize,
+    number: usize,
+}
+
+impl Asid {
+    /// The ASID that hasn't been assigned, which never belongs to the current generation.
+    pub const UNASSIGNED: Self = Self {
+        generation: 0,
+        number: 0,
+    };
+
+    /// The ASID reserved for the kernel, which belongs to every generation.
+    pub const KERNEL: Self = Self {
+        generation: usize::MAX,
+        number: 0,
+    };
+
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    pub fn is_kernel(&self) -> bool {
+        *self == Self::KERNEL
+    }
+
+    /// Returns `true` if the ASID might tag entries in the TLB.
+    pub fn is_assigned(&self) -> bool {
+        *self != Self::UNASSIGNED
+    }
+}
+
+/// The `AsidAllocator` struct assigns the ASIDs from `1` to `asid_limit - 1` in generations.
+/// The ASIDs released in the current generation are recycled before a new generation starts.
+struct AsidAllocator {
+    generation: usize,
+    next_number: usize,
+    asid_limit: usize,
+    free_list: Vec<usize>,
+}
+
+impl AsidAllocator {
+    const fn new() -> Self {
+        Self {
+            generation: 1,
+            next_number: 1,
+            asid_limit: 1,
+            free_list: Vec::new(),
+        }
+    }
+
+    /// Returns `asid` if it belongs to the current generation, or assigns a new ASID otherwise.
+    fn refresh(&mut self, asid: Asid) -> Asid {
+        if asid.is_kernel() {
+            return asid;
+        }
+
+        // Without ASID support, every address space shares the ASID of the kernel, and the TLB is
+        // flushed whenever an address space is activated
+        if self.asid_limit == 1 {
+            flush_all();
+            return Asid {
+                generation: self.generation,
+                number: 0,
+            };
+        }
+
+        if asid.generation == self.generation {
+            return asid;
+        }
+
+        if let Some(number) = self.free_list.pop() {
+            return Asid {
+                generation: self.generation,
+                number,
+            };
+        }
+
+        if self.next_number == self.asid_limit {
+            // Starts a new generation, where every ASID of the previous generations is stale
+            self.generation += 1;
+            self.next_number = 1;
+            self.free_list.clear();
+            flush_all();
+        }
+
+        let number = self.next_number;
+        self.next_number += 1;
+        Asid {
+            generation: self.generation,
+            number,
+        }
+    }
+
+    fn deallocate(&mut self, asid: Asid) {
+        if asid.generation == self.generation && asid.number != 0 {
+            flush_asid(asid.number);
+            self.free_list.push(asid.number);
+        }
+    }
+}
+
+static ASID_ALLOCATOR: Mutex<AsidAllocator> = Mutex::new(AsidAllocator::new());
+
+/// Probes the width of the ASID field supported by the hardware, which writes ones to the field
+/// and reads back the bits that stick.
+pub fn init() {
+    let kernel_satp = satp::read().bits();
+    let asid_mask = ((1 << ASID_WIDTH) - 1) << SATP_ASID_OFFSET;
+    unsafe {
+        satp::write(kernel_satp | asid_mask);
+        let asid_width = ((satp::read().bits() & asid_mask) >> SATP_ASID_OFFSET).count_ones();
+        satp::write(kernel_satp);
+        asm!("sfence.vma");
+        ASID_ALLOCATOR.lock().asid_limit = 1 << asid_width;
+    }
+}
+
+/// Returns `asid` if it belongs to the current generation, or assigns a new ASID otherwise.
+pub fn refresh(asid: Asid) -> Asid {
+    ASID_ALLOCATOR.lock().refresh(asid)
+}
+
+/// Releases an ASID, and flushes its entries in the TLB so that the ASID can be recycled.
+pub fn deallocate(asid: Asid) {
+    ASID_ALLOCATOR.lock().deallocate(asid);
+}
+
+/// Flushes the entries of a page in the address space with a specific ASID. The global entries are
+/// flushed as well if the ASID belongs to the kernel.
+pub fn flush_page(asid: Asid, virtual_address: usize) {
+    unsafe {
+        if asid.is_kernel() {
+            asm!("sfence.vma {}, zero", in(reg) virtual_address);
+        } else {
+            asm!("sfence.vma {}, {}", in(reg) virtual_address, in(reg) asid.number());
+        }
+    }
+}
+
+/// Flushes the non-global entries of the address space with a specific ASID.
+fn flush_asid(number: usize) {
+    unsafe {
+        asm!("sfence.vma zero, {}", in(reg) number);
+    }
+}
+
+/// Flushes every entry in the TLB.
+fn flush_all() {
+    unsafe {
+        asm!("sfence.vma");
+    }
+}
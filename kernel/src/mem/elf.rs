@@ -0,0 +1,286 @@
+//! The `elf` module parses the ELF files that the kernel loads into user address spaces.
+//! An executable that requests an interpreter with `PT_INTERP` is loaded together with the
+//! interpreter, which starts first and finds the executable with the auxiliary vector. The kernel
+//! has no file system, so the interpreter must be one of the programs embedded in the kernel, and
+//! it is looked up by the last component of the requested path, regardless of the directories. The
+//! `PT_DYNAMIC` and `PT_TLS` segments are left to the interpreter or the C library, and the user
+//! stack is never executable regardless of `PT_GNU_STACK`.
+
+use xmas_elf::{
+    header::{self, Class},
+    program::Type,
+    sections::{SectionData, ShType, SHN_LORESERVE},
+    ElfFile,
+};
+
+use crate::{
+    constant::{ASLR_PIE_RANGE, PAGE_SIZE},
+    mem::{page_table::user_address_limit, LoadError, VirtualAddress},
+};
+
+/// The size of an entry of the program header table of a 64-bit ELF file.
+const PROGRAM_HEADER_SIZE: usize = 56;
+
+/// The size of an entry of the section header table of a 64-bit ELF file.
+const SECTION_HEADER_SIZE: usize = 64;
+
+/// The size of an `Elf64_Rela` relocation entry.
+const RELOCATION_SIZE: usize = 24;
+
+/// The `ElfImage` struct describes where an executable and its interpreter are loaded, which is
+/// passed to the program in the auxiliary vector.
+#[derive(Copy, Clone)]
+pub struct ElfImage {
+    program_break: VirtualAddress,
+    entry_point: VirtualAddress,
+    program_header_address: VirtualAddress,
+    program_header_count: usize,
+    interpreter: Option<(VirtualAddress, VirtualAddress)>,
+}
+
+impl ElfImage {
+    /// Creates an [ElfImage], where `interpreter` holds the base address and the entry point of the
+    /// interpreter if the executable has one.
+    pub fn new(
+        program_break: VirtualAddress,
+        entry_point: VirtualAddress,
+        program_header_address: VirtualAddress,
+        program_header_count: usize,
+        interpreter: Option<(VirtualAddress, VirtualAddress)>,
+    ) -> Self {
+        Self {
+            program_break,
+            entry_point,
+            program_header_address,
+            program_header_count,
+            interpreter,
+        }
+    }
+
+    /// Returns the start of the program break, which is above the loadable segments of the
+    /// executable.
+    pub fn program_break(&self) -> VirtualAddress {
+        self.program_break
+    }
+
+    /// Returns the entry point of the executable.
+    pub fn entry_point(&self) -> VirtualAddress {
+        self.entry_point
+    }
+
+    /// Returns the address where the program headers of the executable are mapped, or `0` if they
+    /// are not part of any loadable segment.
+    pub fn program_header_address(&self) -> VirtualAddress {
+        self.program_header_address
+    }
+
+    /// Returns the number of program headers of the executable.
+    pub fn program_header_count(&self) -> usize {
+        self.program_header_count
+    }
+
+    /// Returns the base address of the interpreter, or `None` if the executable has no
+    /// interpreter.
+    pub fn interpreter_base(&self) -> Option<VirtualAddress> {
+        self.interpreter.map(|(base, _)| base)
+    }
+
+    /// Returns the address where the thread starts, which is the entry point of the interpreter if
+    /// the executable has one.
+    pub fn start_address(&self) -> VirtualAddress {
+        self.interpreter
+            .map_or(self.entry_point, |(_, entry_point)| entry_point)
+    }
+}
+
+/// Returns `true` if the `length` bytes at `offset` lie within `elf_data` and are aligned to
+/// `alignment`, which the parser of the ELF file requires of its tables.
+fn is_in_file(elf_data: &[u8], offset: u64, length: u64, alignment: usize) -> bool {
+    offset as usize % alignment == 0
+        && offset
+            .checked_add(length)
+            .map_or(false, |end| end <= elf_data.len() as u64)
+}
+
+/// Returns `true` if a table of `count` entries of `entry_size` bytes at `offset` has entries of
+/// `expected_size` bytes and lies within `elf_data`.
+fn is_table_valid(
+    elf_data: &[u8],
+    offset: u64,
+    count: u16,
+    entry_size: u16,
+    expected_size: usize,
+) -> bool {
+    count == 0
+        || entry_size as usize == expected_size
+            && is_in_file(elf_data, offset, count as u64 * entry_size as u64, 8)
+}
+
+/// Parses the header of a 64-bit ELF file and checks its program headers and section headers, so
+/// that the file can be loaded without further checks. Returns an error if the file is not a valid
+/// ELF file, if a table or a loadable segment lies outside the file, if a loadable segment has
+/// more bytes in the file than in memory, or if a segment or the entry point lies outside user
+/// space.
+pub fn parse_elf(elf_data: &[u8]) -> Result<ElfFile, LoadError> {
+    let elf = ElfFile::new(elf_data).map_err(|_| LoadError::InvalidElf)?;
+    let header = &elf.header.pt2;
+    if elf.header.pt1.magic != [0x7f, b'E', b'L', b'F']
+        || elf.header.pt1.class() != Class::SixtyFour
+        || header.entry_point() >= user_address_limit() as u64
+        || !is_table_valid(
+            elf_data,
+            header.ph_offset(),
+            header.ph_count(),
+            header.ph_entry_size(),
+            PROGRAM_HEADER_SIZE,
+        )
+        || header.sh_count() >= SHN_LORESERVE
+        || !is_table_valid(
+            elf_data,
+            header.sh_offset(),
+            header.sh_count(),
+            header.sh_entry_size(),
+            SECTION_HEADER_SIZE,
+        )
+    {
+        return Err(LoadError::InvalidElf);
+    }
+
+    for program_header_index in 0..header.ph_count() {
+        let program_header = elf
+            .program_header(program_header_index)
+            .map_err(|_| LoadError::InvalidElf)?;
+        let program_type = program_header
+            .get_type()
+            .map_err(|_| LoadError::InvalidElf)?;
+        let is_in_user_space = program_header
+            .virtual_addr()
+            .checked_add(program_header.mem_size())
+            .map_or(false, |end| end <= user_address_limit() as u64);
+        if !is_in_user_space {
+            return Err(LoadError::InvalidElf);
+        }
+
+        let align = program_header.align() as usize;
+        if program_type == Type::Load
+            && (!is_in_file(
+                elf_data,
+                program_header.offset(),
+                program_header.file_size(),
+                1,
+            ) || program_header.file_size() > program_header.mem_size()
+                || align > 1 && (!align.is_power_of_two() || align > ASLR_PIE_RANGE))
+        {
+            return Err(LoadError::InvalidElf);
+        }
+    }
+    Ok(elf)
+}
+
+/// Returns the name of the interpreter that an ELF file requests with `PT_INTERP`, which is the
+/// last component of its path, or `None` if the file has no interpreter. The name is looked up
+/// among the embedded programs rather than in a file system. Returns an error if the file or its
+/// `PT_INTERP` segment is malformed.
+pub fn interpreter_name(elf_data: &[u8]) -> Result<Option<&str>, LoadError> {
+    let elf = parse_elf(elf_data)?;
+    let Some(program_header) = elf
+        .program_iter()
+        .find(|program_header| program_header.get_type() == Ok(Type::Interp))
+    else {
+        return Ok(None);
+    };
+
+    let start = program_header.offset() as usize;
+    let path = start
+        .checked_add(program_header.file_size() as usize)
+        .and_then(|end| elf_data.get(start..end))
+        .ok_or(LoadError::InvalidElf)?;
+    let path = path.split(|byte| *byte == 0).next().unwrap_or(path);
+    let path = core::str::from_utf8(path).map_err(|_| LoadError::InvalidElf)?;
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => Ok(Some(name)),
+        _ => Err(LoadError::InvalidElf),
+    }
+}
+
+/// Returns `true` if the ELF file is position-independent (`ET_DYN`), which can be loaded at any
+/// base address.
+pub fn is_position_independent(elf: &ElfFile) -> bool {
+    elf.header.pt2.type_().as_type() == header::Type::SharedObject
+}
+
+/// Returns the largest alignment of the loadable segments, which the base address of a
+/// position-independent ELF file must be aligned to.
+pub fn load_alignment(elf: &ElfFile) -> usize {
+    elf.program_iter()
+        .filter(|program_header| program_header.get_type() == Ok(Type::Load))
+        .map(|program_header| program_header.align() as usize)
+        .fold(PAGE_SIZE, usize::max)
+}
+
+/// Returns the address where the program headers of an ELF file loaded at `load_base` are mapped,
+/// which is given by `PT_PHDR` or found in the loadable segment that contains them. Returns `0` if
+/// the program headers are not loaded.
+pub fn program_header_address(elf: &ElfFile, load_base: usize) -> VirtualAddress {
+    let program_header_offset = elf.header.pt2.ph_offset() as usize;
+    let address = elf
+        .program_iter()
+        .find(|program_header| program_header.get_type() == Ok(Type::Phdr))
+        .map(|program_header| load_base + program_header.virtual_addr() as usize)
+        .or_else(|| {
+            elf.program_iter()
+                .find(|program_header| {
+                    let offset = program_header.offset() as usize;
+                    program_header.get_type() == Ok(Type::Load)
+                        && offset <= program_header_offset
+                        && program_header_offset < offset + program_header.file_size() as usize
+                })
+                .map(|program_header| {
+                    load_base + program_header.virtual_addr() as usize + program_header_offset
+                        - program_header.offset() as usize
+                })
+        });
+    VirtualAddress::from(address.unwrap_or(0))
+}
+
+/// Applies the `R_RISCV_RELATIVE` relocations of a position-independent executable loaded at
+/// `load_base` to the `bytes` of a loadable segment linked at `virtual_address`. Each relocation
+/// that targets the segment stores the sum of the base address and its addend. Returns an error if
+/// a relocation section lies outside the file.
+pub fn relocate(
+    elf: &ElfFile,
+    load_base: usize,
+    virtual_address: usize,
+    bytes: &mut [u8],
+) -> Result<(), LoadError> {
+    const R_RISCV_RELATIVE: u32 = 3;
+
+    for section_header in elf.section_iter() {
+        if section_header.get_type() != Ok(ShType::Rela) {
+            continue;
+        }
+
+        if section_header.size() as usize % RELOCATION_SIZE != 0
+            || !is_in_file(elf.input, section_header.offset(), section_header.size(), 8)
+        {
+            return Err(LoadError::InvalidElf);
+        }
+        let Ok(SectionData::Rela64(relocation_list)) = section_header.get_data(elf) else {
+            continue;
+        };
+        for relocation in relocation_list {
+            let offset = relocation.get_offset() as usize;
+            if relocation.get_type() != R_RISCV_RELATIVE
+                || offset < virtual_address
+                || offset.saturating_add(8) > virtual_address + bytes.len()
+            {
+                continue;
+            }
+
+            let value = load_base.wrapping_add(relocation.get_addend() as usize);
+            bytes[offset - virtual_address..offset - virtual_address + 8]
+                .copy_from_slice(&value.to_le_bytes());
+        }
+    }
+    Ok(())
+}
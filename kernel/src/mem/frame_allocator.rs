@@ -1,30 +1,63 @@
-//! The `frame_allocator` module provides a frame allocator for the kernel.
+//! The `frame_allocator` module provides a buddy frame allocator for the kernel.
+//! The allocator manages blocks of `2^order` physically contiguous frames, and both the allocation
+//! and the deallocation of a block take `O(log n)` time.
 
-use alloc::vec::Vec;
+use alloc::sync::Arc;
 
 use lazy_static::lazy_static;
 
 use crate::{
-    constant::MEM_LIMIT,
-    mem::{FrameNumber, PhysicalAddress},
+    constant::{MEM_LIMIT, MEM_START, PAGE_SIZE},
+    mem::{FrameNumber, HeapObject, ObjectTag, ObjectType, PhysicalAddress},
     sync::Mutex,
 };
 
-/// The `FrameTracker` struct represents a frame in the physical memory.
-/// It contains the frame number and is responsible for zeroing out the frame when it is created.
-/// It deallocates the frame when it is dropped, which follows the RAII idiom.
+/// The number of orders supported by the [BuddyFrameAllocator].
+/// The largest block contains `2^(MAX_ORDER - 1)` frames, which is 8 MiB. A block can back a 2 MiB
+/// megapage but not a 1 GiB gigapage, which is larger than the physical memory, so the gigapages
+/// only appear in the identical mappings of the kernel and never in the mappings backed by frames.
+const MAX_ORDER: usize = 12;
+
+/// The flag in the [BuddyFrameAllocator::block_state] of the first frame of an allocated block,
+/// whose lower bits hold the order of the block.
+const ALLOCATED: u8 = 0x80;
+
+/// The number of frames in the physical memory.
+const FRAME_COUNT: usize = (MEM_LIMIT - MEM_START) / PAGE_SIZE;
+
+/// The `OutOfMemory` struct is an error that indicates that the frame allocator has run out of
+/// frames.
+#[derive(Debug)]
+pub struct OutOfMemory;
+
+/// The `FrameTracker` struct represents a block of `2^order` physically contiguous frames.
+/// It contains the number of the first frame and is responsible for zeroing out the block when it
+/// is created. It deallocates the block when it is dropped, which follows the RAII idiom.
 pub struct FrameTracker {
     frame_number: FrameNumber,
+    order: usize,
+    _object_tag: ObjectTag<Self>,
+}
+
+impl HeapObject for FrameTracker {
+    const OBJECT_TYPE: ObjectType = ObjectType::FrameTracker;
 }
 
 impl FrameTracker {
-    /// Initializes a frame with a specific [FrameNumber] and zeros out the frame.
-    pub fn new(frame_number: FrameNumber) -> Self {
-        for byte in frame_number.as_bytes_mut() {
-            *byte = 0;
+    /// Initializes a block of `2^order` frames that starts with a specific [FrameNumber] and zeros
+    /// out the block.
+    pub fn new(frame_number: FrameNumber, order: usize) -> Self {
+        for offset in 0..1 << order {
+            for byte in (frame_number + offset).as_bytes_mut() {
+                *byte = 0;
+            }
         }
 
-        Self { frame_number }
+        Self {
+            frame_number,
+            order,
+            _object_tag: ObjectTag::new(),
+        }
     }
 
     pub fn frame_number(&self) -> FrameNumber {
@@ -34,64 +67,192 @@ impl FrameTracker {
 
 impl Drop for FrameTracker {
     fn drop(&mut self) {
-        deallocate_frame(self.frame_number)
+        deallocate_frames(self.frame_number, self.order)
     }
 }
 
 trait FrameAllocator {
     fn new() -> Self;
-    fn allocate(&mut self) -> Option<FrameNumber>;
-    fn deallocate(&mut self, frame_number: FrameNumber);
+    fn allocate(&mut self, order: usize) -> Option<FrameNumber>;
+    fn deallocate(&mut self, frame_number: FrameNumber, order: usize);
 }
 
-pub struct StackFrameAllocator {
+/// The `FreeBlock` struct is stored in the first frame of a free block, which links the block to
+/// the other free blocks of the same order.
+#[derive(Copy, Clone)]
+struct FreeBlock {
+    prev: Option<FrameNumber>,
+    next: Option<FrameNumber>,
+}
+
+/// The `BuddyFrameAllocator` struct is a buddy allocator that manages the frames from
+/// `frame_start` to `frame_end`. The free blocks of each order are linked in an intrusive doubly
+/// linked list, so the allocator doesn't depend on the kernel heap.
+pub struct BuddyFrameAllocator {
     frame_start: FrameNumber,
     frame_end: FrameNumber,
-    deallocated_page: Vec<FrameNumber>,
+    free_list: [Option<FrameNumber>; MAX_ORDER],
+    free_frame_count: usize,
+    /// The `block_state` array stores `order + 1` for the first frame of each free block,
+    /// `ALLOCATED | order` for the first frame of each allocated block, and `0` for the other
+    /// frames.
+    block_state: [u8; FRAME_COUNT],
 }
 
-impl StackFrameAllocator {
+impl BuddyFrameAllocator {
     fn init(&mut self, frame_start: FrameNumber, frame_end: FrameNumber) {
         self.frame_start = frame_start;
         self.frame_end = frame_end;
+
+        let mut frame_number = frame_start;
+        while frame_number < frame_end {
+            let mut order = MAX_ORDER - 1;
+            while usize::from(frame_number) & ((1 << order) - 1) != 0
+                || frame_number + (1 << order) > frame_end
+            {
+                order -= 1;
+            }
+
+            self.push(frame_number, order);
+            frame_number += 1 << order;
+        }
+    }
+
+    fn index(frame_number: FrameNumber) -> usize {
+        usize::from(frame_number) - usize::from(PhysicalAddress::from(MEM_START).floor())
+    }
+
+    fn block(frame_number: FrameNumber) -> &'static mut FreeBlock {
+        PhysicalAddress::from(frame_number).as_mut()
+    }
+
+    fn is_free(&self, frame_number: FrameNumber, order: usize) -> bool {
+        self.frame_start <= frame_number
+            && frame_number < self.frame_end
+            && self.block_state[Self::index(frame_number)] as usize == order + 1
+    }
+
+    /// Returns `true` if the block of `2^order` frames that starts with a specific [FrameNumber]
+    /// has been allocated with the same order, so that freeing it twice or freeing a part of a
+    /// block is detected.
+    fn is_allocated(&self, frame_number: FrameNumber, order: usize) -> bool {
+        order < MAX_ORDER
+            && self.frame_start <= frame_number
+            && frame_number < self.frame_end
+            && self.block_state[Self::index(frame_number)] == ALLOCATED | order as u8
+    }
+
+    /// Inserts a free block at the front of the free list of a specific order.
+    fn push(&mut self, frame_number: FrameNumber, order: usize) {
+        let next = self.free_list[order];
+        if let Some(next) = next {
+            Self::block(next).prev = Some(frame_number);
+        }
+
+        *Self::block(frame_number) = FreeBlock { prev: None, next };
+        self.free_list[order] = Some(frame_number);
+        self.block_state[Self::index(frame_number)] = order as u8 + 1;
+        self.free_frame_count += 1 << order;
+    }
+
+    /// Removes a free block from the free list of a specific order.
+    fn remove(&mut self, frame_number: FrameNumber, order: usize) {
+        let FreeBlock { prev, next } = *Self::block(frame_number);
+        match prev {
+            Some(prev) => Self::block(prev).next = next,
+            None => self.free_list[order] = next,
+        }
+
+        if let Some(next) = next {
+            Self::block(next).prev = prev;
+        }
+        self.block_state[Self::index(frame_number)] = 0;
+        self.free_frame_count -= 1 << order;
     }
 }
 
-impl FrameAllocator for StackFrameAllocator {
+impl FrameAllocator for BuddyFrameAllocator {
     fn new() -> Self {
-        StackFrameAllocator {
+        Self {
             frame_start: FrameNumber::from(0),
             frame_end: FrameNumber::from(0),
-            deallocated_page: Vec::new(),
+            free_list: [None; MAX_ORDER],
+            free_frame_count: 0,
+            block_state: [0; FRAME_COUNT],
         }
     }
 
-    fn allocate(&mut self) -> Option<FrameNumber> {
-        if let Some(frame_number) = self.deallocated_page.pop() {
-            Some(frame_number)
-        } else if self.frame_start == self.frame_end {
-            None
-        } else {
-            let result = Some(self.frame_start);
-            self.frame_start += 1;
-            result
+    fn allocate(&mut self, order: usize) -> Option<FrameNumber> {
+        let block_order = (order..MAX_ORDER).find(|&order| self.free_list[order].is_some())?;
+        let frame_number = self.free_list[block_order].unwrap();
+        self.remove(frame_number, block_order);
+
+        // Splits the block and returns the upper halves to the free lists
+        for split_order in (order..block_order).rev() {
+            self.push(frame_number + (1 << split_order), split_order);
         }
+        self.block_state[Self::index(frame_number)] = ALLOCATED | order as u8;
+        Some(frame_number)
     }
 
-    fn deallocate(&mut self, frame_number: FrameNumber) {
-        if self.frame_start <= frame_number || self.deallocated_page.contains(&frame_number) {
+    fn deallocate(&mut self, frame_number: FrameNumber, order: usize) {
+        if !self.is_allocated(frame_number, order) {
             panic!(
-                "the frame {:#x} has not been allocated",
+                "the block of order {} at the frame {:#x} has not been allocated",
+                order,
                 usize::from(frame_number)
             )
         }
-        self.deallocated_page.push(frame_number);
+
+        self.block_state[Self::index(frame_number)] = 0;
+
+        // Merges the block with its buddy as long as the buddy is free
+        let mut frame_number = frame_number;
+        let mut order = order;
+        while order < MAX_ORDER - 1 {
+            let buddy = FrameNumber::from(usize::from(frame_number) ^ (1 << order));
+            if !self.is_free(buddy, order) {
+                break;
+            }
+
+            self.remove(buddy, order);
+            frame_number = frame_number.min(buddy);
+            order += 1;
+        }
+        self.push(frame_number, order);
     }
 }
 
 lazy_static! {
-    pub static ref FRAME_ALLOCATOR: Mutex<StackFrameAllocator> =
-        Mutex::new(StackFrameAllocator::new());
+    pub static ref FRAME_ALLOCATOR: Mutex<BuddyFrameAllocator> =
+        Mutex::new(BuddyFrameAllocator::new());
+}
+
+lazy_static! {
+    /// The frame filled with zeros that backs the user pages that have never been written, which
+    /// is mapped without write permission and copied on the first write.
+    static ref ZERO_FRAME: Arc<FrameTracker> =
+        Arc::new(allocate_frame().expect("failed to allocate the zero frame"));
+}
+
+/// Returns the shared zero frame.
+pub fn zero_frame() -> Arc<FrameTracker> {
+    ZERO_FRAME.clone()
+}
+
+/// Returns `true` if the [FrameTracker] tracks the shared zero frame.
+pub fn is_zero_frame(frame: &Arc<FrameTracker>) -> bool {
+    Arc::ptr_eq(frame, &ZERO_FRAME)
+}
+
+/// The function that reclaims frames when the kernel itself runs out of frames, which returns
+/// `true` if any frame might have been released.
+static RECLAIM_HANDLER: Mutex<Option<fn() -> bool>> = Mutex::new(None);
+
+/// Sets the function that reclaims frames when the kernel itself runs out of frames, such as the
+/// OOM killer.
+pub fn set_reclaim_handler(reclaim_handler: fn() -> bool) {
+    *RECLAIM_HANDLER.lock() = Some(reclaim_handler);
 }
 
 /// Initializes a frame allocator that manages the physical address from `kernel_end` to
@@ -109,10 +270,56 @@ pub fn init() {
 
 /// Allocates a frame and returns a [FrameTracker] to track the allocated frame when succeeded.
 pub fn allocate_frame() -> Option<FrameTracker> {
-    FRAME_ALLOCATOR.lock().allocate().map(FrameTracker::new)
+    allocate_frames(0)
+}
+
+/// Allocates a block of `2^order` physically contiguous frames and returns a [FrameTracker] to
+/// track the allocated block when succeeded, which is useful for DMA buffers and huge pages. The
+/// block has at most `2^(MAX_ORDER - 1)` frames, which backs a megapage but not a gigapage.
+pub fn allocate_frames(order: usize) -> Option<FrameTracker> {
+    let frame_number = FRAME_ALLOCATOR.lock().allocate(order)?;
+    Some(FrameTracker::new(frame_number, order))
+}
+
+/// Allocates a block of `2^order` physically contiguous frames for the kernel itself. Invokes the
+/// reclaim handler and retries as long as the handler releases frames that the block still lacks.
+/// Fails at once if the block is larger than the memory managed by the allocator, and stops
+/// reclaiming if enough frames are already free but too fragmented to form the block, since
+/// releasing more frames, which might kill processes, isn't guaranteed to help.
+pub fn allocate_kernel_frames(order: usize) -> Option<FrameTracker> {
+    let frame_count = {
+        let frame_allocator = FRAME_ALLOCATOR.lock();
+        usize::from(frame_allocator.frame_end) - usize::from(frame_allocator.frame_start)
+    };
+    if order >= MAX_ORDER || 1 << order > frame_count {
+        return None;
+    }
+
+    loop {
+        if let Some(frame) = allocate_frames(order) {
+            return Some(frame);
+        }
+
+        let previous_free_frame_count = free_frame_count();
+        if previous_free_frame_count >= 1 << order {
+            return None;
+        }
+
+        let reclaim_handler = *RECLAIM_HANDLER.lock();
+        if !reclaim_handler.map_or(false, |reclaim_handler| reclaim_handler())
+            || free_frame_count() <= previous_free_frame_count
+        {
+            return None;
+        }
+    }
+}
+
+/// Returns the number of free frames.
+pub fn free_frame_count() -> usize {
+    FRAME_ALLOCATOR.lock().free_frame_count
 }
 
-/// Deallocates the frame with a specific [FrameNumber].
-pub fn deallocate_frame(frame_number: FrameNumber) {
-    FRAME_ALLOCATOR.lock().deallocate(frame_number);
+/// Deallocates the block of `2^order` frames that starts with a specific [FrameNumber].
+fn deallocate_frames(frame_number: FrameNumber, order: usize) {
+    FRAME_ALLOCATOR.lock().deallocate(frame_number, order);
 }
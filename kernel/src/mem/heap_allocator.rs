@@ -1,28 +1,538 @@
 //! The `heap_allocator` module provides a heap allocator for the kernel.
-//! The heap is initialized with a fixed size as the [KERNEL_HEAP_SIZE] constant.
+//! The heap is initialized with a fixed size as the [KERNEL_HEAP_SIZE] constant, and grows by
+//! requesting blocks of frames from the frame allocator when it runs out of memory.
+//!
+//! Small allocations, such as threads, processes, and page segments, are served by the slab caches
+//! of fixed size classes in `O(1)` time. Each slab cache carves slabs borrowed from the linked-list
+//! heap into objects of the same size, and returns an empty slab to the linked-list heap once it
+//! keeps more than [EMPTY_SLAB_WATERMARK] empty slabs. The linked-list heap only serves the
+//! allocations that are larger than the largest size class.
+//!
+//! The allocator can't tell the type of an object from its layout, so the kernel objects that
+//! matter for memory usage carry an [ObjectTag], which counts the live objects of each type.
+//!
+//! The heap grows with [allocate_kernel_frames], which might invoke the OOM killer. Since the OOM
+//! killer releases memory of other processes, which deallocates memory from the heap, no lock of
+//! the heap is held while the heap grows.
 
-use core::alloc::Layout;
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    array,
+    marker::PhantomData,
+    mem::size_of,
+    ptr::{null_mut, NonNull},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use linked_list_allocator::LockedHeap;
+use linked_list_allocator::Heap;
+use log::info;
 
-use crate::constant::KERNEL_HEAP_SIZE;
+use crate::{
+    constant::{KERNEL_HEAP_SIZE, PAGE_SIZE},
+    mem::{
+        frame_allocator::{allocate_kernel_frames, FrameTracker},
+        PhysicalAddress,
+    },
+    sync::Mutex,
+};
+
+/// The object sizes of the slab caches, in bytes.
+const SLAB_SIZE_CLASS: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+
+/// The number of pages in a slab.
+const SLAB_PAGE_COUNT: usize = 4;
+
+/// The size of a slab, which is also its alignment, so that the header of a slab can be found from
+/// the address of any of its objects.
+const SLAB_SIZE: usize = SLAB_PAGE_COUNT * PAGE_SIZE;
+
+/// The number of empty slabs that a slab cache keeps for later allocations.
+const EMPTY_SLAB_WATERMARK: usize = 2;
+
+/// The maximum number of regions in the linked-list heap.
+const HEAP_REGION_LIMIT: usize = 64;
+
+/// The minimum order of the blocks of frames requested when the heap grows.
+const HEAP_GROWTH_ORDER: usize = 4;
 
 #[global_allocator]
-static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static HEAP_ALLOCATOR: KernelHeap = KernelHeap::empty();
 
 static mut KERNEL_HEAP: [u8; KERNEL_HEAP_SIZE] = [0; KERNEL_HEAP_SIZE];
 
+/// The `SlabStatistics` struct represents the memory usage of a slab cache.
+pub struct SlabStatistics {
+    /// The size of each object in the slab cache, in bytes.
+    pub object_size: usize,
+    /// The number of pages borrowed from the linked-list heap.
+    pub page_count: usize,
+    /// The number of objects that are currently allocated.
+    pub object_count: usize,
+}
+
+/// The `Slab` struct is the header at the start of a slab, which holds the free objects of the slab
+/// in a singly linked free list, where each free object stores the address of the next free
+/// object. The slabs that have free objects are linked in a doubly linked list.
+struct Slab {
+    free_list: usize,
+    object_count: usize,
+    prev: usize,
+    next: usize,
+}
+
+/// The `SlabCache` struct manages objects of the same size in slabs of [SLAB_SIZE] bytes.
+struct SlabCache {
+    object_size: usize,
+    /// The address of the first slab that has free objects, or `0` if every slab is full.
+    partial_list: usize,
+    slab_count: usize,
+    empty_slab_count: usize,
+    object_count: usize,
+}
+
+impl SlabCache {
+    const fn new(object_size: usize) -> Self {
+        Self {
+            object_size,
+            partial_list: 0,
+            slab_count: 0,
+            empty_slab_count: 0,
+            object_count: 0,
+        }
+    }
+
+    fn slab(address: usize) -> &'static mut Slab {
+        unsafe { &mut *(address as *mut Slab) }
+    }
+
+    /// Returns the offset of the first object in a slab, which follows the header.
+    fn object_offset(&self) -> usize {
+        self.object_size.max(size_of::<Slab>())
+    }
+
+    /// Carves a slab borrowed from the linked-list heap into objects and inserts it into the list
+    /// of the slabs that have free objects.
+    fn refill(&mut self, slab: *mut u8) {
+        let address = slab as usize;
+        *Self::slab(address) = Slab {
+            free_list: 0,
+            object_count: 0,
+            prev: 0,
+            next: 0,
+        };
+        for offset in (self.object_offset()..SLAB_SIZE)
+            .step_by(self.object_size)
+            .rev()
+        {
+            Self::push(Self::slab(address), address + offset);
+        }
+
+        self.link(address);
+        self.slab_count += 1;
+        self.empty_slab_count += 1;
+    }
+
+    /// Allocates an object from the first slab that has free objects, or returns a null pointer if
+    /// every slab is full.
+    fn allocate(&mut self) -> *mut u8 {
+        if self.partial_list == 0 {
+            return null_mut();
+        }
+
+        let address = self.partial_list;
+        let slab = Self::slab(address);
+        let object = slab.free_list;
+        slab.free_list = unsafe { (object as *const usize).read() };
+        if slab.object_count == 0 {
+            self.empty_slab_count -= 1;
+        }
+        slab.object_count += 1;
+        if slab.free_list == 0 {
+            self.unlink(address);
+        }
+
+        self.object_count += 1;
+        object as *mut u8
+    }
+
+    /// Returns an object to its slab. Returns the slab if it becomes empty while the cache already
+    /// keeps [EMPTY_SLAB_WATERMARK] empty slabs, which should be returned to the linked-list heap.
+    fn deallocate(&mut self, object: *mut u8) -> Option<*mut u8> {
+        let address = object as usize & !(SLAB_SIZE - 1);
+        let slab = Self::slab(address);
+        let is_full = slab.free_list == 0;
+        Self::push(slab, object as usize);
+        slab.object_count -= 1;
+        self.object_count -= 1;
+        if is_full {
+            self.link(address);
+        }
+
+        if slab.object_count > 0 {
+            return None;
+        }
+        if self.empty_slab_count < EMPTY_SLAB_WATERMARK {
+            self.empty_slab_count += 1;
+            return None;
+        }
+
+        self.unlink(address);
+        self.slab_count -= 1;
+        Some(address as *mut u8)
+    }
+
+    /// Inserts an object at the front of the free list of a slab.
+    fn push(slab: &mut Slab, object: usize) {
+        unsafe {
+            (object as *mut usize).write(slab.free_list);
+        }
+        slab.free_list = object;
+    }
+
+    /// Inserts a slab at the front of the list of the slabs that have free objects.
+    fn link(&mut self, address: usize) {
+        let slab = Self::slab(address);
+        slab.prev = 0;
+        slab.next = self.partial_list;
+        if self.partial_list != 0 {
+            Self::slab(self.partial_list).prev = address;
+        }
+        self.partial_list = address;
+    }
+
+    /// Removes a slab from the list of the slabs that have free objects.
+    fn unlink(&mut self, address: usize) {
+        let Slab { prev, next, .. } = *Self::slab(address);
+        match prev {
+            0 => self.partial_list = next,
+            prev => Self::slab(prev).next = next,
+        }
+        if next != 0 {
+            Self::slab(next).prev = prev;
+        }
+    }
+
+    fn statistics(&self) -> SlabStatistics {
+        SlabStatistics {
+            object_size: self.object_size,
+            page_count: self.slab_count * SLAB_PAGE_COUNT,
+            object_count: self.object_count,
+        }
+    }
+}
+
+/// The `ObjectType` enum represents the types of the kernel objects that are counted in the heap
+/// statistics.
+#[derive(Clone, Copy)]
+pub enum ObjectType {
+    Process,
+    Thread,
+    PageSegment,
+    FrameTracker,
+}
+
+impl ObjectType {
+    const LIST: [ObjectType; 4] = [
+        ObjectType::Process,
+        ObjectType::Thread,
+        ObjectType::PageSegment,
+        ObjectType::FrameTracker,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ObjectType::Process => "Process",
+            ObjectType::Thread => "Thread",
+            ObjectType::PageSegment => "PageSegment",
+            ObjectType::FrameTracker => "FrameTracker",
+        }
+    }
+}
+
+/// The `ObjectCounter` struct counts the live objects of a type and records the size of the type.
+struct ObjectCounter {
+    object_count: AtomicUsize,
+    object_size: AtomicUsize,
+}
+
+impl ObjectCounter {
+    const fn new() -> Self {
+        Self {
+            object_count: AtomicUsize::new(0),
+            object_size: AtomicUsize::new(0),
+        }
+    }
+}
+
+static OBJECT_COUNTER_LIST: [ObjectCounter; ObjectType::LIST.len()] =
+    [const { ObjectCounter::new() }; ObjectType::LIST.len()];
+
+/// The `HeapObject` trait is implemented by the kernel objects that carry an [ObjectTag].
+pub trait HeapObject {
+    const OBJECT_TYPE: ObjectType;
+}
+
+/// The `ObjectTag` struct is a zero-sized field of a [HeapObject], which counts the object from its
+/// creation until it is dropped. Cloning the tag counts the clone of the object.
+pub struct ObjectTag<T: HeapObject> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: HeapObject> ObjectTag<T> {
+    pub fn new() -> Self {
+        let counter = &OBJECT_COUNTER_LIST[T::OBJECT_TYPE as usize];
+        counter.object_size.store(size_of::<T>(), Ordering::Relaxed);
+        counter.object_count.fetch_add(1, Ordering::Relaxed);
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: HeapObject> Clone for ObjectTag<T> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<T: HeapObject> Drop for ObjectTag<T> {
+    fn drop(&mut self) {
+        OBJECT_COUNTER_LIST[T::OBJECT_TYPE as usize]
+            .object_count
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The `HeapRegion` struct represents a region of the linked-list heap. Each region except the
+/// initial one is backed by a block of frames, which is returned to the frame allocator when the
+/// region becomes empty.
+struct HeapRegion {
+    heap: Heap,
+    frame: Option<FrameTracker>,
+}
+
+impl HeapRegion {
+    const EMPTY: Self = Self {
+        heap: Heap::empty(),
+        frame: None,
+    };
+
+    fn is_empty(&self) -> bool {
+        self.heap.size() == 0
+    }
+
+    fn contains(&self, ptr: *mut u8) -> bool {
+        self.heap.bottom() <= ptr && ptr < self.heap.top()
+    }
+}
+
+/// The `HeapRegionList` struct is a linked-list heap that consists of multiple disjoint regions.
+struct HeapRegionList {
+    region_list: Mutex<[HeapRegion; HEAP_REGION_LIMIT]>,
+}
+
+impl HeapRegionList {
+    const fn empty() -> Self {
+        Self {
+            region_list: Mutex::new([HeapRegion::EMPTY; HEAP_REGION_LIMIT]),
+        }
+    }
+
+    /// Allocates memory with the layout from the regions, and grows the heap with a block of frames
+    /// if none of the regions can satisfy the layout. Returns a null pointer if the frame
+    /// allocator runs out of frames.
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        if let Some(ptr) = self.allocate_first_fit(layout) {
+            return ptr;
+        }
+
+        if self.grow(layout) {
+            self.allocate_first_fit(layout).unwrap_or(null_mut())
+        } else {
+            null_mut()
+        }
+    }
+
+    fn allocate_first_fit(&self, layout: Layout) -> Option<*mut u8> {
+        self.region_list
+            .lock()
+            .iter_mut()
+            .filter(|region| !region.is_empty())
+            .find_map(|region| region.heap.allocate_first_fit(layout).ok())
+            .map(NonNull::as_ptr)
+    }
+
+    /// Adds a region backed by a block of frames that is large enough for the layout.
+    fn grow(&self, layout: Layout) -> bool {
+        let page_count = (layout.size() + layout.align() + PAGE_SIZE - 1) / PAGE_SIZE;
+        let order = HEAP_GROWTH_ORDER.max(page_count.next_power_of_two().trailing_zeros() as usize);
+
+        let Some(frame) = allocate_kernel_frames(order) else {
+            return false;
+        };
+
+        let mut region_list = self.region_list.lock();
+        let Some(region) = region_list.iter_mut().find(|region| region.is_empty()) else {
+            return false;
+        };
+        unsafe {
+            region.heap.init(
+                PhysicalAddress::from(frame.frame_number()).as_ptr_mut(),
+                PAGE_SIZE << order,
+            );
+        }
+        region.frame = Some(frame);
+        true
+    }
+
+    fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        let mut region_list = self.region_list.lock();
+        let region = region_list
+            .iter_mut()
+            .find(|region| !region.is_empty() && region.contains(ptr))
+            .unwrap();
+        unsafe {
+            region.heap.deallocate(NonNull::new_unchecked(ptr), layout);
+        }
+
+        if region.frame.is_some() && region.heap.used() == 0 {
+            *region = HeapRegion::EMPTY;
+        }
+    }
+
+    fn used(&self) -> usize {
+        self.region_list
+            .lock()
+            .iter()
+            .map(|region| region.heap.used())
+            .sum()
+    }
+
+    fn size(&self) -> usize {
+        self.region_list
+            .lock()
+            .iter()
+            .map(|region| region.heap.size())
+            .sum()
+    }
+}
+
+/// The `KernelHeap` struct is the global allocator of the kernel, which dispatches an allocation
+/// to the slab cache of the smallest size class that fits the layout, or to the linked-list heap
+/// if the layout is larger than every size class.
+struct KernelHeap {
+    slab_list: Mutex<[SlabCache; SLAB_SIZE_CLASS.len()]>,
+    heap: HeapRegionList,
+}
+
+impl KernelHeap {
+    const SLAB_LAYOUT: Layout = unsafe { Layout::from_size_align_unchecked(SLAB_SIZE, SLAB_SIZE) };
+
+    const fn empty() -> Self {
+        Self {
+            slab_list: Mutex::new([
+                SlabCache::new(SLAB_SIZE_CLASS[0]),
+                SlabCache::new(SLAB_SIZE_CLASS[1]),
+                SlabCache::new(SLAB_SIZE_CLASS[2]),
+                SlabCache::new(SLAB_SIZE_CLASS[3]),
+                SlabCache::new(SLAB_SIZE_CLASS[4]),
+                SlabCache::new(SLAB_SIZE_CLASS[5]),
+                SlabCache::new(SLAB_SIZE_CLASS[6]),
+                SlabCache::new(SLAB_SIZE_CLASS[7]),
+            ]),
+            heap: HeapRegionList::empty(),
+        }
+    }
+
+    /// Returns the index of the smallest size class that fits the layout.
+    fn size_class(layout: &Layout) -> Option<usize> {
+        let size = layout.size().max(layout.align());
+        SLAB_SIZE_CLASS
+            .iter()
+            .position(|&object_size| object_size >= size)
+    }
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(index) = Self::size_class(&layout) else {
+            return self.heap.allocate(layout);
+        };
+
+        let object = self.slab_list.lock()[index].allocate();
+        if !object.is_null() {
+            return object;
+        }
+
+        let slab = self.heap.allocate(Self::SLAB_LAYOUT);
+        if slab.is_null() {
+            return null_mut();
+        }
+
+        let mut slab_list = self.slab_list.lock();
+        slab_list[index].refill(slab);
+        slab_list[index].allocate()
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let Some(index) = Self::size_class(&layout) else {
+            return self.heap.deallocate(ptr, layout);
+        };
+
+        // The lock of the slab caches is released before the slab is returned to the heap
+        let slab = self.slab_list.lock()[index].deallocate(ptr);
+        if let Some(slab) = slab {
+            self.heap.deallocate(slab, Self::SLAB_LAYOUT);
+        }
+    }
+}
+
 /// Initializes the kernel heap with a fixed size as the [KERNEL_HEAP_SIZE]
 /// constant. This function must be called before the heap can be used.
 pub fn init() {
     unsafe {
-        HEAP_ALLOCATOR
-            .lock()
+        HEAP_ALLOCATOR.heap.region_list.lock()[0]
+            .heap
             .init(KERNEL_HEAP.as_mut_ptr(), KERNEL_HEAP_SIZE);
     }
 }
 
-/// Panics when heap allocation fails.
+/// Returns the memory usage of each slab cache.
+pub fn slab_statistics() -> [SlabStatistics; SLAB_SIZE_CLASS.len()] {
+    let slab_list = HEAP_ALLOCATOR.slab_list.lock();
+    array::from_fn(|index| slab_list[index].statistics())
+}
+
+/// Prints the number and the size of the live objects of each [ObjectType], the memory usage of
+/// each slab cache, and the memory usage of the linked-list heap.
+pub fn print_heap_statistics() {
+    for object_type in ObjectType::LIST {
+        let counter = &OBJECT_COUNTER_LIST[object_type as usize];
+        info!(
+            "{}: {} objects of {} bytes",
+            object_type.name(),
+            counter.object_count.load(Ordering::Relaxed),
+            counter.object_size.load(Ordering::Relaxed),
+        );
+    }
+
+    for statistics in slab_statistics() {
+        info!(
+            "slab cache of {} bytes: {} objects in {} pages",
+            statistics.object_size, statistics.object_count, statistics.page_count,
+        );
+    }
+
+    info!(
+        "linked-list heap: {} of {} bytes used",
+        HEAP_ALLOCATOR.heap.used(),
+        HEAP_ALLOCATOR.heap.size()
+    );
+}
+
+/// Panics when heap allocation fails, which happens when both the kernel heap and the frame
+/// allocator run out of memory. Callers that can handle the failure should use fallible APIs such
+/// as `Vec::try_reserve` instead.
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: Layout) -> ! {
     panic!("failed to allocate the desired layout {:?}", layout);
@@ -0,0 +1,83 @@
+//! The `layout` module chooses where the parts of a user address space are placed.
+//! With address space layout randomization, the position-independent executables, the program
+//! break, the anonymous memory mappings, and the user stacks are shifted by random offsets, so
+//! that a user program can't rely on the addresses of its memory. The randomization is disabled if
+//! the kernel is booted with `aslr=off` in its command line, which makes the layout reproducible
+//! for tests.
+
+use crate::{
+    boot_args,
+    constant::{
+        ASLR_BRK_RANGE,
+        ASLR_MMAP_RANGE,
+        ASLR_PIE_RANGE,
+        ASLR_STACK_RANGE,
+        PAGE_SIZE,
+        USER_MMAP_BASE,
+        USER_PIE_BASE,
+    },
+    mem::{page_table::user_stack_base, VirtualAddress},
+    random,
+};
+
+/// Returns `true` if the layout of the user address spaces is randomized, which is the case unless
+/// the kernel is booted with `aslr=off`.
+fn is_aslr_enabled() -> bool {
+    boot_args::get("aslr").as_deref() != Some("off")
+}
+
+/// Returns a random offset below `range` that is a multiple of `alignment`, or `0` if the
+/// randomization is disabled.
+fn random_offset(range: usize, alignment: usize) -> usize {
+    if !is_aslr_enabled() {
+        return 0;
+    }
+    random::random_usize() % (range / alignment) * alignment
+}
+
+/// Returns the address where a position-independent executable is loaded, which is aligned to
+/// `alignment`.
+pub fn pie_base(alignment: usize) -> VirtualAddress {
+    VirtualAddress::from(USER_PIE_BASE + random_offset(ASLR_PIE_RANGE, alignment))
+}
+
+/// Returns the start of the program break of an executable whose loadable segments end at
+/// `end_address`.
+pub fn program_break_start(end_address: VirtualAddress) -> VirtualAddress {
+    end_address + random_offset(ASLR_BRK_RANGE, PAGE_SIZE)
+}
+
+/// The `UserLayout` struct represents the bases of the anonymous memory mappings and the user
+/// stacks of a process, which are chosen when the process loads an executable and inherited by its
+/// child processes. The mappings are placed between the two bases.
+#[derive(Copy, Clone)]
+pub struct UserLayout {
+    mmap_base: VirtualAddress,
+    user_stack_base: VirtualAddress,
+}
+
+impl UserLayout {
+    /// Chooses the bases for a new address space.
+    pub fn new() -> Self {
+        Self {
+            mmap_base: VirtualAddress::from(
+                USER_MMAP_BASE + random_offset(ASLR_MMAP_RANGE, PAGE_SIZE),
+            ),
+            user_stack_base: VirtualAddress::from(
+                user_stack_base() - random_offset(ASLR_STACK_RANGE, PAGE_SIZE),
+            ),
+        }
+    }
+
+    /// Returns the base address of the anonymous memory mappings, which is also the upper limit of
+    /// the program break.
+    pub fn mmap_base(&self) -> VirtualAddress {
+        self.mmap_base
+    }
+
+    /// Returns the base address of the user stacks, which is also the upper limit of the anonymous
+    /// memory mappings.
+    pub fn user_stack_base(&self) -> VirtualAddress {
+        self.user_stack_base
+    }
+}
@@ -1,17 +1,45 @@
 mod address;
+mod asid;
+mod elf;
 mod frame_allocator;
 mod heap_allocator;
+mod layout;
 mod page_table;
 mod segment;
+mod shared_memory;
+mod swap;
 mod user_ptr;
 
 pub use address::{FrameNumber, PageNumber, PhysicalAddress, VirtualAddress};
-pub use frame_allocator::deallocate_frame;
-pub use segment::{MapPermission, PageSet, KERNEL_SPACE};
-pub use user_ptr::UserPtr;
+pub use elf::{interpreter_name, ElfImage};
+pub use frame_allocator::{
+    allocate_kernel_frames,
+    free_frame_count,
+    set_reclaim_handler,
+    FrameTracker,
+    OutOfMemory,
+};
+pub use heap_allocator::{print_heap_statistics, HeapObject, ObjectTag, ObjectType};
+pub use layout::UserLayout;
+pub use page_table::{user_address_limit, user_stack_base, PageSize};
+pub use segment::{LoadError, MapPermission, PageFaultError, PageSet, KERNEL_SPACE};
+pub use shared_memory::{
+    create_shared_memory,
+    find_shared_memory,
+    get_shared_memory,
+    remove_shared_memory,
+    shared_memory_page_count,
+    IPC_PRIVATE,
+};
+pub use swap::is_swap_enabled;
+pub use user_ptr::{copy_from_user, strncpy_from_user, UserAccess, UserAccessError, UserPtr};
 
 pub fn init() {
     heap_allocator::init();
     frame_allocator::init();
+    page_table::probe_paging_mode();
     KERNEL_SPACE.lock().init();
+    user_ptr::init();
+    asid::init();
+    swap::init();
 }
@@ -1,18 +1,28 @@
-//! The `page_table` module defines a 3-level page table
-//! that follows the RISC-V Sv39 page table specification.
-//! The page table supports 512 GB of virtual-address space.
+//! The `page_table` module defines a multi-level page table
+//! that follows the RISC-V Sv39 or Sv48 page table specification.
+//! The paging mode is probed at boot, which supports 512 GB of virtual-address space with the
+//! 3-level Sv39 or 256 TB with the 4-level Sv48. The virtual-address space can be mapped with
+//! 4 KiB pages, 2 MiB megapages, or 1 GiB gigapages.
 
 #![macro_use]
 use alloc::{vec, vec::Vec};
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use bitflags::bitflags;
+use riscv::register::satp;
 
-use crate::mem::{
-    frame_allocator::{allocate_frame, FrameTracker},
-    FrameNumber,
-    PageNumber,
-    PhysicalAddress,
-    VirtualAddress,
+use crate::{
+    constant::PAGE_SIZE_BIT,
+    mem::{
+        asid::{self, Asid, SATP_ASID_OFFSET},
+        frame_allocator::{allocate_frame, FrameTracker, OutOfMemory},
+        FrameNumber,
+        PageNumber,
+        VirtualAddress,
+    },
 };
 
 bitflags! {
@@ -27,6 +37,122 @@ bitflags! {
         const A = 1 << 6;
         const D = 1 << 7;
         const COW = 1 << 8;
+        const SWAP = 1 << 9;
+    }
+}
+
+/// The `PagingMode` enum represents the paging modes supported by the kernel, whose values are the
+/// `MODE` field of the `satp` register.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PagingMode {
+    Sv39 = 8,
+    Sv48 = 9,
+}
+
+impl PagingMode {
+    /// Returns the number of levels of the page table.
+    pub fn level_count(&self) -> usize {
+        match self {
+            Self::Sv39 => 3,
+            Self::Sv48 => 4,
+        }
+    }
+
+    /// Returns the number of bits of a virtual address.
+    pub fn virtual_address_width(&self) -> usize {
+        PAGE_SIZE_BIT + 9 * self.level_count()
+    }
+}
+
+static PAGING_MODE: AtomicUsize = AtomicUsize::new(PagingMode::Sv39 as usize);
+
+/// Returns the paging mode of the page tables.
+pub fn paging_mode() -> PagingMode {
+    if PAGING_MODE.load(Ordering::Relaxed) == PagingMode::Sv48 as usize {
+        PagingMode::Sv48
+    } else {
+        PagingMode::Sv39
+    }
+}
+
+/// Probes whether the hardware supports Sv48, and uses Sv48 for the page tables if supported.
+/// Writing an unsupported mode to the `satp` register has no effect, so the mode is supported if
+/// it can be read back. The probe turns on paging with a page table whose root entry maps the
+/// lowest 512 GiB as an identical terapage, which keeps the kernel accessible, and then turns off
+/// paging. This function must be called before any page table is created.
+pub fn probe_paging_mode() {
+    let frame = allocate_frame().unwrap();
+    frame.frame_number().as_pte_mut()[0] = PageTableEntry::new(
+        FrameNumber::from(0),
+        PTEFlags::V | PTEFlags::R | PTEFlags::W | PTEFlags::X | PTEFlags::A | PTEFlags::D,
+    );
+
+    unsafe {
+        satp::write((PagingMode::Sv48 as usize) << 60 | usize::from(frame.frame_number()));
+        asm!("sfence.vma");
+        if satp::read().bits() >> 60 == PagingMode::Sv48 as usize {
+            PAGING_MODE.store(PagingMode::Sv48 as usize, Ordering::Relaxed);
+        }
+        satp::write(0);
+        asm!("sfence.vma");
+    }
+}
+
+/// Returns the base address of the user stacks, above which each thread reserves the address
+/// range of its user stack. The user stacks occupy the highest quarter of the lower half of the
+/// virtual-address space, which is `0x3000000000` with Sv39 or `0x600000000000` with Sv48.
+pub fn user_stack_base() -> usize {
+    user_address_limit() / 4 * 3
+}
+
+/// Returns the upper limit of the user addresses, which is the end of the lower half of the
+/// virtual-address space.
+pub fn user_address_limit() -> usize {
+    1 << (paging_mode().virtual_address_width() - 1)
+}
+
+/// The `PageSize` enum represents the size of the memory mapped by a leaf [PageTableEntry], which
+/// depends on the level of the page table that contains the entry.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PageSize {
+    /// A 4 KiB page, which is mapped at the last level.
+    Page,
+    /// A 2 MiB megapage, which is mapped at the second last level.
+    MegaPage,
+    /// A 1 GiB gigapage, which is mapped at the third last level.
+    GigaPage,
+}
+
+impl PageSize {
+    /// The page sizes from the largest to the smallest.
+    pub const ALL: [Self; 3] = [Self::GigaPage, Self::MegaPage, Self::Page];
+
+    fn from_level(level: usize) -> Self {
+        match paging_mode().level_count() - 1 - level {
+            0 => Self::Page,
+            1 => Self::MegaPage,
+            _ => Self::GigaPage,
+        }
+    }
+
+    /// Returns the level of the page table that contains the leaf entries of this size, where the
+    /// root page table is at level 0.
+    pub fn level(&self) -> usize {
+        paging_mode().level_count() - 1 - self.order() / 9
+    }
+
+    /// Returns the order of the block of frames that backs a page of this size.
+    pub fn order(&self) -> usize {
+        match self {
+            Self::Page => 0,
+            Self::MegaPage => 9,
+            Self::GigaPage => 18,
+        }
+    }
+
+    /// Returns the number of 4 KiB pages in a page of this size.
+    pub fn page_count(&self) -> usize {
+        1 << self.order()
     }
 }
 
@@ -70,72 +196,160 @@ impl PageTableEntry {
     pub fn is_cow(&self) -> bool {
         self.flags().contains(PTEFlags::COW)
     }
+
+    /// Returns `true` if the page has been swapped out, where the entry is not valid and stores the
+    /// index of the swap slot in place of the frame number.
+    pub fn is_swapped(&self) -> bool {
+        !self.is_valid() && self.flags().contains(PTEFlags::SWAP)
+    }
+
+    /// Creates an entry for a page that has been swapped out to a specific swap slot, which keeps
+    /// the permissions of the page so that they can be restored when the page is swapped in.
+    pub fn swapped(slot_index: usize, flags: PTEFlags) -> Self {
+        let mut flags = flags;
+        flags.remove(PTEFlags::V | PTEFlags::A | PTEFlags::D);
+        Self::new(FrameNumber::from(slot_index), flags | PTEFlags::SWAP)
+    }
+
+    /// Returns `true` if the entry maps a page rather than points to the next level.
+    pub fn is_leaf(&self) -> bool {
+        self.is_valid()
+            && self
+                .flags()
+                .intersects(PTEFlags::R | PTEFlags::W | PTEFlags::X)
+    }
 }
 
 #[repr(C)]
 pub struct PageTable {
     root_frame_number: FrameNumber,
     frame_list: Vec<FrameTracker>,
+    asid: Asid,
 }
 
 impl PageTable {
-    pub fn new() -> Self {
-        let frame = allocate_frame().unwrap();
-        PageTable {
+    /// Creates an empty page table that is tagged with `asid` in the TLB. A user page table should
+    /// use [Asid::UNASSIGNED], which is replaced with an ASID when the page table is activated.
+    pub fn new(asid: Asid) -> Result<Self, OutOfMemory> {
+        let frame = allocate_frame().ok_or(OutOfMemory)?;
+        Ok(PageTable {
             root_frame_number: frame.frame_number(),
             frame_list: vec![frame],
-        }
+            asid,
+        })
+    }
+
+    /// Assigns an ASID of the current generation to the page table if its ASID is stale, and
+    /// returns the value of the `satp` register that points to the page table.
+    pub fn activate(&mut self) -> usize {
+        self.asid = asid::refresh(self.asid);
+        self.satp()
     }
 
     /// Returns the value of the `satp` register that points to the page table.
     pub fn satp(&self) -> usize {
-        8 << 60 | usize::from(self.root_frame_number)
+        (paging_mode() as usize) << 60
+            | self.asid.number() << SATP_ASID_OFFSET
+            | usize::from(self.root_frame_number)
     }
 
-    /// Creates a [PageTable] where the `root_frame_number` points to the frame in the `satp`
-    /// register.
-    pub fn from_satp(satp: usize) -> Self {
-        Self {
-            root_frame_number: FrameNumber::from(satp & ((1 << 44) - 1)),
-            frame_list: Vec::new(),
-        }
+    /// Maps a [PageNumber] to a [FrameNumber] and sets the [PageTableEntry] with [PTEFlags].
+    /// Returns an error if the frame allocator fails to allocate a frame for the page table.
+    pub fn map(
+        &mut self,
+        page_number: PageNumber,
+        frame_number: FrameNumber,
+        flags: PTEFlags,
+    ) -> Result<(), OutOfMemory> {
+        self.map_leaf(page_number, frame_number, flags, PageSize::Page)
     }
 
-    /// Maps a [PageNumber] to a [FrameNumber] and sets the [PageTableEntry] with [PTEFlags].
-    pub fn map(&mut self, page_number: PageNumber, frame_number: FrameNumber, flags: PTEFlags) {
-        let pte = self.create_pte(page_number).unwrap();
+    /// Maps a page of `page_size` that starts with a [PageNumber] to the block of frames that
+    /// starts with a [FrameNumber], both of which must be aligned to `page_size`.
+    pub fn map_leaf(
+        &mut self,
+        page_number: PageNumber,
+        frame_number: FrameNumber,
+        flags: PTEFlags,
+        page_size: PageSize,
+    ) -> Result<(), OutOfMemory> {
+        assert!(
+            usize::from(page_number) % page_size.page_count() == 0
+                && usize::from(frame_number) % page_size.page_count() == 0,
+            "the {:?} is not aligned",
+            page_size
+        );
+
+        let pte = self.create_pte(page_number, page_size.level())?;
         *pte = PageTableEntry::new(frame_number, flags | PTEFlags::V);
+        self.flush_page(page_number);
+        Ok(())
+    }
+
+    /// Sets the [PageTableEntry] of a 4 KiB page to `pte`, which might not be valid, such as the
+    /// entry of a page that has been swapped out. Returns an error if the frame allocator fails to
+    /// allocate a frame for the page table.
+    pub fn set_entry(
+        &mut self,
+        page_number: PageNumber,
+        pte: PageTableEntry,
+    ) -> Result<(), OutOfMemory> {
+        *self.create_pte(page_number, PageSize::Page.level())? = pte;
+        self.flush_page(page_number);
+        Ok(())
+    }
+
+    /// Clears the accessed bit of the [PageTableEntry] that maps the [PageNumber], so that the
+    /// accessed bit is set again when the page is accessed.
+    pub fn clear_accessed(&mut self, page_number: PageNumber) {
+        let (pte, _) = self.find_pte(page_number).unwrap();
+        let mut flags = pte.flags();
+        flags.remove(PTEFlags::A);
+        *pte = PageTableEntry::new(pte.frame_number(), flags);
+        self.flush_page(page_number);
+    }
+
+    /// Replaces the flags of the leaf [PageTableEntry] that maps the [PageNumber], which keeps the
+    /// frame number, or the index of the swap slot if the page has been swapped out.
+    pub fn set_flags(&mut self, page_number: PageNumber, flags: PTEFlags) {
+        let (pte, _) = self.find_pte(page_number).unwrap();
+        *pte = PageTableEntry::new(pte.frame_number(), flags);
+        self.flush_page(page_number);
     }
 
-    /// Clears the [PageTableEntry] corresponding to the [PageNumber].
+    /// Clears the leaf [PageTableEntry] that maps the [PageNumber], which unmaps the whole page
+    /// that contains the [PageNumber].
     pub fn unmap(&mut self, page_number: PageNumber) {
-        let pte = self.find_pte(page_number).unwrap();
+        let (pte, _) = self.find_pte(page_number).unwrap();
         *pte = PageTableEntry::default();
+        self.flush_page(page_number);
     }
 
-    /// Finds the page table with a [VirtualAddress] and returns a [PhysicalAddress].
-    pub fn translate(&self, virtual_address: VirtualAddress) -> Option<PhysicalAddress> {
-        let page_number = PageNumber::from(virtual_address);
-        self.find_pte(page_number).map(|pte| {
-            let frame_number = pte.frame_number();
-            PhysicalAddress::from(frame_number) + virtual_address.page_offset()
-        })
+    /// Flushes the stale entry of a page in the TLB after its [PageTableEntry] is changed.
+    fn flush_page(&self, page_number: PageNumber) {
+        if self.asid.is_assigned() {
+            asid::flush_page(self.asid, usize::from(VirtualAddress::from(page_number)));
+        }
     }
 
-    /// Finds the page table with a [PageNumber] and returns a [PageTableEntry].
+    /// Finds the page table with a [PageNumber] and returns a [PageTableEntry]. If the page is
+    /// part of a megapage or a gigapage, the entry points to the frame that backs the page.
     pub fn translate_page(&self, page_number: PageNumber) -> Option<PageTableEntry> {
-        self.find_pte(page_number).map(|pte| *pte)
+        self.find_pte(page_number).map(|(pte, page_size)| {
+            let offset = usize::from(page_number) & (page_size.page_count() - 1);
+            PageTableEntry::new(pte.frame_number() + offset, pte.flags())
+        })
     }
 
-    /// Finds the page table with a [PageNumber] and returns a mutable reference to a
-    /// [PageTableEntry].
-    fn find_pte(&self, page_number: PageNumber) -> Option<&mut PageTableEntry> {
-        let index = page_number.index();
+    /// Finds the page table with a [PageNumber] and returns a mutable reference to the leaf
+    /// [PageTableEntry] and the size of the page that it maps.
+    fn find_pte(&self, page_number: PageNumber) -> Option<(&mut PageTableEntry, PageSize)> {
+        let level_count = paging_mode().level_count();
         let mut frame_number = self.root_frame_number;
-        for (i, pte_index) in index.iter().enumerate() {
-            let pte = &mut frame_number.as_pte_mut()[*pte_index];
-            if i == 2 {
-                return Some(pte);
+        for level in 0..level_count {
+            let pte = &mut frame_number.as_pte_mut()[page_number.index(level)];
+            if level == level_count - 1 || pte.is_leaf() {
+                return Some((pte, PageSize::from_level(level)));
             }
 
             if pte.is_valid() {
@@ -147,24 +361,35 @@ impl PageTable {
         None
     }
 
-    /// Finds the page table with a [PageNumber] and returns a mutable reference to a
-    /// [PageTableEntry]. Creates a new [PageTableEntry] if not existed.
-    fn create_pte(&mut self, page_number: PageNumber) -> Option<&mut PageTableEntry> {
-        let index = page_number.index();
+    /// Finds the page table at `level` with a [PageNumber] and returns a mutable reference to a
+    /// [PageTableEntry]. Creates the page tables of the levels above if not existed.
+    fn create_pte(
+        &mut self,
+        page_number: PageNumber,
+        level: usize,
+    ) -> Result<&mut PageTableEntry, OutOfMemory> {
         let mut frame_number = self.root_frame_number;
-        for (i, pte_index) in index.iter().enumerate() {
-            let pte = &mut frame_number.as_pte_mut()[*pte_index];
-            if i == 2 {
-                return Some(pte);
-            }
+        for current_level in 0..level {
+            let pte = &mut frame_number.as_pte_mut()[page_number.index(current_level)];
 
+            assert!(
+                !pte.is_leaf(),
+                "the page {:#x} has been mapped by a larger page",
+                usize::from(page_number)
+            );
             if !pte.is_valid() {
-                let frame = allocate_frame().unwrap();
+                let frame = allocate_frame().ok_or(OutOfMemory)?;
                 *pte = PageTableEntry::new(frame.frame_number(), PTEFlags::V);
                 self.frame_list.push(frame);
             }
             frame_number = pte.frame_number();
         }
-        None
+        Ok(&mut frame_number.as_pte_mut()[page_number.index(level)])
+    }
+}
+
+impl Drop for PageTable {
+    fn drop(&mut self) {
+        asid::deallocate(self.asid);
     }
 }
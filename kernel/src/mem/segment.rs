@@ -1,5 +1,5 @@
 use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
-use core::arch::asm;
+use core::{arch::asm, slice};
 
 use bitflags::bitflags;
 use lazy_static::lazy_static;
@@ -7,12 +7,38 @@ use riscv::register::satp;
 use xmas_elf::{program::Type, ElfFile};
 
 use crate::{
-    constant::{MEM_LIMIT, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT_BASE},
+    constant::{
+        MAX_HART_COUNT,
+        MEM_LIMIT,
+        PAGE_SIZE,
+        PLIC_CONTEXT_BASE,
+        PLIC_CONTEXT_SIZE,
+        TRAMPOLINE,
+        TRAP_CONTEXT_BASE,
+        VIRTIO_MMIO_BASE,
+        VIRTIO_MMIO_COUNT,
+        VIRTIO_MMIO_SIZE,
+    },
     mem::{
         address::PageRange,
-        frame_allocator::{allocate_frame, FrameTracker},
-        page_table::{PTEFlags, PageTable, PageTableEntry},
+        asid::Asid,
+        elf::{self, ElfImage},
+        frame_allocator::{
+            allocate_frame,
+            allocate_frames,
+            is_zero_frame,
+            zero_frame,
+            FrameTracker,
+            OutOfMemory,
+        },
+        layout::{self, UserLayout},
+        page_table::{PTEFlags, PageSize, PageTable, PageTableEntry},
+        swap::SwapSlot,
+        user_ptr::UserAccess,
         FrameNumber,
+        HeapObject,
+        ObjectTag,
+        ObjectType,
         PageNumber,
         PhysicalAddress,
         VirtualAddress,
@@ -20,10 +46,49 @@ use crate::{
     sync::Mutex,
 };
 
+/// The `LoadError` enum represents the reasons that an ELF file can't be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The executable file is not found.
+    NotFound,
+    /// The frame allocator runs out of frames.
+    OutOfMemory,
+    /// A loadable segment is both writable and executable, which the W^X policy forbids.
+    WritableExecutable,
+    /// The interpreter that the executable requests with `PT_INTERP` is not found among the
+    /// embedded programs.
+    InterpreterNotFound,
+    /// The executable or its interpreter is not a valid ELF file.
+    InvalidElf,
+}
+
+/// The `PageFaultError` enum represents the reasons that a page fault on a user page can't be
+/// resolved.
+#[derive(Debug)]
+pub enum PageFaultError {
+    /// The frame allocator runs out of frames.
+    OutOfMemory,
+    /// The swap device fails to read the page back.
+    Io,
+}
+
+impl From<OutOfMemory> for PageFaultError {
+    fn from(_: OutOfMemory) -> Self {
+        Self::OutOfMemory
+    }
+}
+
+impl From<OutOfMemory> for LoadError {
+    fn from(_: OutOfMemory) -> Self {
+        Self::OutOfMemory
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MapType {
     Identical,
     Framed,
+    Shared,
 }
 
 bitflags! {
@@ -50,14 +115,33 @@ extern "C" {
 }
 
 /// The `PageSegment` struct represents a consecutive range of pages,
-/// which are mapped to frames in the same method (`Identical` or `Framed`)
+/// which are mapped to frames in the same method (`Identical`, `Framed`, or `Shared`)
 /// and have the same permissions.
+///
+/// An `Identical` segment is mapped with the largest pages that are aligned within the range,
+/// while a `Framed` segment is mapped with pages of `page_size`. The `frame_map` of a `Framed`
+/// segment maps the first [PageNumber] of each page to the block of frames that backs it.
+/// A `Shared` segment is mapped with pages to the frames of a shared memory object, which are
+/// inserted into its `frame_map` before the segment is mapped and are never copied on write.
+///
+/// The 4 KiB pages of a user `Framed` segment are mapped to the shared zero frame without write
+/// permission until they are written, which copies the frame as a copy-on-write page. These pages
+/// can be swapped out as well. The `swap_map` maps each page
+/// that has been swapped out to its [SwapSlot], and keeps the slot after the page is swapped in,
+/// so that the page can be swapped out again without writing it if the page is not dirty.
 #[derive(Clone)]
 pub struct PageSegment {
     page_range: PageRange,
     frame_map: BTreeMap<PageNumber, Arc<FrameTracker>>,
+    swap_map: BTreeMap<PageNumber, SwapSlot>,
     map_type: MapType,
     map_permission: MapPermission,
+    page_size: PageSize,
+    _object_tag: ObjectTag<Self>,
+}
+
+impl HeapObject for PageSegment {
+    const OBJECT_TYPE: ObjectType = ObjectType::PageSegment;
 }
 
 impl PageSegment {
@@ -70,11 +154,24 @@ impl PageSegment {
         Self {
             page_range: PageRange::new(start_address.floor(), end_address.ceil()),
             frame_map: BTreeMap::new(),
+            swap_map: BTreeMap::new(),
             map_type,
             map_permission,
+            page_size: PageSize::Page,
+            _object_tag: ObjectTag::new(),
         }
     }
 
+    /// Sets the size of the pages of a `Framed` segment, whose range must be aligned to the size.
+    pub fn with_page_size(mut self, page_size: PageSize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    pub fn page_size(&self) -> PageSize {
+        self.page_size
+    }
+
     pub fn start(&self) -> PageNumber {
         self.page_range.start()
     }
@@ -95,51 +192,222 @@ impl PageSegment {
         &mut self.frame_map
     }
 
+    /// Returns `true` if the segment maps a shared memory object, which is only unmapped by
+    /// `shmdt`.
+    pub fn is_shared(&self) -> bool {
+        self.map_type == MapType::Shared
+    }
+
+    /// Returns `true` if the pages of the segment start with the zero frame and can be swapped out,
+    /// which excludes the kernel, the trap contexts, the huge pages, and the shared memory.
+    fn is_pageable(&self) -> bool {
+        self.map_type == MapType::Framed
+            && self.page_size == PageSize::Page
+            && self.start() < VirtualAddress::from(TRAP_CONTEXT_BASE).floor()
+    }
+
     /// Maps the range of pages represented with `page_range` to frames in the `page_table`.
-    pub fn map_range(&mut self, page_table: &mut PageTable) {
-        for page_number in self.page_range.iter() {
-            self.map_page(page_table, page_number);
+    pub fn map_range(&mut self, page_table: &mut PageTable) -> Result<(), OutOfMemory> {
+        self.map_pages(page_table, self.page_range.clone())
+    }
+
+    /// Maps the pages in `page_range` to frames in the `page_table`. Unmaps the pages that have
+    /// been mapped if the frame allocator runs out of frames, so that either all or none of the
+    /// pages are mapped.
+    fn map_pages(
+        &mut self,
+        page_table: &mut PageTable,
+        page_range: PageRange,
+    ) -> Result<(), OutOfMemory> {
+        let mut page_number = page_range.start();
+        while page_number < page_range.end() {
+            let page_size = self.leaf_size(page_number, page_range.end());
+            if let Err(error) = self.map_page(page_table, page_number, page_size) {
+                self.unmap_pages(page_table, PageRange::new(page_range.start(), page_number));
+                return Err(error);
+            }
+            page_number = page_number.offset(page_size.page_count());
         }
+        Ok(())
     }
 
     /// Unmaps the range of pages represented with `page_range` from frames in the `page_table`.
     pub fn unmap_range(&mut self, page_table: &mut PageTable) {
-        for page_number in self.page_range.iter() {
+        self.unmap_pages(page_table, self.page_range.clone());
+    }
+
+    /// Unmaps the pages in `page_range` from frames in the `page_table`.
+    fn unmap_pages(&mut self, page_table: &mut PageTable, page_range: PageRange) {
+        let mut page_number = page_range.start();
+        while page_number < page_range.end() {
+            let page_size = self.leaf_size(page_number, page_range.end());
             self.unmap_page(page_table, page_number);
+            page_number = page_number.offset(page_size.page_count());
+        }
+    }
+
+    /// Returns the size of the page that starts with `page_number` and ends before `end`.
+    fn leaf_size(&self, page_number: PageNumber, end: PageNumber) -> PageSize {
+        match self.map_type {
+            MapType::Identical => PageSize::ALL
+                .into_iter()
+                .find(|page_size| {
+                    usize::from(page_number) % page_size.page_count() == 0
+                        && usize::from(page_number) + page_size.page_count() <= usize::from(end)
+                })
+                .unwrap(),
+            MapType::Framed => self.page_size,
+            MapType::Shared => PageSize::Page,
         }
     }
 
-    /// Maps a page with `page_number` to a frame in the `page_table`.
-    pub fn map_page(&mut self, page_table: &mut PageTable, page_number: PageNumber) {
-        let frame_number = match self.map_type {
-            MapType::Identical => FrameNumber::from(usize::from(page_number)),
+    /// Maps a page of `page_size` that starts with `page_number` to a block of frames in the
+    /// `page_table`. Returns an error if the frame allocator runs out of frames.
+    pub fn map_page(
+        &mut self,
+        page_table: &mut PageTable,
+        page_number: PageNumber,
+        page_size: PageSize,
+    ) -> Result<(), OutOfMemory> {
+        let pte_flags = PTEFlags::from_bits(self.map_permission.bits()).unwrap();
+        match self.map_type {
+            MapType::Identical => page_table.map_leaf(
+                page_number,
+                FrameNumber::from(usize::from(page_number)),
+                pte_flags,
+                page_size,
+            ),
+            MapType::Framed if self.is_pageable() => {
+                let mut pte_flags = pte_flags;
+                if pte_flags.contains(PTEFlags::W) {
+                    pte_flags.remove(PTEFlags::W);
+                    pte_flags.insert(PTEFlags::COW);
+                }
+
+                let frame = zero_frame();
+                page_table.map_leaf(page_number, frame.frame_number(), pte_flags, page_size)?;
+                self.frame_map.insert(page_number, frame);
+                Ok(())
+            }
             MapType::Framed => {
-                let frame = allocate_frame().unwrap();
-                let frame_number = frame.frame_number();
+                let frame = allocate_frames(page_size.order()).ok_or(OutOfMemory)?;
+                page_table.map_leaf(page_number, frame.frame_number(), pte_flags, page_size)?;
                 self.frame_map.insert(page_number, Arc::new(frame));
-                frame_number
+                Ok(())
             }
-        };
+            MapType::Shared => {
+                let frame_number = self.frame_map.get(&page_number).unwrap().frame_number();
+                page_table.map_leaf(page_number, frame_number, pte_flags, page_size)
+            }
+        }
+    }
 
-        let pte_flags = PTEFlags::from_bits(self.map_permission.bits()).unwrap();
-        page_table.map(page_number, frame_number, pte_flags);
+    /// Extends the range of pages downward so that it starts at `start`, and maps the new pages to
+    /// frames in the `page_table`.
+    pub fn extend_down(
+        &mut self,
+        page_table: &mut PageTable,
+        start: PageNumber,
+    ) -> Result<(), OutOfMemory> {
+        self.map_pages(page_table, PageRange::new(start, self.start()))?;
+        self.page_range = PageRange::new(start, self.end());
+        Ok(())
+    }
+
+    /// Moves the end of the range of pages to `end`, which maps the new pages to frames in the
+    /// `page_table` when the range grows, and unmaps the pages beyond `end` when the range shrinks.
+    pub fn resize(
+        &mut self,
+        page_table: &mut PageTable,
+        end: PageNumber,
+    ) -> Result<(), OutOfMemory> {
+        if end > self.end() {
+            self.map_pages(page_table, PageRange::new(self.end(), end))?;
+        } else {
+            self.unmap_pages(page_table, PageRange::new(end, self.end()));
+        }
+        self.page_range = PageRange::new(self.start(), end);
+        Ok(())
     }
 
-    /// Unmaps a page with `page_number` from a frame in the `page_table`.
+    /// Unmaps the page that starts with `page_number` from its frames in the `page_table`, or
+    /// releases its swap slot if the page has been swapped out.
     pub fn unmap_page(&mut self, page_table: &mut PageTable, page_number: PageNumber) {
-        if self.map_type == MapType::Framed {
+        if self.map_type != MapType::Identical {
             self.frame_map.remove(&page_number);
+            self.swap_map.remove(&page_number);
         }
         page_table.unmap(page_number);
     }
 
-    /// Writes `bytes` to the pages represented with `page_range`.
-    pub fn clone_bytes(&mut self, page_table: &mut PageTable, bytes: &[u8]) {
+    /// Splits the segment at `page_number`, which must be aligned to the page size of the segment,
+    /// and returns the part above it, which takes the frames and the swap slots of its pages.
+    fn split_off(&mut self, page_number: PageNumber) -> Self {
+        let segment = Self {
+            page_range: PageRange::new(page_number, self.end()),
+            frame_map: self.frame_map.split_off(&page_number),
+            swap_map: self.swap_map.split_off(&page_number),
+            map_type: self.map_type,
+            map_permission: self.map_permission,
+            page_size: self.page_size,
+            _object_tag: ObjectTag::new(),
+        };
+        self.page_range = PageRange::new(self.start(), page_number);
+        segment
+    }
+
+    /// Changes the permission of a `Framed` segment and updates the entries of its pages. A
+    /// writable page whose frame is shared with another process, such as the zero frame, stays
+    /// read-only as a copy-on-write page.
+    fn set_permission(&mut self, page_table: &mut PageTable, map_permission: MapPermission) {
+        self.map_permission = map_permission;
+        let permission_flags = PTEFlags::from_bits(map_permission.bits()).unwrap();
+        let mut page_number = self.start();
+        while page_number < self.end() {
+            let page_size = self.leaf_size(page_number, self.end());
+            let pte = page_table.translate_page(page_number).unwrap();
+            let mut pte_flags = pte.flags()
+                & (PTEFlags::V | PTEFlags::A | PTEFlags::D | PTEFlags::SWAP)
+                | permission_flags;
+
+            let is_shared = self
+                .frame_map
+                .get(&page_number)
+                .map_or(false, |frame| Arc::strong_count(frame) > 1);
+            if pte_flags.contains(PTEFlags::W) && is_shared {
+                pte_flags.remove(PTEFlags::W);
+                pte_flags.insert(PTEFlags::COW);
+            }
+
+            page_table.set_flags(page_number, pte_flags);
+            page_number = page_number.offset(page_size.page_count());
+        }
+    }
+
+    /// Writes `bytes` to the pages represented with `page_range`. The pages that are mapped to the
+    /// zero frame are mapped to private frames first, which returns an error if the frame allocator
+    /// runs out of frames.
+    pub fn clone_bytes(
+        &mut self,
+        page_table: &mut PageTable,
+        bytes: &[u8],
+    ) -> Result<(), OutOfMemory> {
         let mut offset = 0;
-        for state in self.page_range.iter() {
+        for page_number in self.page_range.clone().iter() {
+            if self
+                .frame_map
+                .get(&page_number)
+                .map_or(false, is_zero_frame)
+            {
+                let frame = allocate_frame().ok_or(OutOfMemory)?;
+                let pte_flags = PTEFlags::from_bits(self.map_permission.bits()).unwrap();
+                page_table.map(page_number, frame.frame_number(), pte_flags)?;
+                self.frame_map.insert(page_number, Arc::new(frame));
+            }
+
             let source = &bytes[offset..bytes.len().min(offset + PAGE_SIZE)];
             let destination = &mut page_table
-                .translate_page(state)
+                .translate_page(page_number)
                 .unwrap()
                 .frame_number()
                 .as_bytes_mut()[..source.len()];
@@ -150,6 +418,7 @@ impl PageSegment {
                 break;
             }
         }
+        Ok(())
     }
 }
 
@@ -160,27 +429,45 @@ pub struct PageSet {
 }
 
 impl PageSet {
-    pub fn new() -> Self {
-        Self {
-            page_table: PageTable::new(),
+    /// Creates an empty user [PageSet], which is assigned an ASID when it is activated.
+    pub fn new() -> Result<Self, OutOfMemory> {
+        Self::with_asid(Asid::UNASSIGNED)
+    }
+
+    fn with_asid(asid: Asid) -> Result<Self, OutOfMemory> {
+        Ok(Self {
+            page_table: PageTable::new(asid)?,
             segment_list: Vec::new(),
-        }
+        })
     }
 
-    pub fn clone_from(page_set: &mut Self) -> Self {
-        let mut page_set_clone = Self::new();
+    /// Clones the [PageSet] of a process for its child process. The user segments are shared with
+    /// copy-on-write, while the segments of the trap contexts are copied. The shared memory
+    /// segments are mapped to the same frames in both processes. Returns an error if the frame
+    /// allocator runs out of frames.
+    pub fn clone_from(page_set: &mut Self) -> Result<Self, OutOfMemory> {
+        let mut page_set_clone = Self::new()?;
         page_set_clone.page_table.map(
             PageNumber::from(VirtualAddress::from(TRAMPOLINE)),
             FrameNumber::from(PhysicalAddress::from(trampoline_start as usize)),
-            PTEFlags::R | PTEFlags::W | PTEFlags::X,
-        );
+            PTEFlags::R | PTEFlags::X | PTEFlags::G,
+        )?;
 
         let mut page_mappings = Vec::new();
         for page_segment in page_set.segment_list().iter() {
             let page_segment_clone = page_segment.clone();
 
-            if page_segment_clone.start() >= PageNumber::from(TRAP_CONTEXT_BASE) {
-                page_set_clone.push(page_segment_clone, None);
+            // The shared memory segments hold references to the frames of the shared memory objects
+            if page_segment_clone.map_type == MapType::Shared {
+                page_set_clone.push(page_segment_clone, None)?;
+                continue;
+            }
+
+            // The trap contexts and the huge pages are copied, while the other pages are shared
+            if page_segment_clone.start() >= VirtualAddress::from(TRAP_CONTEXT_BASE).floor()
+                || page_segment_clone.page_size() != PageSize::Page
+            {
+                page_set_clone.push(page_segment_clone, None)?;
                 for page_number in page_segment.page_range().iter() {
                     let source = page_set.translate(page_number).unwrap().frame_number();
                     let destination = page_set_clone
@@ -192,9 +479,16 @@ impl PageSet {
                         .clone_from_slice(source.as_bytes());
                 }
             } else {
-                page_set_clone.push_mapped(page_segment_clone, None);
+                page_set_clone.push_mapped(page_segment_clone);
                 for page_number in page_segment.page_range().iter() {
                     let pte = page_set.page_table.translate_page(page_number).unwrap();
+
+                    // The swapped-out pages share the swap slots, which are cloned with the segment
+                    if pte.is_swapped() {
+                        page_set_clone.page_table.set_entry(page_number, pte)?;
+                        continue;
+                    }
+
                     let frame_number = pte.frame_number();
 
                     let mut pte_flags = pte.flags();
@@ -210,12 +504,12 @@ impl PageSet {
         for (page_number, frame_number, pte_flags) in page_mappings {
             page_set
                 .page_table
-                .map(page_number, frame_number, pte_flags);
+                .map(page_number, frame_number, pte_flags)?;
             page_set_clone
                 .page_table
-                .map(page_number, frame_number, pte_flags);
+                .map(page_number, frame_number, pte_flags)?;
         }
-        page_set_clone
+        Ok(page_set_clone)
     }
 
     pub fn init(&self) {
@@ -229,14 +523,209 @@ impl PageSet {
         self.page_table.satp()
     }
 
+    /// Prepares the [PageSet] to be switched to, and returns the value of the `satp` register that
+    /// points to its page table.
+    pub fn activate(&mut self) -> usize {
+        self.page_table.activate()
+    }
+
     pub fn translate(&self, page_number: PageNumber) -> Option<PageTableEntry> {
         self.page_table.translate_page(page_number)
     }
 
-    pub fn clone_frame(&mut self, virtual_address: VirtualAddress) -> bool {
-        let page_number = PageNumber::from(virtual_address);
+    /// Translates a [VirtualAddress] that the kernel accesses on behalf of the user. Returns
+    /// `None` if the page is not a user page or lacks the permission for the access. A swapped-out
+    /// page is swapped in, and a write to a copy-on-write page copies the frame first, which
+    /// returns an error if the frame allocator runs out of frames or the swap device fails.
+    pub fn translate_user(
+        &mut self,
+        virtual_address: VirtualAddress,
+        access: UserAccess,
+    ) -> Result<Option<PhysicalAddress>, PageFaultError> {
+        self.swap_in(virtual_address)?;
+        if access == UserAccess::Write {
+            self.clone_frame(virtual_address)?;
+        }
+        Ok(self.translate_resident(virtual_address, access))
+    }
+
+    /// Translates a [VirtualAddress] of a resident user page that the kernel accesses on behalf of
+    /// the user, and sets the accessed and dirty bits of the leaf [PageTableEntry] as the hardware
+    /// would. Returns `None` if the page is not a user page or lacks the permission for the access.
+    fn translate_resident(
+        &mut self,
+        virtual_address: VirtualAddress,
+        access: UserAccess,
+    ) -> Option<PhysicalAddress> {
+        let page_number = virtual_address.floor();
+        let pte = self.page_table.translate_page(page_number)?;
+
+        let permitted = match access {
+            UserAccess::Read => pte.is_readable(),
+            UserAccess::Write => pte.is_writable(),
+        };
+        if !pte.is_valid() || !pte.flags().contains(PTEFlags::U) || !permitted {
+            return None;
+        }
+
+        // The kernel accesses the frame directly, so the page is marked as accessed, and as dirty
+        // on a write to be written to the swap area when it is swapped out. The flags are updated
+        // on the leaf that maps the page, which might be a huge page
+        let accessed_flags = match access {
+            UserAccess::Read => PTEFlags::A,
+            UserAccess::Write => PTEFlags::A | PTEFlags::D,
+        };
+        if !pte.flags().contains(accessed_flags) {
+            self.page_table
+                .set_flags(page_number, pte.flags() | accessed_flags);
+        }
+        Some(PhysicalAddress::from(pte.frame_number()) + virtual_address.page_offset())
+    }
+
+    /// Copies `bytes` to the user memory at a specific [VirtualAddress], which must be resident and
+    /// mapped with write permission. Returns an error if the frame allocator runs out of frames.
+    pub fn write_user(
+        &mut self,
+        virtual_address: VirtualAddress,
+        bytes: &[u8],
+    ) -> Result<(), OutOfMemory> {
+        let mut virtual_address = virtual_address;
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let length = (PAGE_SIZE - virtual_address.page_offset()).min(bytes.len() - offset);
+            self.clone_frame(virtual_address)?;
+            let physical_address = self
+                .translate_resident(virtual_address, UserAccess::Write)
+                .expect("the user memory is not writable");
+            let page = unsafe { slice::from_raw_parts_mut(physical_address.as_ptr_mut(), length) };
+            page.copy_from_slice(&bytes[offset..offset + length]);
+
+            offset += length;
+            virtual_address += length;
+        }
+        Ok(())
+    }
+
+    /// Advances the clock hand over the resident pages that can be swapped out in the order of
+    /// their addresses, starting with `hand`. Clears the accessed bit of each page that has been
+    /// accessed since the hand passed it, and returns the first page that hasn't been accessed, or
+    /// `None` if the hand reaches the end of the address space. The pages shared with copy-on-write
+    /// are skipped, since swapping them out of a single process doesn't release their frames.
+    pub fn find_victim(&mut self, hand: PageNumber) -> Option<PageNumber> {
+        let mut hand = hand;
+        loop {
+            let segment = self
+                .segment_list
+                .iter()
+                .filter(|segment| segment.is_pageable() && segment.end() > hand)
+                .min_by_key(|segment| segment.start())?;
+
+            for (&page_number, frame) in segment.frame_map.range(hand.max(segment.start())..) {
+                if Arc::strong_count(frame) > 1 {
+                    continue;
+                }
+
+                let pte = self.page_table.translate_page(page_number).unwrap();
+                if !pte.flags().contains(PTEFlags::A) {
+                    return Some(page_number);
+                }
+                self.page_table.clear_accessed(page_number);
+            }
+            hand = segment.end();
+        }
+    }
+
+    /// Writes a resident page to the swap area, and replaces its [PageTableEntry] with a swap
+    /// entry so that its frame can be released. The page is not written if it still has a swap
+    /// slot from the last time it was swapped out and it is not dirty. Returns `false` if the page
+    /// can't be swapped out.
+    pub fn swap_out(&mut self, page_number: PageNumber) -> bool {
+        let Some(segment) = self.segment_list.iter_mut().find(|segment| {
+            segment.is_pageable() && segment.start() <= page_number && page_number < segment.end()
+        }) else {
+            return false;
+        };
+
+        let Some(frame) = segment.frame_map.get(&page_number) else {
+            return false;
+        };
+        if Arc::strong_count(frame) > 1 {
+            return false;
+        }
+
         let pte = self.page_table.translate_page(page_number).unwrap();
-        if pte.is_cow() {
+        let swap_slot = match segment.swap_map.remove(&page_number) {
+            Some(swap_slot) if !pte.flags().contains(PTEFlags::D) => swap_slot,
+            Some(swap_slot) if swap_slot.is_exclusive() => {
+                if swap_slot.write(frame.frame_number()).is_err() {
+                    return false;
+                }
+                swap_slot
+            }
+            _ => {
+                let Some(swap_slot) = SwapSlot::allocate(frame.frame_number()) else {
+                    return false;
+                };
+                swap_slot
+            }
+        };
+
+        // The entry of the page exists, so no frame is allocated for the page table
+        self.page_table
+            .set_entry(
+                page_number,
+                PageTableEntry::swapped(swap_slot.index(), pte.flags()),
+            )
+            .unwrap();
+        segment.swap_map.insert(page_number, swap_slot);
+        segment.frame_map.remove(&page_number);
+        true
+    }
+
+    /// Reads the page that contains a specific [VirtualAddress] back from the swap area if the
+    /// page has been swapped out. Returns `false` if the page has not been swapped out, or an error
+    /// if the frame allocator runs out of frames or the swap device fails to read the page.
+    pub fn swap_in(&mut self, virtual_address: VirtualAddress) -> Result<bool, PageFaultError> {
+        let page_number = virtual_address.floor();
+        let Some(pte) = self.page_table.translate_page(page_number) else {
+            return Ok(false);
+        };
+        if !pte.is_swapped() {
+            return Ok(false);
+        }
+
+        let segment = self
+            .segment_list
+            .iter_mut()
+            .find(|segment| segment.start() <= page_number && page_number < segment.end())
+            .unwrap();
+        let frame = allocate_frame().ok_or(OutOfMemory)?;
+        segment
+            .swap_map
+            .get(&page_number)
+            .unwrap()
+            .read(frame.frame_number())
+            .map_err(|_| PageFaultError::Io)?;
+
+        // The page is marked as accessed so that the clock hand doesn't evict it immediately
+        let mut pte_flags = pte.flags();
+        pte_flags.remove(PTEFlags::SWAP);
+        self.page_table
+            .map(page_number, frame.frame_number(), pte_flags | PTEFlags::A)?;
+        segment.frame_map.insert(page_number, Arc::new(frame));
+        Ok(true)
+    }
+
+    /// Copies the frame of a copy-on-write page that contains a specific [VirtualAddress], and maps
+    /// the page to the copy with write permission. Returns `false` if the page is not a
+    /// copy-on-write page, or an error if the frame allocator runs out of frames.
+    pub fn clone_frame(&mut self, virtual_address: VirtualAddress) -> Result<bool, OutOfMemory> {
+        let page_number = PageNumber::from(virtual_address);
+        let Some(pte) = self.page_table.translate_page(page_number) else {
+            return Ok(false);
+        };
+
+        if pte.is_valid() && pte.is_cow() {
             if let Some(page_segment) = self.find_segment_mut(virtual_address) {
                 let source_frame_tracker = page_segment.frame_map().get(&page_number).unwrap();
                 let source_frame = source_frame_tracker.frame_number();
@@ -245,38 +734,49 @@ impl PageSet {
                 pte_flags.remove(PTEFlags::COW);
 
                 if Arc::strong_count(source_frame_tracker) == 1 {
-                    self.page_table.map(page_number, source_frame, pte_flags);
+                    self.page_table.map(page_number, source_frame, pte_flags)?;
                 } else {
-                    let destination_frame_tracker = allocate_frame().unwrap();
+                    // The frame is zeroed when it's allocated, so the zero frame isn't copied
+                    let destination_frame_tracker = allocate_frame().ok_or(OutOfMemory)?;
                     let destination_frame = destination_frame_tracker.frame_number();
-                    destination_frame
-                        .as_bytes_mut()
-                        .clone_from_slice(source_frame.as_bytes());
+                    if !is_zero_frame(source_frame_tracker) {
+                        destination_frame
+                            .as_bytes_mut()
+                            .clone_from_slice(source_frame.as_bytes());
+                    }
                     page_segment
                         .frame_map_mut()
                         .insert(page_number, Arc::new(destination_frame_tracker));
                     self.page_table
-                        .map(page_number, destination_frame, pte_flags);
+                        .map(page_number, destination_frame, pte_flags)?;
                 }
             }
-            true
+            Ok(true)
         } else {
-            false
+            Ok(false)
         }
     }
 
-    pub fn push(&mut self, mut segment: PageSegment, bytes: Option<&[u8]>) {
-        segment.map_range(&mut self.page_table);
+    /// Maps the pages of a [PageSegment] and inserts the segment into the [PageSet]. Returns an
+    /// error without inserting the segment if the frame allocator runs out of frames.
+    pub fn push(
+        &mut self,
+        mut segment: PageSegment,
+        bytes: Option<&[u8]>,
+    ) -> Result<(), OutOfMemory> {
+        segment.map_range(&mut self.page_table)?;
         if let Some(bytes) = bytes {
-            segment.clone_bytes(&mut self.page_table, bytes);
+            if let Err(error) = segment.clone_bytes(&mut self.page_table, bytes) {
+                segment.unmap_range(&mut self.page_table);
+                return Err(error);
+            }
         }
         self.segment_list.push(segment);
+        Ok(())
     }
 
-    pub fn push_mapped(&mut self, mut segment: PageSegment, bytes: Option<&[u8]>) {
-        if let Some(bytes) = bytes {
-            segment.clone_bytes(&mut self.page_table, bytes);
-        }
+    /// Inserts a [PageSegment] whose pages have been mapped into the [PageSet].
+    pub fn push_mapped(&mut self, segment: PageSegment) {
         self.segment_list.push(segment);
     }
 
@@ -289,11 +789,61 @@ impl PageSet {
         start_address: VirtualAddress,
         end_address: VirtualAddress,
         map_permission: MapPermission,
-    ) {
+    ) -> Result<(), OutOfMemory> {
+        self.insert_frame_with_page_size(start_address, end_address, map_permission, PageSize::Page)
+    }
+
+    /// Maps the range from `start_address` to `end_address` to blocks of frames with pages of
+    /// `page_size`, where both addresses must be aligned to the page size.
+    pub fn insert_frame_with_page_size(
+        &mut self,
+        start_address: VirtualAddress,
+        end_address: VirtualAddress,
+        map_permission: MapPermission,
+        page_size: PageSize,
+    ) -> Result<(), OutOfMemory> {
         self.push(
-            PageSegment::new(start_address, end_address, MapType::Framed, map_permission),
+            PageSegment::new(start_address, end_address, MapType::Framed, map_permission)
+                .with_page_size(page_size),
             None,
-        );
+        )
+    }
+
+    /// Maps the range that starts at `start_address` to a list of frames of a shared memory object,
+    /// one frame for each page.
+    pub fn insert_shared(
+        &mut self,
+        start_address: VirtualAddress,
+        frame_list: &[Arc<FrameTracker>],
+        map_permission: MapPermission,
+    ) -> Result<(), OutOfMemory> {
+        let end_address = start_address + frame_list.len() * PAGE_SIZE;
+        let mut segment =
+            PageSegment::new(start_address, end_address, MapType::Shared, map_permission);
+        for (page_number, frame) in segment.page_range.clone().iter().zip(frame_list) {
+            segment.frame_map.insert(page_number, frame.clone());
+        }
+        self.push(segment, None)
+    }
+
+    /// Removes the `Shared` [PageSegment] that starts at `start_address`. Returns `false` if no
+    /// such segment exists.
+    pub fn remove_shared(&mut self, start_address: VirtualAddress) -> bool {
+        let is_shared = self
+            .segment_list
+            .iter()
+            .any(|segment| segment.is_shared() && segment.start() == start_address.floor());
+        if is_shared {
+            self.remove_segment(start_address);
+        }
+        is_shared
+    }
+
+    /// Returns `true` if no [PageSegment] overlaps the pages from `start` to `end`.
+    fn is_range_free(&self, start: PageNumber, end: PageNumber) -> bool {
+        self.segment_list
+            .iter()
+            .all(|segment| segment.end() <= start || end <= segment.start())
     }
 
     pub fn find_segment_mut(&mut self, address: VirtualAddress) -> Option<&mut PageSegment> {
@@ -303,6 +853,166 @@ impl PageSet {
         })
     }
 
+    /// Extends the [PageSegment] that contains a specific [VirtualAddress] downward so that it
+    /// starts at the page that contains `start_address`. Returns `false` if no segment contains the
+    /// address, or if the extended part would overlap another segment.
+    pub fn extend_segment_down(
+        &mut self,
+        address: VirtualAddress,
+        start_address: VirtualAddress,
+    ) -> Result<bool, OutOfMemory> {
+        let Some(index) = self.segment_list.iter().position(|segment| {
+            VirtualAddress::from(segment.start()) <= address
+                && address < VirtualAddress::from(segment.end())
+        }) else {
+            return Ok(false);
+        };
+
+        let start = start_address.floor();
+        let segment_start = self.segment_list[index].start();
+        if start < segment_start {
+            if !self.is_range_free(start, segment_start) {
+                return Ok(false);
+            }
+            self.segment_list[index].extend_down(&mut self.page_table, start)?;
+        }
+        Ok(true)
+    }
+
+    /// Moves the end of the [PageSegment] that starts at `start_address` to the page boundary above
+    /// `end_address`, which might leave the segment empty. Returns `false` if no segment starts at
+    /// the address, or if the grown part would overlap another segment.
+    pub fn resize_segment(
+        &mut self,
+        start_address: VirtualAddress,
+        end_address: VirtualAddress,
+    ) -> Result<bool, OutOfMemory> {
+        let Some(index) = self
+            .segment_list
+            .iter()
+            .position(|segment| segment.start() == start_address.floor())
+        else {
+            return Ok(false);
+        };
+
+        let end = end_address.ceil();
+        let segment_end = self.segment_list[index].end();
+        if end > segment_end && !self.is_range_free(segment_end, end) {
+            return Ok(false);
+        }
+        self.segment_list[index].resize(&mut self.page_table, end)?;
+        Ok(true)
+    }
+
+    /// Changes the permission of the pages from `start_address` to `end_address`, which must be
+    /// within a `Framed` user segment and aligned to its page size. The segment is split at the
+    /// boundaries of the range, so that the other pages keep their permission. Returns `false` if
+    /// no such segment contains the range.
+    pub fn protect(
+        &mut self,
+        start_address: VirtualAddress,
+        end_address: VirtualAddress,
+        map_permission: MapPermission,
+    ) -> bool {
+        let start = start_address.floor();
+        let end = end_address.ceil();
+        let trap_context_page = VirtualAddress::from(TRAP_CONTEXT_BASE).floor();
+        let Some(index) = self.segment_list.iter().position(|segment| {
+            segment.map_type == MapType::Framed
+                && segment.start() < trap_context_page
+                && segment.start() <= start
+                && end <= segment.end()
+        }) else {
+            return false;
+        };
+
+        let page_count = self.segment_list[index].page_size().page_count();
+        if usize::from(start) % page_count != 0 || usize::from(end) % page_count != 0 {
+            return false;
+        }
+
+        let mut segment = self.segment_list.remove(index);
+        if end < segment.end() {
+            let upper_segment = segment.split_off(end);
+            self.segment_list.push(upper_segment);
+        }
+        if start > segment.start() {
+            let middle_segment = segment.split_off(start);
+            self.segment_list.push(segment);
+            segment = middle_segment;
+        }
+        segment.set_permission(&mut self.page_table, map_permission);
+        self.segment_list.push(segment);
+        true
+    }
+
+    /// Returns the lowest address aligned to `page_size` from `start_address` where `size` bytes
+    /// don't overlap any [PageSegment], or `None` if such range would exceed `end_address`.
+    pub fn find_free_range(
+        &self,
+        start_address: VirtualAddress,
+        end_address: VirtualAddress,
+        size: usize,
+        page_size: PageSize,
+    ) -> Option<VirtualAddress> {
+        let alignment = page_size.page_count() * PAGE_SIZE;
+        let mut address = start_address;
+        loop {
+            address = VirtualAddress::from(
+                (usize::from(address) + alignment - 1) / alignment * alignment,
+            );
+            let range_end = address + size;
+            if range_end > end_address {
+                return None;
+            }
+
+            match self.segment_list.iter().find(|segment| {
+                VirtualAddress::from(segment.start()) < range_end
+                    && address < VirtualAddress::from(segment.end())
+            }) {
+                Some(segment) => address = VirtualAddress::from(segment.end()),
+                None => return Some(address),
+            }
+        }
+    }
+
+    /// Returns the number of frames mapped to the user segments, which excludes the trap contexts
+    /// and the zero frame. A frame that is referenced `n` times, such as a frame of a shared memory
+    /// object or a copy-on-write page, counts as `1 / n` of a frame, so that a shared frame is
+    /// counted once across the processes rather than once for each process that maps it.
+    pub fn user_frame_count(&self) -> usize {
+        /// The scale of the fixed-point fractions of frames.
+        const FRACTION_SCALE: usize = 1 << 16;
+
+        let scaled_frame_count: usize = self
+            .segment_list
+            .iter()
+            .filter(|segment| segment.start() < VirtualAddress::from(TRAP_CONTEXT_BASE).floor())
+            .map(|segment| {
+                let scaled_frame_count: usize = segment
+                    .frame_map()
+                    .values()
+                    .filter(|frame| !is_zero_frame(frame))
+                    .map(|frame| FRACTION_SCALE / Arc::strong_count(frame))
+                    .sum();
+                scaled_frame_count * segment.page_size().page_count()
+            })
+            .sum();
+        scaled_frame_count / FRACTION_SCALE
+    }
+
+    /// Removes the user segments, which excludes the trap contexts, and releases their frames.
+    pub fn remove_user_segments(&mut self) {
+        let trap_context_page = VirtualAddress::from(TRAP_CONTEXT_BASE).floor();
+        for segment in self.segment_list.iter_mut() {
+            if segment.start() < trap_context_page {
+                segment.unmap_range(&mut self.page_table);
+            }
+        }
+        self.segment_list
+            .retain(|segment| segment.start() >= trap_context_page);
+    }
+
     /// Removes a [PageSegment] that contains a specific [VirtualAddress].
     pub fn remove_segment(&mut self, address: VirtualAddress) {
         if let Some((index, segment)) =
@@ -319,13 +1029,17 @@ impl PageSet {
         }
     }
 
-    pub fn from_kernel() -> Self {
-        let mut page_set = Self::new();
+    /// Creates the [PageSet] of the kernel, which uses the reserved ASID. Only the trampoline is
+    /// mapped as a global page, since it is mapped at the same address in every address space. The
+    /// identity mappings are tagged with the reserved ASID instead, because they overlap the range
+    /// that the user address spaces might use, where a global TLB entry would shadow a user page.
+    pub fn from_kernel() -> Result<Self, OutOfMemory> {
+        let mut page_set = Self::with_asid(Asid::KERNEL)?;
         page_set.page_table.map(
             PageNumber::from(VirtualAddress::from(TRAMPOLINE)),
             FrameNumber::from(PhysicalAddress::from(trampoline_start as usize)),
-            PTEFlags::R | PTEFlags::X,
-        );
+            PTEFlags::R | PTEFlags::X | PTEFlags::G,
+        )?;
 
         page_set.push(
             PageSegment::new(
@@ -335,7 +1049,7 @@ impl PageSet {
                 MapPermission::R | MapPermission::X,
             ),
             None,
-        );
+        )?;
 
         page_set.push(
             PageSegment::new(
@@ -345,7 +1059,7 @@ impl PageSet {
                 MapPermission::R,
             ),
             None,
-        );
+        )?;
 
         page_set.push(
             PageSegment::new(
@@ -355,7 +1069,7 @@ impl PageSet {
                 MapPermission::R | MapPermission::W,
             ),
             None,
-        );
+        )?;
 
         page_set.push(
             PageSegment::new(
@@ -365,7 +1079,7 @@ impl PageSet {
                 MapPermission::R | MapPermission::W,
             ),
             None,
-        );
+        )?;
 
         page_set.push(
             PageSegment::new(
@@ -375,33 +1089,130 @@ impl PageSet {
                 MapPermission::R | MapPermission::W,
             ),
             None,
-        );
+        )?;
 
-        page_set
+        page_set.push(
+            PageSegment::new(
+                VirtualAddress::from(VIRTIO_MMIO_BASE),
+                VirtualAddress::from(VIRTIO_MMIO_BASE + VIRTIO_MMIO_COUNT * VIRTIO_MMIO_SIZE),
+                MapType::Identical,
+                MapPermission::R | MapPermission::W,
+            ),
+            None,
+        )?;
+
+        page_set.push(
+            PageSegment::new(
+                VirtualAddress::from(PLIC_CONTEXT_BASE),
+                VirtualAddress::from(PLIC_CONTEXT_BASE + 2 * MAX_HART_COUNT * PLIC_CONTEXT_SIZE),
+                MapType::Identical,
+                MapPermission::R | MapPermission::W,
+            ),
+            None,
+        )?;
+
+        Ok(page_set)
     }
 
-    pub fn from_elf(elf_data: &[u8]) -> (Self, VirtualAddress, VirtualAddress) {
-        let mut page_set = Self::new();
+    /// Loads an ELF file into a new [PageSet], together with its interpreter if `interpreter_data`
+    /// is given, and returns the [PageSet] and an [ElfImage] that describes where they are loaded.
+    /// The program break starts at a random page boundary above the loadable segments of the
+    /// executable, where an empty segment is reserved for the heap of the process.
+    ///
+    /// An executable (`ET_EXEC`) is loaded at its link address, while a position-independent
+    /// executable (`ET_DYN`) is loaded at a random base address. Without an interpreter, the
+    /// `R_RISCV_RELATIVE` relocations of a position-independent executable are applied as the
+    /// segments are copied, and other relocations are not supported. With an interpreter, the
+    /// interpreter is loaded at the base of the anonymous memory mappings if it is
+    /// position-independent, and it relocates the executable and itself.
+    /// Returns an error if a loadable segment is both writable and executable.
+    pub fn from_elf(
+        elf_data: &[u8],
+        interpreter_data: Option<&[u8]>,
+        user_layout: &UserLayout,
+    ) -> Result<(Self, ElfImage), LoadError> {
+        let mut page_set = Self::new()?;
         page_set.page_table.map(
             PageNumber::from(VirtualAddress::from(TRAMPOLINE)),
             FrameNumber::from(PhysicalAddress::from(trampoline_start as usize)),
-            PTEFlags::R | PTEFlags::X,
-        );
+            PTEFlags::R | PTEFlags::X | PTEFlags::G,
+        )?;
 
-        let elf = ElfFile::new(elf_data).unwrap();
-        assert_eq!(
-            elf.header.pt1.magic,
-            [0x7f, b'E', b'L', b'F'],
-            "the ELF is invalid"
+        let elf = elf::parse_elf(elf_data)?;
+        let is_position_independent = elf::is_position_independent(&elf);
+        let load_base = if is_position_independent {
+            usize::from(layout::pie_base(elf::load_alignment(&elf)))
+        } else {
+            0
+        };
+        let virtual_address_limit = page_set.load_segments(
+            &elf,
+            load_base,
+            is_position_independent && interpreter_data.is_none(),
+        )?;
+
+        let interpreter = match interpreter_data {
+            Some(interpreter_data) => {
+                let interpreter = elf::parse_elf(interpreter_data)?;
+                let interpreter_base = if elf::is_position_independent(&interpreter) {
+                    let alignment = elf::load_alignment(&interpreter);
+                    (usize::from(user_layout.mmap_base()) + alignment - 1) / alignment * alignment
+                } else {
+                    0
+                };
+                page_set.load_segments(&interpreter, interpreter_base, false)?;
+                Some((
+                    VirtualAddress::from(interpreter_base),
+                    VirtualAddress::from(
+                        interpreter_base + interpreter.header.pt2.entry_point() as usize,
+                    ),
+                ))
+            }
+            None => None,
+        };
+
+        let program_break = layout::program_break_start(virtual_address_limit);
+        page_set.push(
+            PageSegment::new(
+                program_break,
+                program_break,
+                MapType::Framed,
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            ),
+            None,
+        )?;
+
+        let elf_image = ElfImage::new(
+            program_break,
+            VirtualAddress::from(load_base + elf.header.pt2.entry_point() as usize),
+            elf::program_header_address(&elf, load_base),
+            elf.header.pt2.ph_count() as usize,
+            interpreter,
         );
+        Ok((page_set, elf_image))
+    }
 
+    /// Maps the loadable segments of an ELF file at `load_base` and copies their contents, and
+    /// applies the `R_RISCV_RELATIVE` relocations to the copies if `relocation` is `true`.
+    /// Returns the page boundary above the segments, or an error if a segment is both writable and
+    /// executable or lies outside the file.
+    fn load_segments(
+        &mut self,
+        elf: &ElfFile,
+        load_base: usize,
+        relocation: bool,
+    ) -> Result<VirtualAddress, LoadError> {
         let mut virtual_address_limit = VirtualAddress::from(0);
         for program_header_index in 0..elf.header.pt2.ph_count() {
-            let program_header = elf.program_header(program_header_index).unwrap();
-            if program_header.get_type().unwrap() == Type::Load {
-                let start_address = VirtualAddress::from(program_header.virtual_addr() as usize);
+            let program_header = elf
+                .program_header(program_header_index)
+                .map_err(|_| LoadError::InvalidElf)?;
+            if program_header.get_type() == Ok(Type::Load) {
+                let start_address =
+                    VirtualAddress::from(load_base + program_header.virtual_addr() as usize);
                 let end_address = VirtualAddress::from(
-                    (program_header.virtual_addr() + program_header.mem_size()) as usize,
+                    load_base
+                        + (program_header.virtual_addr() + program_header.mem_size()) as usize,
                 );
 
                 let mut map_permission = MapPermission::U;
@@ -417,29 +1228,43 @@ impl PageSet {
                     map_permission |= MapPermission::X;
                 }
 
+                if map_permission.contains(MapPermission::W | MapPermission::X) {
+                    return Err(LoadError::WritableExecutable);
+                }
+
                 let page_segment =
                     PageSegment::new(start_address, end_address, MapType::Framed, map_permission);
-                virtual_address_limit = VirtualAddress::from(page_segment.end());
+                virtual_address_limit =
+                    virtual_address_limit.max(VirtualAddress::from(page_segment.end()));
 
-                page_set.push(
-                    page_segment,
-                    Some(
-                        &elf.input[program_header.offset() as usize
-                            ..(program_header.offset() + program_header.file_size()) as usize],
-                    ),
-                );
+                let bytes = (program_header.offset() as usize)
+                    .checked_add(program_header.file_size() as usize)
+                    .and_then(|end| elf.input.get(program_header.offset() as usize..end))
+                    .ok_or(LoadError::InvalidElf)?;
+                if relocation {
+                    let mut relocated_bytes = Vec::new();
+                    relocated_bytes
+                        .try_reserve(bytes.len())
+                        .map_err(|_| OutOfMemory)?;
+                    relocated_bytes.extend_from_slice(bytes);
+                    elf::relocate(
+                        elf,
+                        load_base,
+                        program_header.virtual_addr() as usize,
+                        &mut relocated_bytes,
+                    )?;
+                    self.push(page_segment, Some(&relocated_bytes))?;
+                } else {
+                    self.push(page_segment, Some(bytes))?;
+                }
             }
         }
-
-        let user_stack_base = virtual_address_limit + PAGE_SIZE;
-        (
-            page_set,
-            user_stack_base,
-            VirtualAddress::from(elf.header.pt2.entry_point() as usize),
-        )
+        Ok(virtual_address_limit)
     }
 }
 
 lazy_static! {
-    pub static ref KERNEL_SPACE: Arc<Mutex<PageSet>> = Arc::new(Mutex::new(PageSet::from_kernel()));
+    pub static ref KERNEL_SPACE: Arc<Mutex<PageSet>> = Arc::new(Mutex::new(
+        PageSet::from_kernel().expect("failed to map the kernel space")
+    ));
 }
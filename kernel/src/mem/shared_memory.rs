@@ -0,0 +1,113 @@
+//! The `shared_memory` module provides the System V shared memory objects, which are blocks of
+//! frames that can be mapped into the address spaces of multiple processes.
+//!
+//! Each object is identified with an ID, and can be looked up with a key unless the key is
+//! `IPC_PRIVATE`. The frames are reference-counted, so the segments that map an object keep its
+//! frames alive after the object is removed, and the frames are released when the last segment is
+//! unmapped.
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+
+use crate::{
+    constant::PAGE_SIZE,
+    mem::frame_allocator::{allocate_frame, FrameTracker, OutOfMemory},
+    sync::Mutex,
+};
+
+/// The key that never matches an existing shared memory object.
+pub const IPC_PRIVATE: usize = 0;
+
+/// The `SharedMemory` struct represents a shared memory object of `size` bytes, which is backed by
+/// a list of frames.
+pub struct SharedMemory {
+    key: usize,
+    size: usize,
+    frame_list: Vec<Arc<FrameTracker>>,
+}
+
+impl SharedMemory {
+    /// Allocates the frames of a shared memory object of `size` bytes. Returns an error if the
+    /// frame allocator runs out of frames.
+    fn new(key: usize, size: usize) -> Result<Self, OutOfMemory> {
+        let frame_count = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+        let mut frame_list = Vec::new();
+        frame_list
+            .try_reserve(frame_count)
+            .map_err(|_| OutOfMemory)?;
+        for _ in 0..frame_count {
+            frame_list.push(Arc::new(allocate_frame().ok_or(OutOfMemory)?));
+        }
+
+        Ok(Self {
+            key,
+            size,
+            frame_list,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn frame_list(&self) -> &[Arc<FrameTracker>] {
+        &self.frame_list
+    }
+}
+
+/// The `SharedMemoryTable` struct maps the IDs to the shared memory objects.
+struct SharedMemoryTable {
+    next_id: usize,
+    object_map: BTreeMap<usize, Arc<SharedMemory>>,
+}
+
+static SHARED_MEMORY_TABLE: Mutex<SharedMemoryTable> = Mutex::new(SharedMemoryTable {
+    next_id: 1,
+    object_map: BTreeMap::new(),
+});
+
+/// Returns the ID and the object of the shared memory object with a specific key, or `None` if
+/// the key is `IPC_PRIVATE` or no object has the key.
+pub fn find_shared_memory(key: usize) -> Option<(usize, Arc<SharedMemory>)> {
+    if key == IPC_PRIVATE {
+        return None;
+    }
+
+    SHARED_MEMORY_TABLE
+        .lock()
+        .object_map
+        .iter()
+        .find(|(_, shared_memory)| shared_memory.key == key)
+        .map(|(id, shared_memory)| (*id, shared_memory.clone()))
+}
+
+/// Creates a shared memory object of `size` bytes with a specific key, and returns its ID.
+/// Returns an error if the frame allocator runs out of frames.
+pub fn create_shared_memory(key: usize, size: usize) -> Result<usize, OutOfMemory> {
+    let shared_memory = Arc::new(SharedMemory::new(key, size)?);
+    let mut shared_memory_table = SHARED_MEMORY_TABLE.lock();
+    let id = shared_memory_table.next_id;
+    shared_memory_table.next_id += 1;
+    shared_memory_table.object_map.insert(id, shared_memory);
+    Ok(id)
+}
+
+/// Returns the number of pages of the shared memory objects that haven't been removed.
+pub fn shared_memory_page_count() -> usize {
+    SHARED_MEMORY_TABLE
+        .lock()
+        .object_map
+        .values()
+        .map(|shared_memory| shared_memory.frame_list.len())
+        .sum()
+}
+
+/// Returns the shared memory object with a specific ID.
+pub fn get_shared_memory(id: usize) -> Option<Arc<SharedMemory>> {
+    SHARED_MEMORY_TABLE.lock().object_map.get(&id).cloned()
+}
+
+/// Removes the shared memory object with a specific ID, whose frames are released after every
+/// segment that maps them is unmapped. Returns `false` if no object has the ID.
+pub fn remove_shared_memory(id: usize) -> bool {
+    SHARED_MEMORY_TABLE.lock().object_map.remove(&id).is_some()
+}
@@ -0,0 +1,140 @@
+//! The `swap` module manages the swap area on a virtio block device, where the pages of the user
+//! processes are written when the frames run low.
+//!
+//! The swap area is divided into slots of a page. A slot is shared by the processes forked after
+//! its page was swapped out, so each slot is reference-counted with [SwapSlot] and released when
+//! the last process drops it.
+
+use alloc::{vec, vec::Vec};
+
+use log::info;
+
+use crate::{
+    constant::PAGE_SIZE,
+    drivers::{IoError, VirtioBlock, SECTOR_SIZE},
+    mem::FrameNumber,
+    sync::Mutex,
+};
+
+/// The number of sectors in a slot of the swap area.
+const SECTORS_PER_SLOT: usize = PAGE_SIZE / SECTOR_SIZE;
+
+/// The `SwapArea` struct represents the swap area on a block device, and stores the number of
+/// references to each slot.
+struct SwapArea {
+    device: VirtioBlock,
+    reference_count: Vec<u16>,
+    next_index: usize,
+}
+
+impl SwapArea {
+    /// Returns the index of a free slot, which is searched from the slot after the last allocated
+    /// one.
+    fn allocate(&mut self) -> Option<usize> {
+        let slot_count = self.reference_count.len();
+        let index = (0..slot_count)
+            .map(|offset| (self.next_index + offset) % slot_count)
+            .find(|&index| self.reference_count[index] == 0)?;
+
+        self.reference_count[index] = 1;
+        self.next_index = (index + 1) % slot_count;
+        Some(index)
+    }
+
+    fn write(&mut self, index: usize, frame_number: FrameNumber) -> Result<(), IoError> {
+        self.device
+            .write(index * SECTORS_PER_SLOT, frame_number.as_bytes())
+    }
+}
+
+static SWAP_AREA: Mutex<Option<SwapArea>> = Mutex::new(None);
+
+/// Uses the first virtio block device as the swap area. The pages are never swapped out if no
+/// block device is attached.
+pub fn init() {
+    let Some(device) = VirtioBlock::probe() else {
+        info!("no block device is attached, so swapping is disabled");
+        return;
+    };
+
+    let slot_count = device.sector_count() / SECTORS_PER_SLOT;
+    info!("swap area: {} slots of {} bytes", slot_count, PAGE_SIZE);
+    *SWAP_AREA.lock() = Some(SwapArea {
+        device,
+        reference_count: vec![0; slot_count],
+        next_index: 0,
+    });
+}
+
+/// Returns `true` if the swap area is available.
+pub fn is_swap_enabled() -> bool {
+    SWAP_AREA.lock().is_some()
+}
+
+/// The `SwapSlot` struct represents a reference to a slot of the swap area, which holds the
+/// contents of a page. Cloning the struct adds a reference to the slot, and the slot is released
+/// when every reference is dropped, which follows the RAII idiom.
+pub struct SwapSlot {
+    index: usize,
+}
+
+impl SwapSlot {
+    /// Allocates a slot and writes the frame to it. Returns `None` if the swap area is full or
+    /// unavailable, or if the device fails to write the frame.
+    pub fn allocate(frame_number: FrameNumber) -> Option<Self> {
+        let mut swap_area = SWAP_AREA.lock();
+        let swap_area = swap_area.as_mut()?;
+        let index = swap_area.allocate()?;
+        if swap_area.write(index, frame_number).is_err() {
+            swap_area.reference_count[index] = 0;
+            return None;
+        }
+        Some(Self { index })
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns `true` if no other process holds a reference to the slot.
+    pub fn is_exclusive(&self) -> bool {
+        SWAP_AREA.lock().as_ref().unwrap().reference_count[self.index] == 1
+    }
+
+    /// Overwrites the contents of the slot with the frame, which requires the slot to be
+    /// exclusive.
+    pub fn write(&self, frame_number: FrameNumber) -> Result<(), IoError> {
+        SWAP_AREA
+            .lock()
+            .as_mut()
+            .unwrap()
+            .write(self.index, frame_number)
+    }
+
+    /// Reads the contents of the slot to the frame.
+    pub fn read(&self, frame_number: FrameNumber) -> Result<(), IoError> {
+        SWAP_AREA
+            .lock()
+            .as_mut()
+            .unwrap()
+            .device
+            .read(self.index * SECTORS_PER_SLOT, frame_number.as_bytes_mut())
+    }
+}
+
+impl Clone for SwapSlot {
+    fn clone(&self) -> Self {
+        let mut swap_area = SWAP_AREA.lock();
+        let reference_count = &mut swap_area.as_mut().unwrap().reference_count[self.index];
+        *reference_count = reference_count
+            .checked_add(1)
+            .expect("too many references to a swap slot");
+        Self { index: self.index }
+    }
+}
+
+impl Drop for SwapSlot {
+    fn drop(&mut self) {
+        SWAP_AREA.lock().as_mut().unwrap().reference_count[self.index] -= 1;
+    }
+}
@@ -1,105 +1,239 @@
+//! The `user_ptr` module provides checked access to the memory of user processes.
+//! The kernel never dereferences a user pointer directly. Instead, each access translates the
+//! pointer page by page with the page table of the process, which checks that the page belongs to
+//! user space and has the required permission. A write to a copy-on-write page copies the frame,
+//! and an access below the user stack extends the stack, as a page fault in user space would.
+//!
+//! The kernel page table doesn't map user space, so a stray dereference of a user pointer faults
+//! instead of reaching user memory. The `SUM` and `MXR` bits of the `sstatus` register are never
+//! set, since the kernel never accesses a user page through its user virtual address, and the
+//! translation likewise refuses to read execute-only pages.
+//!
+//! The translated pages are accessed through the identical mapping of the physical memory, which
+//! shouldn't fault. Every copy from or to user memory, including [strncpy_from_user], still goes
+//! through [_copy_user_bytes], whose loads and stores are listed in the fixup table, so that a
+//! fault turns into an error instead of a kernel panic.
+
 use alloc::{string::String, vec::Vec};
-use core::{
-    marker::PhantomData,
-    ops::{Deref, DerefMut},
-};
+use core::{arch::asm, marker::PhantomData, mem, slice};
+
+use riscv::register::sstatus;
 
 use crate::{
     constant::PAGE_SIZE,
-    mem::{address::PageRange, page_table::PageTable, PageNumber, VirtualAddress},
+    mem::{page_table::user_address_limit, VirtualAddress},
+    task::Thread,
 };
 
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct UserPtr<T> {
-    satp: usize,
-    ptr: *mut T,
-    phantom: PhantomData<T>,
+/// The `UserAccessError` enum represents the reasons that an access to user memory fails.
+#[derive(Debug, PartialEq, Eq)]
+pub enum UserAccessError {
+    /// The address is not mapped in user space or lacks the required permission.
+    Fault,
+    /// The kernel runs out of memory while copying a frame or growing a buffer.
+    OutOfMemory,
+    /// The string is not terminated within the length limit.
+    TooLong,
 }
 
-unsafe impl<T: Clone + Copy + 'static> Send for UserPtr<T> {}
-unsafe impl<T: Clone + Copy + 'static> Sync for UserPtr<T> {}
+/// The `UserAccess` enum represents the kind of an access to user memory.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum UserAccess {
+    Read,
+    Write,
+}
 
-impl<T> UserPtr<T> {
-    pub fn new(satp: usize, ptr: usize) -> Self {
-        Self {
-            satp,
-            ptr: ptr as *mut T,
-            phantom: PhantomData,
-        }
+/// Clears the `SUM` and `MXR` bits of the `sstatus` register, which the kernel never sets.
+pub fn init() {
+    unsafe {
+        sstatus::clear_sum();
+        sstatus::clear_mxr();
     }
+}
 
-    pub fn as_string(&self) -> String {
-        let page_table = PageTable::from_satp(self.satp);
-        let mut virtual_address = VirtualAddress::from(self.ptr as usize);
-        let mut string = String::new();
-        loop {
-            let char_pointer = page_table.translate(virtual_address).unwrap().as_ptr() as *const u8;
+/// Returns the [VirtualAddress] that starts a user buffer of `length` bytes, or an error if any
+/// part of the buffer is outside user space.
+fn user_address(address: usize, length: usize) -> Result<VirtualAddress, UserAccessError> {
+    match address.checked_add(length) {
+        Some(end) if end <= user_address_limit() => Ok(VirtualAddress::from(address)),
+        _ => Err(UserAccessError::Fault),
+    }
+}
 
-            let char = unsafe { *char_pointer as char };
+/// Copies `length` bytes from `source` to `destination`, and returns 0, or 1 if an access faults.
+/// The load and the store are listed in the fixup table, which resumes a faulting access at the
+/// label that returns 1.
+#[naked]
+unsafe extern "C" fn _copy_user_bytes(
+    destination: *mut u8,
+    source: *const u8,
+    length: usize,
+) -> usize {
+    asm!(
+        "li a3, 0",
+        "beqz a2, 2f",
+        "1:",
+        "3: lbu t0, 0(a1)",
+        "4: sb t0, 0(a0)",
+        "addi a0, a0, 1",
+        "addi a1, a1, 1",
+        "addi a2, a2, -1",
+        "bnez a2, 1b",
+        "2:",
+        "mv a0, a3",
+        "ret",
+        // Returns 1, where the trap handler resumes a faulting access
+        "6:",
+        "li a3, 1",
+        "j 2b",
+        ".pushsection .fixup_table, \"a\"",
+        ".balign 8",
+        ".dword 3b, 6b",
+        ".dword 4b, 6b",
+        ".popsection",
+        options(noreturn)
+    )
+}
 
-            if char == '\0' {
-                break;
-            }
-            string.push(char);
-            virtual_address += 1;
-        }
-        string
+/// Copies the bytes of `source` to `destination`, which have the same length, and returns an error
+/// if an access faults.
+fn copy_user_bytes(
+    destination: *mut u8,
+    source: *const u8,
+    length: usize,
+) -> Result<(), UserAccessError> {
+    match unsafe { _copy_user_bytes(destination, source, length) } {
+        0 => Ok(()),
+        _ => Err(UserAccessError::Fault),
     }
+}
 
-    pub fn as_buffer(&self, length: usize) -> Vec<&'static [u8]> {
-        let page_table = PageTable::from_satp(self.satp);
-        let mut translated_buffer = Vec::new();
-
-        let buffer_address_start = VirtualAddress::from(self.ptr as usize);
-        let buffer_address_end = buffer_address_start + length;
-
-        let page_range = PageRange::new(
-            PageNumber::from(buffer_address_start),
-            PageNumber::from(buffer_address_end).offset(1),
-        );
-
-        for (index, page_number) in page_range.iter().enumerate() {
-            let frame_number = page_table
-                .translate_page(page_number)
-                .unwrap()
-                .frame_number();
-            let lower_bound = {
-                if index == 0 {
-                    buffer_address_start.page_offset()
-                } else {
-                    0
-                }
-            };
-
-            let upper_bound = {
-                if index == page_range.len() - 1 {
-                    buffer_address_end.page_offset()
-                } else {
-                    PAGE_SIZE
-                }
-            };
-            translated_buffer.push(&frame_number.as_bytes()[lower_bound..upper_bound]);
-        }
+/// Copies `destination.len()` bytes from the user memory at `source` to `destination`.
+pub fn copy_from_user(
+    thread: &Thread,
+    destination: &mut [u8],
+    source: usize,
+) -> Result<(), UserAccessError> {
+    let mut virtual_address = user_address(source, destination.len())?;
+    let mut offset = 0;
+    while offset < destination.len() {
+        let length = (PAGE_SIZE - virtual_address.page_offset()).min(destination.len() - offset);
+        let physical_address = thread.translate_user(virtual_address, UserAccess::Read)?;
+        copy_user_bytes(
+            destination[offset..].as_mut_ptr(),
+            physical_address.as_ptr(),
+            length,
+        )?;
+
+        offset += length;
+        virtual_address += length;
+    }
+    Ok(())
+}
 
-        translated_buffer
+/// Copies the bytes of `source` to the user memory at `destination`.
+pub fn copy_to_user(
+    thread: &Thread,
+    destination: usize,
+    source: &[u8],
+) -> Result<(), UserAccessError> {
+    let mut virtual_address = user_address(destination, source.len())?;
+    let mut offset = 0;
+    while offset < source.len() {
+        let length = (PAGE_SIZE - virtual_address.page_offset()).min(source.len() - offset);
+        let physical_address = thread.translate_user(virtual_address, UserAccess::Write)?;
+        copy_user_bytes(
+            physical_address.as_ptr_mut(),
+            source[offset..].as_ptr(),
+            length,
+        )?;
+
+        offset += length;
+        virtual_address += length;
     }
+    Ok(())
 }
 
-impl<T: 'static> Deref for UserPtr<T> {
-    type Target = T;
+/// Copies the null-terminated string at `source` in user memory into a [String], which contains
+/// at most `length_limit` bytes excluding the null terminator. Returns an error if the string is
+/// longer than the limit.
+pub fn strncpy_from_user(
+    thread: &Thread,
+    source: usize,
+    length_limit: usize,
+) -> Result<String, UserAccessError> {
+    let mut virtual_address = user_address(source, 0)?;
+    let mut bytes = Vec::new();
+    loop {
+        if usize::from(virtual_address) >= user_address_limit() {
+            return Err(UserAccessError::Fault);
+        }
 
-    fn deref(&self) -> &T {
-        let page_table = PageTable::from_satp(self.satp);
-        let virtual_address = VirtualAddress::from(self.ptr as usize);
-        page_table.translate(virtual_address).unwrap().as_ref()
+        // Copies the rest of the page, but no more than one byte beyond the limit, which is enough
+        // to tell whether the string is too long
+        let length =
+            (PAGE_SIZE - virtual_address.page_offset()).min(length_limit + 1 - bytes.len());
+        let physical_address = thread.translate_user(virtual_address, UserAccess::Read)?;
+        let start = bytes.len();
+        bytes
+            .try_reserve(length)
+            .map_err(|_| UserAccessError::OutOfMemory)?;
+        bytes.resize(start + length, 0);
+        copy_user_bytes(
+            bytes[start..].as_mut_ptr(),
+            physical_address.as_ptr(),
+            length,
+        )?;
+
+        if let Some(terminator) = bytes[start..].iter().position(|byte| *byte == 0) {
+            bytes.truncate(start + terminator);
+            return Ok(String::from_utf8_lossy(&bytes).into_owned());
+        }
+        if bytes.len() > length_limit {
+            return Err(UserAccessError::TooLong);
+        }
+        virtual_address += length;
     }
 }
 
-impl<T: 'static> DerefMut for UserPtr<T> {
-    fn deref_mut(&mut self) -> &mut T {
-        let page_table = PageTable::from_satp(self.satp);
-        let virtual_address = VirtualAddress::from(self.ptr as usize);
-        page_table.translate(virtual_address).unwrap().as_mut()
+/// The `UserPtr` struct represents a pointer to a value of type `T` in user memory, which is
+/// accessed with [copy_from_user] and [copy_to_user].
+#[derive(Clone, Copy)]
+pub struct UserPtr<T> {
+    address: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Copy> UserPtr<T> {
+    pub fn new(address: usize) -> Self {
+        Self {
+            address,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.address == 0
+    }
+
+    /// Copies the value that the pointer points to from user memory.
+    pub fn read(&self, thread: &Thread) -> Result<T, UserAccessError> {
+        let mut value = mem::MaybeUninit::<T>::uninit();
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, mem::size_of::<T>())
+        };
+        copy_from_user(thread, bytes, self.address)?;
+        Ok(unsafe { value.assume_init() })
+    }
+
+    /// Copies a value to user memory where the pointer points to.
+    pub fn write(&self, thread: &Thread, value: T) -> Result<(), UserAccessError> {
+        let bytes =
+            unsafe { slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>()) };
+        copy_to_user(thread, self.address, bytes)
     }
 }
@@ -0,0 +1,79 @@
+//! The `random` module provides an entropy pool that generates random numbers for the kernel.
+//! The pool is seeded with the time at boot, and the time of each timer interrupt is mixed into the
+//! pool, which depends on how long the threads have run. The numbers are generated with the
+//! xoshiro256** algorithm, which is fast but not cryptographically secure.
+
+use riscv::register::time;
+
+use crate::sync::IrqMutex;
+
+/// The `EntropyPool` struct represents the state of the xoshiro256** generator.
+struct EntropyPool {
+    state: [u64; 4],
+    mix_index: usize,
+}
+
+impl EntropyPool {
+    /// Mixes `value` into a word of the state, and advances the generator so that the value
+    /// affects every word.
+    fn add_entropy(&mut self, value: u64) {
+        self.state[self.mix_index] ^= splitmix64(value);
+        self.mix_index = (self.mix_index + 1) % self.state.len();
+        self.next();
+    }
+
+    fn next(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.state[1] << 17;
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+        result
+    }
+}
+
+/// Scrambles the bits of `value`, which turns the similar timestamps into unrelated words.
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// The entropy pool is an [IrqMutex] since the timer interrupt handler mixes the time into it.
+static ENTROPY_POOL: IrqMutex<EntropyPool> = IrqMutex::new(EntropyPool {
+    state: [
+        0x9e3779b97f4a7c15,
+        0xbf58476d1ce4e5b9,
+        0x94d049bb133111eb,
+        0x2545f4914f6cdd1d,
+    ],
+    mix_index: 0,
+});
+
+/// Seeds the entropy pool with the time at boot.
+pub fn init() {
+    add_entropy(time::read());
+}
+
+/// Mixes `value` into the entropy pool.
+pub fn add_entropy(value: usize) {
+    ENTROPY_POOL.lock().add_entropy(value as u64);
+}
+
+/// Returns a random number from the entropy pool.
+pub fn random_usize() -> usize {
+    ENTROPY_POOL.lock().next() as usize
+}
+
+/// Fills `buffer` with random bytes from the entropy pool.
+pub fn fill_random(buffer: &mut [u8]) {
+    let mut entropy_pool = ENTROPY_POOL.lock();
+    for chunk in buffer.chunks_mut(8) {
+        let bytes = entropy_pool.next().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
@@ -0,0 +1,68 @@
+//! The `async_mutex` module provides an [AsyncMutex], whose lock future sleeps instead of spinning
+//! while the lock is held by another task.
+
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+};
+
+use crate::sync::{semaphore::SemaphorePermit, Semaphore};
+
+/// The `AsyncMutex` struct is a mutual exclusion primitive for the data that is held across an
+/// `await`. The lock is handed over to the tasks in the order that they started waiting.
+pub struct AsyncMutex<T> {
+    semaphore: Semaphore,
+    cell: UnsafeCell<T>,
+}
+
+impl<T> AsyncMutex<T> {
+    /// Creates a new `AsyncMutex` with the given initial value.
+    pub const fn new(value: T) -> Self {
+        Self {
+            semaphore: Semaphore::new(1),
+            cell: UnsafeCell::new(value),
+        }
+    }
+
+    /// Acquires the lock, and sleeps until it is released if it is held by another task. Returns
+    /// an [AsyncMutexGuard] that provides exclusive access to the shared data.
+    pub async fn lock(&self) -> AsyncMutexGuard<'_, T> {
+        let permit = self.semaphore.acquire().await;
+        AsyncMutexGuard {
+            mutex: self,
+            _permit: permit,
+        }
+    }
+}
+
+unsafe impl<T: Send> Sync for AsyncMutex<T> {}
+
+unsafe impl<T: Send> Send for AsyncMutex<T> {}
+
+/// The `AsyncMutexGuard` struct is an RAII guard of an [AsyncMutex]. When the guard goes out of
+/// scope, the lock is released.
+pub struct AsyncMutexGuard<'a, T> {
+    mutex: &'a AsyncMutex<T>,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl<'a, T> AsyncMutexGuard<'a, T> {
+    /// Returns the [AsyncMutex] that the guard locks.
+    pub fn mutex(&self) -> &'a AsyncMutex<T> {
+        self.mutex
+    }
+}
+
+impl<T> Deref for AsyncMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.cell.get() }
+    }
+}
+
+impl<T> DerefMut for AsyncMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.cell.get() }
+    }
+}
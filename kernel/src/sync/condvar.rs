@@ -0,0 +1,52 @@
+//! The `condvar` module provides a [Condvar], which suspends tasks until a condition on the data
+//! protected by an [AsyncMutex] might have changed.
+
+use crate::sync::{async_mutex::AsyncMutexGuard, WaitQueue};
+
+/// The `Condvar` struct is a condition variable that is used with an [AsyncMutex].
+pub struct Condvar {
+    wait_queue: WaitQueue,
+}
+
+impl Condvar {
+    /// Creates a new `Condvar`.
+    pub const fn new() -> Self {
+        Self {
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// Releases the lock of `guard`, sleeps until the condition variable is notified, and acquires
+    /// the lock again. The task starts waiting before the lock is released, so a notification
+    /// sent by the next holder of the lock is never lost.
+    pub async fn wait<'a, T>(&self, guard: AsyncMutexGuard<'a, T>) -> AsyncMutexGuard<'a, T> {
+        let mutex = guard.mutex();
+        let wait_future = self.wait_queue.wait();
+        drop(guard);
+
+        wait_future.await;
+        mutex.lock().await
+    }
+
+    /// Waits on the condition variable until `condition` returns `false`.
+    pub async fn wait_while<'a, T, F: FnMut(&mut T) -> bool>(
+        &self,
+        mut guard: AsyncMutexGuard<'a, T>,
+        mut condition: F,
+    ) -> AsyncMutexGuard<'a, T> {
+        while condition(&mut guard) {
+            guard = self.wait(guard).await;
+        }
+        guard
+    }
+
+    /// Wakes the task that has waited the longest. Returns `false` if no task is waiting.
+    pub fn notify_one(&self) -> bool {
+        self.wait_queue.notify_one()
+    }
+
+    /// Wakes all the waiting tasks, and returns the number of tasks woken.
+    pub fn notify_all(&self) -> usize {
+        self.wait_queue.notify_all()
+    }
+}
@@ -1,11 +1,11 @@
 //! The `event_bus` module provides an [EventBus] that supports publish-subscribe-style
 //! communication between different tasks.
 
-use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc};
 use core::{
     future::Future,
     pin::Pin,
-    task::{Context, Poll},
+    task::{Context, Poll, Waker},
 };
 
 use bitflags::bitflags;
@@ -13,7 +13,7 @@ use bitflags::bitflags;
 use crate::sync::Mutex;
 
 bitflags! {
-    #[derive(Default, Copy, Clone)]
+    #[derive(Default, Copy, Clone, PartialEq, Eq)]
     /// The `Event` struct represents events that can be subscribed to on [EventBus].
   pub struct Event: u32 {
     /// Indicates that a child process has quit.
@@ -21,13 +21,16 @@ bitflags! {
   }
 }
 
+/// The callback of a subscription, which returns `true` if it has fired and should be removed.
 type EventCallback = Box<dyn Fn(Event) -> bool + Send>;
 
-/// The `EventBus` structsupports publish-subscribe-style communication between different tasks.
+/// The `EventBus` struct supports publish-subscribe-style communication between different tasks.
+/// The published events stay on the bus until they are consumed by [wait_for_event].
 #[derive(Default)]
 pub struct EventBus {
     event: Event,
-    callback_list: Vec<EventCallback>,
+    next_subscription_id: usize,
+    callback_map: BTreeMap<usize, EventCallback>,
 }
 
 impl EventBus {
@@ -36,57 +39,100 @@ impl EventBus {
         Arc::new(Mutex::new(Self::default()))
     }
 
-    /// Publishes an event on the event bus.
+    /// Publishes an event on the event bus, and removes the callbacks that have fired.
     pub fn push(&mut self, event: Event) {
-        self.event.set(event, true);
-        for callback in &self.callback_list {
-            callback(event);
-        }
+        self.event.insert(event);
+        self.callback_map.retain(|_, callback| !callback(event));
+    }
+
+    /// Subscribes to events on the event bus with a callback, and returns the ID of the
+    /// subscription.
+    fn subscribe(&mut self, callback: EventCallback) -> usize {
+        let subscription_id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        self.callback_map.insert(subscription_id, callback);
+        subscription_id
     }
 
-    /// Clears an event from the event bus.
-    pub fn clear(&mut self, event: Event) {
-        self.event.remove(event);
+    /// Returns the number of callbacks that are subscribed to the event bus.
+    pub fn subscription_count(&self) -> usize {
+        self.callback_map.len()
     }
 
-    /// Subscribes to events on the event bus and executes the given callback function when an event
-    /// is published.
-    pub fn subscribe(&mut self, callback: EventCallback) {
-        self.callback_list.push(callback);
+    fn is_subscribed(&self, subscription_id: usize) -> bool {
+        self.callback_map.contains_key(&subscription_id)
+    }
+}
+
+/// The `Subscription` struct is a handle to a callback on an [EventBus], which is removed when the
+/// handle is dropped, unless it has been removed since it fired.
+struct Subscription {
+    event_bus: Arc<Mutex<EventBus>>,
+    id: usize,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.event_bus.lock().callback_map.remove(&self.id);
     }
 }
 
-/// The `EventBusFuture` struct is a future that completes when a specified event is published on
-/// an [EventBus].
+/// The `EventBusFuture` struct is a future that completes when any of the subscribed events is
+/// published on an [EventBus]. The future subscribes to the bus at most once at a time, with a
+/// callback that wakes the latest waker and is removed when it fires.
 struct EventBusFuture {
     event_bus: Arc<Mutex<EventBus>>,
     subscribed_event: Event,
+    waker: Arc<Mutex<Option<Waker>>>,
+    subscription: Option<Subscription>,
 }
 
 impl Future for EventBusFuture {
     type Output = Event;
 
-    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Event> {
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Event> {
+        *self.waker.lock() = Some(context.waker().clone());
+
         let mut event_bus = self.event_bus.lock();
-        if event_bus.event.contains(self.subscribed_event) {
-            return Poll::Ready(event_bus.event);
+        // The events are checked and consumed under the same lock, so an event is consumed once
+        let event = event_bus.event & self.subscribed_event;
+        if !event.is_empty() {
+            event_bus.event.remove(event);
+            return Poll::Ready(event);
+        }
+
+        let is_subscribed = self
+            .subscription
+            .as_ref()
+            .is_some_and(|subscription| event_bus.is_subscribed(subscription.id));
+        if is_subscribed {
+            return Poll::Pending;
         }
 
         let subscribed_event = self.subscribed_event;
-        let waker = context.waker().clone();
-        event_bus.subscribe(Box::new(move |event| {
-            if event.contains(subscribed_event) {
-                waker.wake_by_ref();
-                true
-            } else {
-                false
+        let waker = self.waker.clone();
+        let subscription_id = event_bus.subscribe(Box::new(move |event| {
+            if !event.intersects(subscribed_event) {
+                return false;
+            }
+            if let Some(waker) = waker.lock().take() {
+                waker.wake();
             }
+            true
         }));
+        drop(event_bus);
+
+        // The previous subscription has fired, so dropping its handle only takes the lock again
+        self.subscription = Some(Subscription {
+            event_bus: self.event_bus.clone(),
+            id: subscription_id,
+        });
         Poll::Pending
     }
 }
 
-/// Returns a future that completes when a specified event is published on an [EventBus].
+/// Returns a future that completes when any of the subscribed events is published on an
+/// [EventBus], which consumes and returns the published events among them.
 pub fn wait_for_event(
     event_bus: Arc<Mutex<EventBus>>,
     subscribed_event: Event,
@@ -94,5 +140,7 @@ pub fn wait_for_event(
     EventBusFuture {
         event_bus,
         subscribed_event,
+        waker: Arc::new(Mutex::new(None)),
+        subscription: None,
     }
 }
@@ -1,7 +1,21 @@
-//! The `sync` module provides synchronization primitives for concurrent programming.
+//! The `sync` module provides synchronization primitives for concurrent programming. The [Mutex]
+//! spins while the lock is held, while the [WaitQueue] and the primitives built on it, such as the
+//! [AsyncMutex], suspend the waiting tasks until they are notified.
 
+mod async_mutex;
+mod condvar;
 mod event_bus;
 mod mutex;
+mod rw_lock;
+mod self_test;
+mod semaphore;
+mod wait_queue;
 
+pub use async_mutex::AsyncMutex;
+pub use condvar::Condvar;
 pub use event_bus::{wait_for_event, Event, EventBus};
-pub use mutex::{Mutex, MutexGuard};
+pub use mutex::{IrqMutex, Mutex, MutexGuard};
+pub use rw_lock::RwLock;
+pub use self_test::spawn_self_test;
+pub use semaphore::Semaphore;
+pub use wait_queue::WaitQueue;
@@ -1,4 +1,6 @@
-//! The `mutex` module provides a mutual exclusion primitive useful for protecting shared data.
+//! The `mutex` module provides mutual exclusion primitives useful for protecting shared data. The
+//! [Mutex] leaves the interrupts enabled, while the [IrqMutex] disables them while it is held,
+//! which is required for the locks taken by interrupt handlers.
 
 use core::{
     cell::UnsafeCell,
@@ -8,6 +10,8 @@ use core::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
+use crate::hart;
+
 /// The `Mutex` struct is a mutual exclusion primitive useful for protecting shared data, which
 /// implements the [Send] and [Sync] traits.
 pub struct Mutex<T> {
@@ -18,7 +22,7 @@ pub struct Mutex<T> {
 
 impl<T> Mutex<T> {
     /// Creates a new `Mutex` with the given initial value.
-    pub fn new(value: T) -> Self {
+    pub const fn new(value: T) -> Self {
         Self {
             lock: AtomicBool::new(false),
             cell: UnsafeCell::new(value),
@@ -27,8 +31,20 @@ impl<T> Mutex<T> {
     }
 
     /// Acquires a lock on the `Mutex` and returns a [MutexGuard] that provides exclusive access to
-    /// the shared resource.
+    /// the shared resource. A lock taken by an interrupt handler might be held by the code that it
+    /// interrupts, which would spin forever, so the debug build panics if the lock is taken in an
+    /// interrupt handler, which should use an [IrqMutex] instead.
     pub fn lock(&self) -> MutexGuard<T> {
+        debug_assert!(
+            !hart::is_in_interrupt(),
+            "a Mutex is locked in an interrupt handler, which should be an IrqMutex"
+        );
+        self.acquire();
+        MutexGuard::new(self)
+    }
+
+    /// Spins until the lock is acquired.
+    fn acquire(&self) {
         while self
             .lock
             .compare_exchange(false, true, Ordering::Acquire, Ordering::Acquire)
@@ -38,8 +54,20 @@ impl<T> Mutex<T> {
                 hint::spin_loop();
             }
         }
+    }
 
-        MutexGuard::new(self)
+    /// Attempts to acquire a lock on the `Mutex` without spinning, and returns `None` if the lock
+    /// is held by others. As with [Mutex::lock], the debug build panics if the lock is taken in an
+    /// interrupt handler.
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        debug_assert!(
+            !hart::is_in_interrupt(),
+            "a Mutex is locked in an interrupt handler, which should be an IrqMutex"
+        );
+        self.lock
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard::new(self))
     }
 
     /// Releases the lock on the `Mutex`.
@@ -84,3 +112,55 @@ impl<'a, T> Drop for MutexGuard<'a, T> {
         self.mutex.unlock();
     }
 }
+
+/// The `IrqMutex` struct is a [Mutex] that disables the interrupts on the current hart while it is
+/// held, so that an interrupt handler that takes the lock can't interrupt a holder of the lock on
+/// the same hart. The interrupts are enabled again when the outermost [IrqMutexGuard] is dropped,
+/// if they were enabled before the lock was taken.
+pub struct IrqMutex<T> {
+    mutex: Mutex<T>,
+}
+
+impl<T> IrqMutex<T> {
+    /// Creates a new `IrqMutex` with the given initial value.
+    pub const fn new(value: T) -> Self {
+        Self {
+            mutex: Mutex::new(value),
+        }
+    }
+
+    /// Disables the interrupts, acquires a lock on the `IrqMutex`, and returns an [IrqMutexGuard]
+    /// that provides exclusive access to the shared resource.
+    pub fn lock(&self) -> IrqMutexGuard<T> {
+        hart::disable_interrupt();
+        self.mutex.acquire();
+        IrqMutexGuard { mutex: &self.mutex }
+    }
+}
+
+/// The `IrqMutexGuard` struct is an RAII guard of an [IrqMutex]. When the guard goes out of scope,
+/// the lock is released and the interrupts are restored.
+pub struct IrqMutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<'a, T> Deref for IrqMutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.cell.get() }
+    }
+}
+
+impl<'a, T> DerefMut for IrqMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.cell.get() }
+    }
+}
+
+impl<'a, T> Drop for IrqMutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+        hart::restore_interrupt();
+    }
+}
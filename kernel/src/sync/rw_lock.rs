@@ -0,0 +1,168 @@
+//! The `rw_lock` module provides a [RwLock], which allows either a number of readers or a single
+//! writer at a time, and whose waiting tasks sleep instead of spinning.
+
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+};
+
+use crate::sync::{wait_queue::wait_for_handoff, Mutex, WaitQueue};
+
+/// The `RwLockState` struct represents the holders of a [RwLock].
+struct RwLockState {
+    reader_count: usize,
+    writer: bool,
+}
+
+/// The `RwLock` struct is a reader-writer lock that alternates between the readers and the
+/// writers. A reader that arrives while a writer is waiting waits behind it, so the writers can't
+/// be starved, and a writer that releases the lock admits all the waiting readers at once before
+/// the next writer, so the readers can't be starved either.
+pub struct RwLock<T> {
+    state: Mutex<RwLockState>,
+    reader_queue: WaitQueue,
+    writer_queue: WaitQueue,
+    cell: UnsafeCell<T>,
+}
+
+impl<T> RwLock<T> {
+    /// Creates a new `RwLock` with the given initial value.
+    pub const fn new(value: T) -> Self {
+        Self {
+            state: Mutex::new(RwLockState {
+                reader_count: 0,
+                writer: false,
+            }),
+            reader_queue: WaitQueue::new(),
+            writer_queue: WaitQueue::new(),
+            cell: UnsafeCell::new(value),
+        }
+    }
+
+    /// Acquires a shared lock, and sleeps while a writer holds the lock or waits for it.
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        let wait_future = {
+            let mut state = self.state.lock();
+            if !state.writer && self.writer_queue.is_empty() {
+                state.reader_count += 1;
+                return RwLockReadGuard { lock: self };
+            }
+            self.reader_queue.wait()
+        };
+
+        wait_for_handoff(wait_future, |is_handed_over| {
+            if is_handed_over {
+                self.read_unlock();
+            }
+        })
+        .await;
+        RwLockReadGuard { lock: self }
+    }
+
+    /// Acquires an exclusive lock, and sleeps while the lock is held or other tasks wait for it.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let wait_future = {
+            let mut state = self.state.lock();
+            if !state.writer
+                && state.reader_count == 0
+                && self.reader_queue.is_empty()
+                && self.writer_queue.is_empty()
+            {
+                state.writer = true;
+                return RwLockWriteGuard { lock: self };
+            }
+            self.writer_queue.wait()
+        };
+
+        // The readers that wait behind a writer are admitted if the writer leaves the queue
+        wait_for_handoff(wait_future, |is_handed_over| {
+            if is_handed_over {
+                self.write_unlock();
+            } else {
+                self.admit(&mut self.state.lock());
+            }
+        })
+        .await;
+        RwLockWriteGuard { lock: self }
+    }
+
+    /// Hands the lock over to the first waiting writer if the lock is free, or to all the waiting
+    /// readers if no writer is waiting and no writer holds the lock.
+    fn admit(&self, state: &mut RwLockState) {
+        if state.writer {
+            return;
+        }
+        if state.reader_count == 0 && self.writer_queue.notify_one() {
+            state.writer = true;
+        } else if self.writer_queue.is_empty() {
+            state.reader_count += self.reader_queue.notify_all();
+        }
+    }
+
+    /// Releases a shared lock, and hands the lock over to the first waiting writer if it was the
+    /// last reader.
+    fn read_unlock(&self) {
+        let mut state = self.state.lock();
+        state.reader_count -= 1;
+        self.admit(&mut state);
+    }
+
+    /// Releases an exclusive lock, and hands the lock over to all the waiting readers before the
+    /// next writer.
+    fn write_unlock(&self) {
+        let mut state = self.state.lock();
+        state.writer = false;
+        state.reader_count += self.reader_queue.notify_all();
+        self.admit(&mut state);
+    }
+}
+
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+
+/// The `RwLockReadGuard` struct is an RAII guard of a shared lock of a [RwLock]. When the guard
+/// goes out of scope, the shared lock is released.
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.cell.get() }
+    }
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.read_unlock();
+    }
+}
+
+/// The `RwLockWriteGuard` struct is an RAII guard of an exclusive lock of a [RwLock]. When the
+/// guard goes out of scope, the exclusive lock is released.
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.cell.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.cell.get() }
+    }
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.write_unlock();
+    }
+}
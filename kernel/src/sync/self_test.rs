@@ -0,0 +1,238 @@
+//! The `self_test` module tests the kernel tasks and the primitives of the `sync` module in a
+//! kernel task, which is spawned at every boot and runs alongside the user programs. A test that
+//! fails panics, and a test that misses a wakeup leaves the kernel task behind, which is reported
+//! when the kernel shuts down.
+
+use alloc::{sync::Arc, vec, vec::Vec};
+
+use log::info;
+
+use crate::{
+    executor::{self, kernel_task_list, yield_now},
+    sync::{wait_for_event, AsyncMutex, Condvar, Event, EventBus, RwLock, Semaphore, WaitQueue},
+};
+
+/// Spawns the kernel task that runs the tests.
+pub fn spawn_self_test() {
+    drop(executor::spawn_kernel_task("sync-self-test", run()));
+}
+
+async fn run() {
+    test_kernel_task().await;
+    test_wait_queue().await;
+    test_mutex_fairness().await;
+    test_semaphore_cancellation().await;
+    test_condvar().await;
+    test_rw_lock_fairness().await;
+    test_event_bus().await;
+    info!("sync self-test passed");
+}
+
+/// Yields the CPU until the tasks that have been spawned have run and started waiting.
+async fn settle() {
+    for _ in 0..4 {
+        yield_now().await;
+    }
+}
+
+/// Returns `true` if a kernel task with a specific name hasn't completed or been cancelled.
+fn is_kernel_task_alive(name: &str) -> bool {
+    kernel_task_list()
+        .iter()
+        .any(|(_, task_name)| task_name == name)
+}
+
+async fn test_kernel_task() {
+    // A task that completes hands its output over to the handle and leaves the task list
+    let handle = executor::spawn_kernel_task("join-test", async {
+        yield_now().await;
+        42
+    });
+    assert!(is_kernel_task_alive("join-test"));
+    assert_eq!(handle.await, Some(42));
+    assert!(!is_kernel_task_alive("join-test"));
+
+    // A task that is cancelled before it first runs never polls its future
+    let handle = executor::spawn_kernel_task("cancel-test", async { unreachable!() });
+    handle.cancel();
+    assert_eq!(handle.await, None);
+    assert!(!is_kernel_task_alive("cancel-test"));
+
+    // A task whose handle is dropped keeps running in the background
+    let wait_queue = Arc::new(WaitQueue::new());
+    let detached_queue = wait_queue.clone();
+    let wait_future = wait_queue.wait();
+    drop(executor::spawn_kernel_task("detach-test", async move {
+        detached_queue.notify_one();
+    }));
+    wait_future.await;
+    settle().await;
+    assert!(!is_kernel_task_alive("detach-test"));
+}
+
+async fn test_wait_queue() {
+    let wait_queue = WaitQueue::new();
+
+    // A notification that is sent before the future is first polled isn't lost
+    let wait_future = wait_queue.wait();
+    assert!(wait_queue.notify_one());
+    wait_future.await;
+
+    // A waiter that leaves the queue doesn't take the notification of the others
+    let first_future = wait_queue.wait();
+    let second_future = wait_queue.wait();
+    drop(first_future);
+    assert!(wait_queue.notify_one());
+    assert!(second_future.is_notified());
+    second_future.await;
+    assert!(!wait_queue.notify_one());
+
+    let future_list: Vec<_> = (0..3).map(|_| wait_queue.wait()).collect();
+    assert_eq!(wait_queue.notify_all(), 3);
+    for wait_future in future_list {
+        wait_future.await;
+    }
+}
+
+async fn test_mutex_fairness() {
+    let mutex = Arc::new(AsyncMutex::new(Vec::new()));
+    let guard = mutex.lock().await;
+
+    let handle_list: Vec<_> = (0..4)
+        .map(|index| {
+            let mutex = mutex.clone();
+            executor::spawn_kernel_task("mutex-waiter", async move {
+                let mut order = mutex.lock().await;
+                yield_now().await;
+                order.push(index);
+            })
+        })
+        .collect();
+    settle().await;
+
+    // The lock is handed over to the waiting tasks, so this task can't take it back at once
+    drop(guard);
+    let mut order = mutex.lock().await;
+    order.push(4);
+    assert_eq!(
+        *order,
+        vec![0, 1, 2, 3, 4],
+        "the lock is not acquired in FIFO order"
+    );
+    drop(order);
+
+    for handle in handle_list {
+        assert!(handle.await.is_some());
+    }
+}
+
+async fn test_semaphore_cancellation() {
+    let semaphore = Arc::new(Semaphore::new(1));
+    let permit = semaphore.acquire().await;
+
+    let spawn_waiter = || {
+        let semaphore = semaphore.clone();
+        executor::spawn_kernel_task("semaphore-waiter", async move {
+            drop(semaphore.acquire().await);
+        })
+    };
+    let first_handle = spawn_waiter();
+    let second_handle = spawn_waiter();
+    settle().await;
+
+    // The permit handed over to a cancelled task is passed on to the next one
+    first_handle.cancel();
+    drop(permit);
+    assert!(first_handle.await.is_none());
+    assert!(second_handle.await.is_some());
+    drop(semaphore.acquire().await);
+}
+
+async fn test_condvar() {
+    let state = Arc::new((AsyncMutex::new(false), Condvar::new()));
+    let guard = state.0.lock().await;
+
+    // The producer takes the lock as soon as `wait` releases it and notifies at once, which is
+    // never lost since the consumer has started waiting before releasing the lock
+    let producer_state = state.clone();
+    let producer = executor::spawn_kernel_task("condvar-producer", async move {
+        *producer_state.0.lock().await = true;
+        producer_state.1.notify_one();
+    });
+    settle().await;
+    let guard = state.1.wait_while(guard, |ready| !*ready).await;
+    assert!(*guard);
+    drop(guard);
+    assert!(producer.await.is_some());
+
+    *state.0.lock().await = false;
+    let handle_list: Vec<_> = (0..3)
+        .map(|_| {
+            let state = state.clone();
+            executor::spawn_kernel_task("condvar-consumer", async move {
+                let guard = state.0.lock().await;
+                drop(state.1.wait_while(guard, |ready| !*ready).await);
+            })
+        })
+        .collect();
+    settle().await;
+
+    *state.0.lock().await = true;
+    assert_eq!(state.1.notify_all(), 3);
+    for handle in handle_list {
+        assert!(handle.await.is_some());
+    }
+}
+
+async fn test_rw_lock_fairness() {
+    let lock = Arc::new(RwLock::new(0));
+    let first_reader = lock.read().await;
+    let second_reader = lock.read().await;
+
+    // The writer waits for the readers, and a reader that arrives later waits behind the writer
+    let writer_lock = lock.clone();
+    let writer = executor::spawn_kernel_task("rw-lock-writer", async move {
+        *writer_lock.write().await += 1;
+    });
+    settle().await;
+    let reader_lock = lock.clone();
+    let late_reader =
+        executor::spawn_kernel_task("rw-lock-reader", async move { *reader_lock.read().await });
+    settle().await;
+
+    assert_eq!(*first_reader + *second_reader, 0);
+    drop(first_reader);
+    drop(second_reader);
+    assert_eq!(late_reader.await, Some(1), "the writer is starved");
+    assert!(writer.await.is_some());
+}
+
+async fn test_event_bus() {
+    let event_bus = EventBus::new();
+
+    // A task that waits repeatedly holds at most one subscription, which is removed when it fires
+    for _ in 0..3 {
+        let waiter_bus = event_bus.clone();
+        let waiter = executor::spawn_kernel_task("event-bus-waiter", async move {
+            wait_for_event(waiter_bus, Event::CHILD_PROCESS_QUIT).await
+        });
+        settle().await;
+        assert_eq!(event_bus.lock().subscription_count(), 1);
+
+        event_bus.lock().push(Event::CHILD_PROCESS_QUIT);
+        assert_eq!(event_bus.lock().subscription_count(), 0);
+        assert!(waiter
+            .await
+            .is_some_and(|event| event == Event::CHILD_PROCESS_QUIT));
+    }
+
+    // The event is consumed by the waiter, and a waiter that is dropped unsubscribes
+    let waiter_bus = event_bus.clone();
+    let waiter = executor::spawn_kernel_task("event-bus-waiter", async move {
+        wait_for_event(waiter_bus, Event::CHILD_PROCESS_QUIT).await
+    });
+    settle().await;
+    waiter.cancel();
+    assert!(waiter.await.is_none());
+    assert_eq!(event_bus.lock().subscription_count(), 0);
+}
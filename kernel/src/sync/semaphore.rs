@@ -0,0 +1,63 @@
+//! The `semaphore` module provides a counting [Semaphore], whose waiting tasks sleep in a
+//! [WaitQueue] instead of spinning.
+
+use crate::sync::{wait_queue::wait_for_handoff, Mutex, WaitQueue};
+
+/// The `Semaphore` struct is a counting semaphore. A released permit is handed over to the task
+/// that has waited the longest, so a task that arrives later can't take it first.
+pub struct Semaphore {
+    permit_count: Mutex<usize>,
+    wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    /// Creates a `Semaphore` with the given number of permits.
+    pub const fn new(permit_count: usize) -> Self {
+        Self {
+            permit_count: Mutex::new(permit_count),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// Acquires a permit, and sleeps until a permit is released if there is none. Returns a
+    /// [SemaphorePermit] that releases the permit when it is dropped.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        let wait_future = {
+            let mut permit_count = self.permit_count.lock();
+            if *permit_count > 0 && self.wait_queue.is_empty() {
+                *permit_count -= 1;
+                return SemaphorePermit { semaphore: self };
+            }
+            // The task joins the queue before the lock is released, so no permit is missed
+            self.wait_queue.wait()
+        };
+
+        wait_for_handoff(wait_future, |is_handed_over| {
+            if is_handed_over {
+                self.release();
+            }
+        })
+        .await;
+        SemaphorePermit { semaphore: self }
+    }
+
+    /// Releases a permit, which is handed over to the first waiting task if there is one.
+    fn release(&self) {
+        let mut permit_count = self.permit_count.lock();
+        if !self.wait_queue.notify_one() {
+            *permit_count += 1;
+        }
+    }
+}
+
+/// The `SemaphorePermit` struct is an RAII guard of a permit of a [Semaphore]. When the guard goes
+/// out of scope, the permit is released.
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
@@ -0,0 +1,161 @@
+//! The `wait_queue` module provides a [WaitQueue], which suspends tasks until they are notified
+//! and wakes them in the order that they started waiting.
+
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+use core::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use crate::sync::Mutex;
+
+/// The `Waiter` struct is shared between a [WaitFuture] and the [WaitQueue] it waits on. The
+/// `notified` flag is set while the waiter list is locked, so that a waiter is either in the list
+/// or notified.
+struct Waiter {
+    notified: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Waiter {
+    fn wake(&self) {
+        let waker = self.waker.lock().take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// The `WaitQueue` struct holds the tasks that are waiting for a condition, which are woken in a
+/// FIFO order by [WaitQueue::notify_one] or all at once by [WaitQueue::notify_all].
+pub struct WaitQueue {
+    waiter_list: Mutex<VecDeque<Arc<Waiter>>>,
+}
+
+impl WaitQueue {
+    /// Creates an empty `WaitQueue`.
+    pub const fn new() -> Self {
+        Self {
+            waiter_list: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Returns a future that completes when it is notified. The future joins the queue as soon as
+    /// it is created rather than when it is first polled, so a notification that is sent after
+    /// this function returns is never lost, even if the caller releases a lock before awaiting.
+    pub fn wait(&self) -> WaitFuture<'_> {
+        let waiter = Arc::new(Waiter {
+            notified: AtomicBool::new(false),
+            waker: Mutex::new(None),
+        });
+        self.waiter_list.lock().push_back(waiter.clone());
+        WaitFuture {
+            wait_queue: self,
+            waiter,
+        }
+    }
+
+    /// Wakes the task that has waited the longest. Returns `false` if no task is waiting.
+    pub fn notify_one(&self) -> bool {
+        let mut waiter_list = self.waiter_list.lock();
+        let Some(waiter) = waiter_list.pop_front() else {
+            return false;
+        };
+        waiter.notified.store(true, Ordering::Release);
+        drop(waiter_list);
+
+        waiter.wake();
+        true
+    }
+
+    /// Wakes all the waiting tasks, and returns the number of tasks woken.
+    pub fn notify_all(&self) -> usize {
+        let waiter_list: Vec<_> = self
+            .waiter_list
+            .lock()
+            .drain(..)
+            .inspect(|waiter| waiter.notified.store(true, Ordering::Release))
+            .collect();
+        for waiter in &waiter_list {
+            waiter.wake();
+        }
+        waiter_list.len()
+    }
+
+    /// Returns `true` if no task is waiting.
+    pub fn is_empty(&self) -> bool {
+        self.waiter_list.lock().is_empty()
+    }
+}
+
+/// The `WaitFuture` struct is a future that completes when it is notified by its [WaitQueue]. The
+/// future leaves the queue if it is dropped before it is notified.
+pub struct WaitFuture<'a> {
+    wait_queue: &'a WaitQueue,
+    waiter: Arc<Waiter>,
+}
+
+impl WaitFuture<'_> {
+    /// Returns `true` if the future has been notified, even if it hasn't been polled since.
+    pub fn is_notified(&self) -> bool {
+        self.waiter.notified.load(Ordering::Acquire)
+    }
+
+    /// Leaves the queue if the future hasn't been notified, and returns `true` if it has.
+    fn leave(&self) -> bool {
+        let mut waiter_list = self.wait_queue.waiter_list.lock();
+        if self.is_notified() {
+            return true;
+        }
+        waiter_list.retain(|waiter| !Arc::ptr_eq(waiter, &self.waiter));
+        false
+    }
+}
+
+impl Future for WaitFuture<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        // The waker is stored before checking the flag so that a notification is never missed
+        *self.waiter.waker.lock() = Some(context.waker().clone());
+        if self.is_notified() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for WaitFuture<'_> {
+    fn drop(&mut self) {
+        self.leave();
+    }
+}
+
+/// The `Handoff` struct calls `abandon` when it is dropped before `wait_future` completes.
+struct Handoff<'a, F: FnOnce(bool)> {
+    wait_future: WaitFuture<'a>,
+    abandon: Option<F>,
+}
+
+impl<F: FnOnce(bool)> Drop for Handoff<'_, F> {
+    fn drop(&mut self) {
+        if let Some(abandon) = self.abandon.take() {
+            abandon(self.wait_future.leave());
+        }
+    }
+}
+
+/// Waits for `wait_future` to be notified, where the notification hands over a resource, such as
+/// a permit of a semaphore, from the task that sends it. If the task is cancelled before it takes
+/// the resource, `abandon` is called with whether the resource has been handed over, so that the
+/// resource can be passed on.
+pub async fn wait_for_handoff<F: FnOnce(bool)>(wait_future: WaitFuture<'_>, abandon: F) {
+    let mut handoff = Handoff {
+        wait_future,
+        abandon: Some(abandon),
+    };
+    poll_fn(|context| Pin::new(&mut handoff.wait_future).poll(context)).await;
+    handoff.abandon = None;
+}
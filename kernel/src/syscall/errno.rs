@@ -0,0 +1,53 @@
+//! The `errno` module defines the error numbers of system calls, which follow the definitions in
+//! Linux. A system call returns the negated error number when it fails.
+
+use crate::mem::UserAccessError;
+
+/// Indicates that the operation requires a privilege that the caller doesn't have.
+pub const EPERM: isize = 1;
+
+/// Indicates that the requested object doesn't exist.
+pub const ENOENT: isize = 2;
+
+/// Indicates that the requested process doesn't exist.
+pub const ESRCH: isize = 3;
+
+/// Indicates that the executable file or its interpreter is not a valid ELF file.
+pub const ENOEXEC: isize = 8;
+
+/// Indicates that the kernel fails to allocate memory.
+pub const ENOMEM: isize = 12;
+
+/// Indicates that the operation is forbidden by the W^X policy.
+pub const EACCES: isize = 13;
+
+/// Indicates that an address passed to the system call is not accessible.
+pub const EFAULT: isize = 14;
+
+/// Indicates that the resource is busy, such as the bandwidth of the deadline tasks.
+pub const EBUSY: isize = 16;
+
+/// Indicates that the object to be created already exists.
+pub const EEXIST: isize = 17;
+
+/// Indicates that an argument of the system call is invalid.
+pub const EINVAL: isize = 22;
+
+/// Indicates that a system-wide limit on the resource, such as [SHMALL](crate::constant::SHMALL),
+/// would be exceeded.
+pub const ENOSPC: isize = 28;
+
+/// Indicates that a path passed to the system call is too long.
+pub const ENAMETOOLONG: isize = 36;
+
+/// Indicates that the system call is not implemented.
+pub const ENOSYS: isize = 38;
+
+/// Returns the error number of a failed access to user memory.
+pub fn user_access_errno(error: UserAccessError) -> isize {
+    match error {
+        UserAccessError::Fault => EFAULT,
+        UserAccessError::OutOfMemory => ENOMEM,
+        UserAccessError::TooLong => ENAMETOOLONG,
+    }
+}
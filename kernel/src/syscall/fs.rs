@@ -1,26 +1,27 @@
 //! The `fs` module provides system calls to interact with the file system.
 
-use core::str;
-
 use log::error;
 
 use crate::{
     executor::{yield_now, ControlFlow},
-    mem::UserPtr,
-    print,
+    mem::{copy_from_user, UserPtr},
     sbi,
-    syscall::SystemCall,
+    syscall::{errno::user_access_errno, SystemCall},
+    task::Signal,
 };
 
 const STDIN: usize = 0;
 const STDOUT: usize = 1;
 
+/// The number of bytes that `sys_write` copies from user memory at a time.
+const WRITE_CHUNK_SIZE: usize = 256;
+
 impl SystemCall<'_> {
     /// Reads the content from a file descriptor and writes them to a buffer.
     pub async fn sys_read(
         &self,
         fd: usize,
-        mut buffer: UserPtr<u8>,
+        buffer: UserPtr<u8>,
         _length: usize,
     ) -> (isize, ControlFlow) {
         match fd {
@@ -30,12 +31,17 @@ impl SystemCall<'_> {
                     char = sbi::console_getchar();
                     if char == 0 {
                         yield_now().await;
+                        if self.thread.process().state().lock().is_killed() {
+                            return (0, ControlFlow::Exit(Signal::Kill.exit_code()));
+                        }
                     } else {
                         break;
                     }
                 }
-                *buffer = char as u8;
-                (1, ControlFlow::Continue)
+                match buffer.write(self.thread, char as u8) {
+                    Ok(()) => (1, ControlFlow::Continue),
+                    Err(error) => (-user_access_errno(error), ControlFlow::Continue),
+                }
             }
             _ => {
                 error!("the file descriptor {} is not supported in 'sys_write'", fd);
@@ -44,14 +50,31 @@ impl SystemCall<'_> {
         }
     }
 
-    /// Writes the contents of a buffer to a file descriptor.
+    /// Writes the contents of a buffer to a file descriptor. Returns the number of bytes written
+    /// before the first inaccessible byte of the buffer, or `EFAULT` if no byte is written.
     pub fn sys_write(&self, fd: usize, buffer: UserPtr<u8>, length: usize) -> (isize, ControlFlow) {
         match fd {
             STDOUT => {
-                for buffer in buffer.as_buffer(length) {
-                    print!("{}", str::from_utf8(buffer).unwrap());
+                let mut chunk = [0; WRITE_CHUNK_SIZE];
+                let mut offset = 0;
+                while offset < length {
+                    let chunk_length = WRITE_CHUNK_SIZE.min(length - offset);
+                    let chunk = &mut chunk[..chunk_length];
+                    if let Err(error) =
+                        copy_from_user(self.thread, chunk, buffer.address() + offset)
+                    {
+                        if offset == 0 {
+                            return (-user_access_errno(error), ControlFlow::Continue);
+                        }
+                        break;
+                    }
+
+                    for byte in chunk.iter() {
+                        sbi::console_putchar((*byte).into());
+                    }
+                    offset += chunk_length;
                 }
-                (length as isize, ControlFlow::Continue)
+                (offset as isize, ControlFlow::Continue)
             }
             _ => {
                 error!("the file descriptor {} is not supported in 'sys_write'", fd);
@@ -0,0 +1,136 @@
+//! The `ipc` module provides system calls for the System V shared memory objects.
+
+use crate::{
+    constant::{PAGE_SIZE, SHMALL, SHMMAX},
+    executor::ControlFlow,
+    mem::{
+        create_shared_memory,
+        find_shared_memory,
+        get_shared_memory,
+        remove_shared_memory,
+        shared_memory_page_count,
+        MapPermission,
+        OutOfMemory,
+        PageSize,
+        VirtualAddress,
+        IPC_PRIVATE,
+    },
+    syscall::{
+        errno::{EEXIST, EINVAL, ENOENT, ENOMEM, ENOSPC},
+        SystemCall,
+    },
+};
+
+const IPC_CREAT: usize = 0o1000;
+const IPC_EXCL: usize = 0o2000;
+const IPC_RMID: usize = 0;
+
+const SHM_RDONLY: usize = 0o10000;
+
+impl SystemCall<'_> {
+    /// Returns the ID of the shared memory object with a specific key. With `IPC_CREAT`, creates
+    /// an object of `size` bytes if no object has the key, or if the key is `IPC_PRIVATE`. An
+    /// object can't be larger than [SHMMAX] bytes, and the objects can't have more than [SHMALL]
+    /// pages in total.
+    pub fn sys_shmget(&self, key: usize, size: usize, flags: usize) -> (isize, ControlFlow) {
+        if let Some((id, shared_memory)) = find_shared_memory(key) {
+            if flags & IPC_CREAT != 0 && flags & IPC_EXCL != 0 {
+                return (-EEXIST, ControlFlow::Continue);
+            }
+
+            if size > shared_memory.size() {
+                return (-EINVAL, ControlFlow::Continue);
+            }
+            return (id as isize, ControlFlow::Continue);
+        }
+
+        if key != IPC_PRIVATE && flags & IPC_CREAT == 0 {
+            return (-ENOENT, ControlFlow::Continue);
+        }
+
+        if size == 0 || size > SHMMAX {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+        if shared_memory_page_count() + (size + PAGE_SIZE - 1) / PAGE_SIZE > SHMALL {
+            return (-ENOSPC, ControlFlow::Continue);
+        }
+
+        match create_shared_memory(key, size) {
+            Ok(id) => (id as isize, ControlFlow::Continue),
+            Err(OutOfMemory) => (-ENOMEM, ControlFlow::Continue),
+        }
+    }
+
+    /// Maps the shared memory object with a specific ID into the address space of the current
+    /// process, and returns the start address of the mapping. The address is chosen by the kernel,
+    /// so `address` must be `0`. With `SHM_RDONLY`, the mapping is read-only.
+    pub fn sys_shmat(&self, id: usize, address: usize, flags: usize) -> (isize, ControlFlow) {
+        if address != 0 {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        let Some(shared_memory) = get_shared_memory(id) else {
+            return (-EINVAL, ControlFlow::Continue);
+        };
+
+        let mut map_permission = MapPermission::R | MapPermission::U;
+        if flags & SHM_RDONLY == 0 {
+            map_permission |= MapPermission::W;
+        }
+
+        let process = self.thread.process();
+        let mut process_state = process.state().lock();
+        let user_layout = process_state.user_layout();
+        let page_set = process_state.page_set_mut();
+        let Some(start_address) = page_set.find_free_range(
+            user_layout.mmap_base(),
+            user_layout.user_stack_base(),
+            shared_memory.frame_list().len() * PAGE_SIZE,
+            PageSize::Page,
+        ) else {
+            return (-ENOMEM, ControlFlow::Continue);
+        };
+
+        match page_set.insert_shared(start_address, shared_memory.frame_list(), map_permission) {
+            Ok(()) => (usize::from(start_address) as isize, ControlFlow::Continue),
+            Err(OutOfMemory) => (-ENOMEM, ControlFlow::Continue),
+        }
+    }
+
+    /// Unmaps the shared memory object mapped at `address` from the address space of the current
+    /// process.
+    pub fn sys_shmdt(&self, address: usize) -> (isize, ControlFlow) {
+        let process = self.thread.process();
+        let mut process_state = process.state().lock();
+        let user_layout = process_state.user_layout();
+        if !(user_layout.mmap_base()..user_layout.user_stack_base())
+            .contains(&VirtualAddress::from(address))
+        {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        if process_state
+            .page_set_mut()
+            .remove_shared(VirtualAddress::from(address))
+        {
+            (0, ControlFlow::Continue)
+        } else {
+            (-EINVAL, ControlFlow::Continue)
+        }
+    }
+
+    /// Controls the shared memory object with a specific ID. Only `IPC_RMID` is supported, which
+    /// removes the object so that it can't be attached anymore, while the existing mappings remain
+    /// valid until they are unmapped.
+    pub fn sys_shmctl(&self, id: usize, command: usize) -> (isize, ControlFlow) {
+        if command != IPC_RMID {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        if remove_shared_memory(id) {
+            (0, ControlFlow::Continue)
+        } else {
+            (-EINVAL, ControlFlow::Continue)
+        }
+    }
+}
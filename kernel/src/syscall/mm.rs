@@ -0,0 +1,215 @@
+//! The `mm` module provides system calls to manage the memory of processes.
+
+use crate::{
+    constant::{PAGE_SIZE, USER_MMAP_BASE},
+    executor::ControlFlow,
+    mem::{
+        user_address_limit,
+        user_stack_base,
+        MapPermission,
+        OutOfMemory,
+        PageSize,
+        VirtualAddress,
+    },
+    syscall::{
+        errno::{EACCES, EINVAL, ENOMEM},
+        SystemCall,
+    },
+};
+
+const PROT_READ: usize = 1 << 0;
+const PROT_WRITE: usize = 1 << 1;
+const PROT_EXEC: usize = 1 << 2;
+
+const MAP_PRIVATE: usize = 1 << 1;
+const MAP_ANONYMOUS: usize = 1 << 5;
+const MAP_HUGETLB: usize = 1 << 18;
+
+/// Returns the [MapPermission] of the user pages with a specific protection, where the writable
+/// pages are also readable.
+fn map_permission(protection: usize) -> MapPermission {
+    let mut map_permission = MapPermission::U;
+    if protection & (PROT_READ | PROT_WRITE) != 0 {
+        map_permission |= MapPermission::R;
+    }
+
+    if protection & PROT_WRITE != 0 {
+        map_permission |= MapPermission::W;
+    }
+
+    if protection & PROT_EXEC != 0 {
+        map_permission |= MapPermission::X;
+    }
+    map_permission
+}
+
+impl SystemCall<'_> {
+    /// Sets the program break of the current process to `address`, which allocates or releases
+    /// the heap of the process. Returns the new program break when succeeded, or the current
+    /// program break if `address` is below the start of the program break.
+    pub fn sys_brk(&self, address: usize) -> (isize, ControlFlow) {
+        let process = self.thread.process();
+        let mut process_state = process.state().lock();
+        let program_break_start = process_state.program_break_start();
+        if address < usize::from(program_break_start) {
+            return (
+                usize::from(process_state.program_break()) as isize,
+                ControlFlow::Continue,
+            );
+        }
+
+        if address > usize::from(process_state.user_layout().mmap_base()) {
+            return (-ENOMEM, ControlFlow::Continue);
+        }
+
+        let program_break = VirtualAddress::from(address);
+        match process_state
+            .page_set_mut()
+            .resize_segment(program_break_start, program_break)
+        {
+            Ok(true) => {
+                process_state.set_program_break(program_break);
+                (address as isize, ControlFlow::Continue)
+            }
+            Ok(false) | Err(OutOfMemory) => (-ENOMEM, ControlFlow::Continue),
+        }
+    }
+
+    /// Maps `length` bytes of anonymous memory into the address space of the current process, and
+    /// returns the start address of the mapping. Only private anonymous mappings are supported, so
+    /// the address hint, the file descriptor, and the offset are ignored. With `MAP_HUGETLB`, the
+    /// mapping is backed by 2 MiB megapages, and its length is rounded up to the megapage size.
+    pub fn sys_mmap(&self, length: usize, protection: usize, flags: usize) -> (isize, ControlFlow) {
+        if length == 0
+            || flags & MAP_PRIVATE == 0
+            || flags & MAP_ANONYMOUS == 0
+            || protection & (PROT_READ | PROT_WRITE | PROT_EXEC) == 0
+        {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        if protection & PROT_WRITE != 0 && protection & PROT_EXEC != 0 {
+            return (-EACCES, ControlFlow::Continue);
+        }
+
+        if length > user_stack_base() - USER_MMAP_BASE {
+            return (-ENOMEM, ControlFlow::Continue);
+        }
+
+        let map_permission = map_permission(protection);
+
+        let (page_size, length) = if flags & MAP_HUGETLB != 0 {
+            let huge_page_size = PageSize::MegaPage.page_count() * PAGE_SIZE;
+            (
+                PageSize::MegaPage,
+                (length + huge_page_size - 1) / huge_page_size * huge_page_size,
+            )
+        } else {
+            (PageSize::Page, length)
+        };
+
+        let process = self.thread.process();
+        let mut process_state = process.state().lock();
+        let user_layout = process_state.user_layout();
+        let page_set = process_state.page_set_mut();
+        let Some(start_address) = page_set.find_free_range(
+            user_layout.mmap_base(),
+            user_layout.user_stack_base(),
+            length,
+            page_size,
+        ) else {
+            return (-ENOMEM, ControlFlow::Continue);
+        };
+
+        match page_set.insert_frame_with_page_size(
+            start_address,
+            start_address + length,
+            map_permission,
+            page_size,
+        ) {
+            Ok(()) => (usize::from(start_address) as isize, ControlFlow::Continue),
+            Err(OutOfMemory) => (-ENOMEM, ControlFlow::Continue),
+        }
+    }
+
+    /// Changes the protection of the pages from `address` to `address + length`, which must be
+    /// within a single anonymous memory mapping or loadable segment, or within the heap or a user
+    /// stack. The W^X policy forbids pages that are both writable and executable, and `PROT_NONE`
+    /// is not supported.
+    pub fn sys_mprotect(
+        &self,
+        address: usize,
+        length: usize,
+        protection: usize,
+    ) -> (isize, ControlFlow) {
+        if length == 0
+            || !VirtualAddress::from(address).is_aligned()
+            || protection & (PROT_READ | PROT_WRITE | PROT_EXEC) == 0
+            || protection & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0
+        {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        if protection & PROT_WRITE != 0 && protection & PROT_EXEC != 0 {
+            return (-EACCES, ControlFlow::Continue);
+        }
+
+        let Some(end) = address
+            .checked_add(length)
+            .filter(|end| *end <= user_address_limit())
+        else {
+            return (-ENOMEM, ControlFlow::Continue);
+        };
+
+        let process = self.thread.process();
+        let mut process_state = process.state().lock();
+        if process_state.page_set_mut().protect(
+            VirtualAddress::from(address),
+            VirtualAddress::from(end),
+            map_permission(protection),
+        ) {
+            (0, ControlFlow::Continue)
+        } else {
+            (-ENOMEM, ControlFlow::Continue)
+        }
+    }
+
+    /// Unmaps the anonymous memory mapping that starts at `address` and spans `length` bytes, which
+    /// is rounded up to the page size of the mapping. Unmapping a part of a mapping is not
+    /// supported.
+    pub fn sys_munmap(&self, address: usize, length: usize) -> (isize, ControlFlow) {
+        let process = self.thread.process();
+        let mut process_state = process.state().lock();
+        let user_layout = process_state.user_layout();
+        let user_stack_base = usize::from(user_layout.user_stack_base());
+        if !(usize::from(user_layout.mmap_base())..user_stack_base).contains(&address)
+            || length == 0
+            || length > user_stack_base - address
+            || !VirtualAddress::from(address).is_aligned()
+        {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        let start_address = VirtualAddress::from(address);
+        let page_set = process_state.page_set_mut();
+        let Some(segment) = page_set.find_segment_mut(start_address) else {
+            return (-EINVAL, ControlFlow::Continue);
+        };
+
+        // A shared memory object is only detached by `shmdt`
+        if segment.is_shared() {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        let segment_size = (segment.end() - segment.start()) * PAGE_SIZE;
+        let page_size = segment.page_size().page_count() * PAGE_SIZE;
+        if segment.start() != start_address.floor()
+            || segment_size != (length + page_size - 1) / page_size * page_size
+        {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        page_set.remove_segment(start_address);
+        (0, ControlFlow::Continue)
+    }
+}
@@ -1,19 +1,45 @@
 //! The `syscall` module provides system calls for interacting with the operating system.
 
-use crate::{executor::ControlFlow, mem::UserPtr, task::Thread};
+use log::warn;
 
+use crate::{executor::ControlFlow, mem::UserPtr, syscall::errno::ENOSYS, task::Thread};
+
+mod errno;
 mod fs;
+mod ipc;
+mod mm;
 mod process;
 mod timer;
 
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_SCHED_SETSCHEDULER: usize = 119;
+const SYSCALL_SCHED_GETSCHEDULER: usize = 120;
+const SYSCALL_SCHED_SETAFFINITY: usize = 122;
+const SYSCALL_SCHED_GETAFFINITY: usize = 123;
 const SYSCALL_SCHED_YIELD: usize = 128;
+const SYSCALL_SETPRIORITY: usize = 140;
+const SYSCALL_GETPRIORITY: usize = 141;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_GETRLIMIT: usize = 163;
+const SYSCALL_SETRLIMIT: usize = 164;
+const SYSCALL_GETRUSAGE: usize = 165;
+const SYSCALL_GETCPU: usize = 168;
 const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_SHMGET: usize = 194;
+const SYSCALL_SHMCTL: usize = 195;
+const SYSCALL_SHMAT: usize = 196;
+const SYSCALL_SHMDT: usize = 197;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_SCHED_SETATTR: usize = 274;
 
 /// The `SystemCall` struct provides an interface for invoking system calls on a given thread.
 pub struct SystemCall<'a> {
@@ -37,34 +63,56 @@ impl<'a> SystemCall<'a> {
         let argument_0 = trap_context.user_register(10);
         let argument_1 = trap_context.user_register(11);
         let argument_2 = trap_context.user_register(12);
+        let argument_3 = trap_context.user_register(13);
 
         let (exit_code, control_flow) = match system_call_id {
             SYSCALL_READ => {
-                self.sys_read(
-                    argument_0,
-                    UserPtr::new(self.thread.satp(), argument_1),
-                    argument_2,
-                )
-                .await
+                self.sys_read(argument_0, UserPtr::new(argument_1), argument_2)
+                    .await
             }
-            SYSCALL_WRITE => self.sys_write(
-                argument_0,
-                UserPtr::new(self.thread.satp(), argument_1),
-                argument_2,
-            ),
+            SYSCALL_WRITE => self.sys_write(argument_0, UserPtr::new(argument_1), argument_2),
             SYSCALL_EXIT => self.sys_exit(argument_0),
+            SYSCALL_CLOCK_GETTIME => self.sys_clock_gettime(argument_0, UserPtr::new(argument_1)),
+            SYSCALL_SCHED_SETSCHEDULER => {
+                self.sys_sched_setscheduler(argument_0, argument_1, UserPtr::new(argument_2))
+            }
+            SYSCALL_SCHED_GETSCHEDULER => self.sys_sched_getscheduler(argument_0),
+            SYSCALL_SCHED_SETAFFINITY => {
+                self.sys_sched_setaffinity(argument_0, argument_1, UserPtr::new(argument_2))
+            }
+            SYSCALL_SCHED_GETAFFINITY => {
+                self.sys_sched_getaffinity(argument_0, argument_1, UserPtr::new(argument_2))
+            }
             SYSCALL_SCHED_YIELD => self.sys_sched_yield(),
+            SYSCALL_SETPRIORITY => self.sys_setpriority(argument_0, argument_1, argument_2),
+            SYSCALL_GETPRIORITY => self.sys_getpriority(argument_0, argument_1),
+            SYSCALL_TIMES => self.sys_times(UserPtr::new(argument_0)),
+            SYSCALL_GETRLIMIT => self.sys_getrlimit(argument_0, UserPtr::new(argument_1)),
+            SYSCALL_SETRLIMIT => self.sys_setrlimit(argument_0, UserPtr::new(argument_1)),
+            SYSCALL_GETRUSAGE => self.sys_getrusage(argument_0 as isize, UserPtr::new(argument_1)),
+            SYSCALL_GETCPU => self.sys_getcpu(UserPtr::new(argument_0), UserPtr::new(argument_1)),
             SYSCALL_GET_TIME => self.sys_get_time(),
+            SYSCALL_SHMGET => self.sys_shmget(argument_0, argument_1, argument_2),
+            SYSCALL_SHMCTL => self.sys_shmctl(argument_0, argument_1),
+            SYSCALL_SHMAT => self.sys_shmat(argument_0, argument_1, argument_2),
+            SYSCALL_SHMDT => self.sys_shmdt(argument_0),
+            SYSCALL_BRK => self.sys_brk(argument_0),
+            SYSCALL_MUNMAP => self.sys_munmap(argument_0, argument_1),
             SYSCALL_FORK => self.sys_fork(),
-            SYSCALL_EXEC => self.sys_exec(UserPtr::new(self.thread.satp(), argument_0)),
+            SYSCALL_EXEC => self.sys_exec(UserPtr::new(argument_0)),
+            SYSCALL_MMAP => self.sys_mmap(argument_1, argument_2, argument_3),
+            SYSCALL_MPROTECT => self.sys_mprotect(argument_0, argument_1, argument_2),
             SYSCALL_WAITPID => {
-                self.sys_waitpid(
-                    argument_0 as isize,
-                    UserPtr::new(self.thread.satp(), argument_1),
-                )
-                .await
+                self.sys_waitpid(argument_0 as isize, UserPtr::new(argument_1))
+                    .await
+            }
+            SYSCALL_SCHED_SETATTR => {
+                self.sys_sched_setattr(argument_0, UserPtr::new(argument_1), argument_2)
+            }
+            _ => {
+                warn!("unsupported syscall {}", system_call_id);
+                (-ENOSYS, ControlFlow::Continue)
             }
-            _ => panic!("unsupported syscall {}", system_call_id),
         };
 
         if control_flow == ControlFlow::Continue || control_flow == ControlFlow::Yield {
@@ -1,15 +1,51 @@
 //! The `process` module provides system calls to interact with processes.
 
-use alloc::vec::Vec;
+use alloc::{sync::Arc, vec::Vec};
+use core::mem::size_of;
 
 use crate::{
-    executor::ControlFlow,
-    mem::UserPtr,
+    executor::{
+        self,
+        ControlFlow,
+        SchedulingAttribute,
+        SchedulingPolicy,
+        TaskInfo,
+        NICE_MAX,
+        NICE_MIN,
+    },
+    hart,
+    mem::{strncpy_from_user, LoadError, OutOfMemory, UserPtr},
     sync::{wait_for_event, Event},
-    syscall::SystemCall,
-    task::Status,
+    syscall::{
+        errno::{user_access_errno, EACCES, EBUSY, EINVAL, ENOENT, ENOEXEC, ENOMEM, EPERM, ESRCH},
+        SystemCall,
+    },
+    task::{self, Process, ResourceLimit, Signal, Status},
 };
 
+const RLIMIT_STACK: usize = 3;
+
+/// Selects a process by its PID in `setpriority` and `getpriority`.
+const PRIO_PROCESS: usize = 0;
+
+/// The maximum length of a path, in bytes.
+const PATH_MAX: usize = 4096;
+
+/// The `UserSchedulingAttribute` struct represents the `sched_attr` argument of `sched_setattr`,
+/// whose layout follows the definition in Linux. The durations are in nanoseconds.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct UserSchedulingAttribute {
+    size: u32,
+    policy: u32,
+    flags: u64,
+    nice: i32,
+    priority: u32,
+    runtime: u64,
+    deadline: u64,
+    period: u64,
+}
+
 impl SystemCall<'_> {
     /// Terminates the current thread with the given exit code.
     pub fn sys_exit(&self, exit_code: usize) -> (isize, ControlFlow) {
@@ -21,28 +57,306 @@ impl SystemCall<'_> {
         (0, ControlFlow::Yield)
     }
 
+    /// Returns the process with a specific PID, which is the current process if `pid` is `0`.
+    fn target_process(&self, pid: usize) -> Result<Arc<Process>, isize> {
+        if pid == 0 {
+            Ok(self.thread.process())
+        } else {
+            task::get_process(pid).ok_or(ESRCH)
+        }
+    }
+
+    /// Returns the process that `setpriority` and `getpriority` select. Only [PRIO_PROCESS] is
+    /// supported.
+    fn priority_target(&self, which: usize, who: usize) -> Result<Arc<Process>, isize> {
+        if which != PRIO_PROCESS {
+            return Err(EINVAL);
+        }
+        self.target_process(who)
+    }
+
+    /// Sets the scheduling class of every thread of a process. Returns [EBUSY] if a thread would
+    /// become a deadline task beyond the bandwidth of the deadline tasks.
+    fn set_scheduling_attribute(
+        &self,
+        pid: usize,
+        attribute: SchedulingAttribute,
+    ) -> (isize, ControlFlow) {
+        let process = match self.target_process(pid) {
+            Ok(process) => process,
+            Err(errno) => return (-errno, ControlFlow::Continue),
+        };
+
+        let mut process_state = process.state().lock();
+        let task_info_list: Vec<&TaskInfo> = process_state
+            .thread_list_mut()
+            .iter()
+            .map(|thread| thread.task_info().as_ref())
+            .collect();
+        if !executor::set_scheduling_attribute(&task_info_list, attribute) {
+            return (-EBUSY, ControlFlow::Continue);
+        }
+        (0, ControlFlow::Continue)
+    }
+
+    /// Sets the scheduling class of a process to the normal class or a real-time class, where
+    /// `parameter` points to the real-time priority. The deadline class can only be set with
+    /// `sched_setattr`.
+    pub fn sys_sched_setscheduler(
+        &self,
+        pid: usize,
+        policy: usize,
+        parameter: UserPtr<i32>,
+    ) -> (isize, ControlFlow) {
+        let priority = match parameter.read(self.thread) {
+            Ok(priority) => priority,
+            Err(error) => return (-user_access_errno(error), ControlFlow::Continue),
+        };
+
+        let attribute = match SchedulingPolicy::try_from(policy) {
+            Ok(SchedulingPolicy::Deadline) | Err(()) => None,
+            Ok(policy) => SchedulingAttribute::new(policy, priority as usize, 0, 0, 0),
+        };
+        let Some(attribute) = attribute else {
+            return (-EINVAL, ControlFlow::Continue);
+        };
+        self.set_scheduling_attribute(pid, attribute)
+    }
+
+    /// Returns the scheduling policy of a process.
+    pub fn sys_sched_getscheduler(&self, pid: usize) -> (isize, ControlFlow) {
+        match self.target_process(pid) {
+            Ok(process) => {
+                let attribute = process.state().lock().main_thread().task_info().attribute();
+                (attribute.policy() as isize, ControlFlow::Continue)
+            }
+            Err(errno) => (-errno, ControlFlow::Continue),
+        }
+    }
+
+    /// Sets the scheduling class of a process and its parameters from `attribute`, which also sets
+    /// the nice value of a normal process. No flags are supported.
+    pub fn sys_sched_setattr(
+        &self,
+        pid: usize,
+        attribute: UserPtr<UserSchedulingAttribute>,
+        flags: usize,
+    ) -> (isize, ControlFlow) {
+        let attribute = match attribute.read(self.thread) {
+            Ok(attribute) => attribute,
+            Err(error) => return (-user_access_errno(error), ControlFlow::Continue),
+        };
+        if flags != 0 || attribute.flags != 0 {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+
+        let scheduling_attribute = SchedulingPolicy::try_from(attribute.policy as usize)
+            .ok()
+            .and_then(|policy| {
+                SchedulingAttribute::new(
+                    policy,
+                    attribute.priority as usize,
+                    attribute.runtime as usize,
+                    attribute.deadline as usize,
+                    attribute.period as usize,
+                )
+            });
+        let Some(scheduling_attribute) = scheduling_attribute else {
+            return (-EINVAL, ControlFlow::Continue);
+        };
+
+        // The nice value is checked first, so that a failed call leaves the process unchanged
+        if scheduling_attribute.policy() == SchedulingPolicy::Normal {
+            let result = self.sys_setpriority(PRIO_PROCESS, pid, attribute.nice as usize);
+            if result.0 != 0 {
+                return result;
+            }
+        }
+        self.set_scheduling_attribute(pid, scheduling_attribute)
+    }
+
+    /// Sets the affinity mask of every thread of a process from `mask`, whose size is `size` bytes.
+    /// The harts beyond the first `usize` of the mask are ignored, since they can't be online.
+    /// Returns [EINVAL] if the mask contains no online hart. The kernel only runs on the boot hart,
+    /// so a valid mask always contains it and doesn't change where the threads run.
+    pub fn sys_sched_setaffinity(
+        &self,
+        pid: usize,
+        size: usize,
+        mask: UserPtr<usize>,
+    ) -> (isize, ControlFlow) {
+        if size < size_of::<usize>() {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+        let mask = match mask.read(self.thread) {
+            Ok(mask) => mask,
+            Err(error) => return (-user_access_errno(error), ControlFlow::Continue),
+        };
+        let process = match self.target_process(pid) {
+            Ok(process) => process,
+            Err(errno) => return (-errno, ControlFlow::Continue),
+        };
+
+        for thread in process.state().lock().thread_list_mut() {
+            if !thread.task_info().set_affinity(mask) {
+                return (-EINVAL, ControlFlow::Continue);
+            }
+        }
+        (0, ControlFlow::Continue)
+    }
+
+    /// Writes the affinity mask of a process to `mask`, whose size is `size` bytes, and returns the
+    /// number of bytes written as in Linux.
+    pub fn sys_sched_getaffinity(
+        &self,
+        pid: usize,
+        size: usize,
+        mask: UserPtr<usize>,
+    ) -> (isize, ControlFlow) {
+        if size < size_of::<usize>() {
+            return (-EINVAL, ControlFlow::Continue);
+        }
+        let process = match self.target_process(pid) {
+            Ok(process) => process,
+            Err(errno) => return (-errno, ControlFlow::Continue),
+        };
+
+        let affinity = process.state().lock().main_thread().task_info().affinity();
+        match mask.write(self.thread, affinity) {
+            Ok(()) => (size_of::<usize>() as isize, ControlFlow::Continue),
+            Err(error) => (-user_access_errno(error), ControlFlow::Continue),
+        }
+    }
+
+    /// Writes the ID of the hart that the current thread is running on to `cpu`, and the NUMA node
+    /// to `node`, which is always `0`. Either pointer can be null.
+    pub fn sys_getcpu(&self, cpu: UserPtr<u32>, node: UserPtr<u32>) -> (isize, ControlFlow) {
+        for (pointer, value) in [(cpu, hart::hart_id() as u32), (node, 0)] {
+            if pointer.is_null() {
+                continue;
+            }
+            if let Err(error) = pointer.write(self.thread, value) {
+                return (-user_access_errno(error), ControlFlow::Continue);
+            }
+        }
+        (0, ControlFlow::Continue)
+    }
+
+    /// Sets the nice value of every thread of a process, which is clamped between `-20` and `19`.
+    /// Returns [EPERM] if the nice value would be lowered, since no process is privileged to raise
+    /// its priority.
+    pub fn sys_setpriority(&self, which: usize, who: usize, nice: usize) -> (isize, ControlFlow) {
+        let process = match self.priority_target(which, who) {
+            Ok(process) => process,
+            Err(errno) => return (-errno, ControlFlow::Continue),
+        };
+
+        let nice = (nice as isize).clamp(NICE_MIN, NICE_MAX);
+        let mut process_state = process.state().lock();
+        if nice < process_state.main_thread().task_info().nice() {
+            return (-EPERM, ControlFlow::Continue);
+        }
+        for thread in process_state.thread_list_mut() {
+            thread.task_info().set_nice(nice);
+        }
+        (0, ControlFlow::Continue)
+    }
+
+    /// Returns the nice value of a process as `20 - nice`, which ranges from `1` to `40` so that it
+    /// can't be mistaken for an error number, as in Linux.
+    pub fn sys_getpriority(&self, which: usize, who: usize) -> (isize, ControlFlow) {
+        let process = match self.priority_target(which, who) {
+            Ok(process) => process,
+            Err(errno) => return (-errno, ControlFlow::Continue),
+        };
+
+        let nice = process.state().lock().main_thread().task_info().nice();
+        (NICE_MAX + 1 - nice, ControlFlow::Continue)
+    }
+
+    /// Reads the soft limit and the hard limit of a resource to `resource_limit`.
+    pub fn sys_getrlimit(
+        &self,
+        resource: usize,
+        resource_limit: UserPtr<ResourceLimit>,
+    ) -> (isize, ControlFlow) {
+        match resource {
+            RLIMIT_STACK => {
+                let stack_limit = self.thread.process().state().lock().stack_limit();
+                match resource_limit.write(self.thread, stack_limit) {
+                    Ok(()) => (0, ControlFlow::Continue),
+                    Err(error) => (-user_access_errno(error), ControlFlow::Continue),
+                }
+            }
+            _ => (-EINVAL, ControlFlow::Continue),
+        }
+    }
+
+    /// Sets the soft limit and the hard limit of a resource from `resource_limit`.
+    /// The soft limit can't exceed the hard limit, and the hard limit can only be lowered, since no
+    /// process is privileged to raise it.
+    pub fn sys_setrlimit(
+        &self,
+        resource: usize,
+        resource_limit: UserPtr<ResourceLimit>,
+    ) -> (isize, ControlFlow) {
+        match resource {
+            RLIMIT_STACK => {
+                let resource_limit = match resource_limit.read(self.thread) {
+                    Ok(resource_limit) => resource_limit,
+                    Err(error) => return (-user_access_errno(error), ControlFlow::Continue),
+                };
+
+                let process = self.thread.process();
+                let mut process_state = process.state().lock();
+                if resource_limit.current() > resource_limit.maximum() {
+                    return (-EINVAL, ControlFlow::Continue);
+                }
+                if resource_limit.maximum() > process_state.stack_limit().maximum() {
+                    return (-EPERM, ControlFlow::Continue);
+                }
+
+                process_state.set_stack_limit(resource_limit);
+                (0, ControlFlow::Continue)
+            }
+            _ => (-EINVAL, ControlFlow::Continue),
+        }
+    }
+
     /// Forks the current process and create a new child process.
     pub fn sys_fork(&self) -> (isize, ControlFlow) {
-        let process = self.thread.process().fork();
-        (process.pid() as isize, ControlFlow::Continue)
+        match self.thread.process().fork() {
+            Ok(process) => (process.pid() as isize, ControlFlow::Continue),
+            Err(OutOfMemory) => (-ENOMEM, ControlFlow::Continue),
+        }
     }
 
     /// Waits for a child process with the given process to terminate, and return the PID and exit.
     pub async fn sys_waitpid(
         &self,
         pid: isize,
-        mut wait_status: UserPtr<usize>,
+        wait_status: UserPtr<usize>,
     ) -> (isize, ControlFlow) {
         loop {
             let process = self.thread.process();
             let mut process_state = process.state().lock();
+            if process_state.is_killed() {
+                return (0, ControlFlow::Exit(Signal::Kill.exit_code()));
+            }
+
             let child_list = process_state.child_list_mut();
 
-            if let Some((pid, exit_code)) = match pid {
+            // The CPU time of a reaped child includes the CPU time of its reaped descendants
+            if let Some((pid, exit_code, cpu_time)) = match pid {
                 -1 | 0 => child_list.iter().find_map(|child_process| {
                     let child_process_state = child_process.state().lock();
                     if child_process_state.status() == Status::Zombie {
-                        Some((child_process.pid(), child_process_state.exit_code()))
+                        Some((
+                            child_process.pid(),
+                            child_process_state.exit_code(),
+                            child_process_state.cpu_time()
+                                + child_process_state.children_cpu_time(),
+                        ))
                     } else {
                         None
                     }
@@ -52,20 +366,40 @@ impl SystemCall<'_> {
                     if child_process.pid() == pid as usize
                         && child_process_state.status() == Status::Zombie
                     {
-                        Some((child_process.pid(), child_process_state.exit_code()))
+                        Some((
+                            child_process.pid(),
+                            child_process_state.exit_code(),
+                            child_process_state.cpu_time()
+                                + child_process_state.children_cpu_time(),
+                        ))
                     } else {
                         None
                     }
                 }),
             } {
+                drop(process_state);
+
+                // The exit status is written before the child is reaped, so that the child can
+                // still be waited for if the write fails
+                if !wait_status.is_null() {
+                    if let Err(error) = wait_status.write(self.thread, exit_code) {
+                        return (-user_access_errno(error), ControlFlow::Continue);
+                    }
+                }
+
+                // Another thread of the process might have reaped the child in the meantime
+                let mut process_state = process.state().lock();
+                let child_list = process_state.child_list_mut();
+                let child_count = child_list.len();
                 child_list.retain(|child_process| child_process.pid() != pid);
-                *wait_status = exit_code;
-                return (pid as isize, ControlFlow::Continue);
+                if child_list.len() < child_count {
+                    process_state.add_children_cpu_time(cpu_time);
+                    return (pid as isize, ControlFlow::Continue);
+                }
             } else {
                 let event_bus = process.event_bus();
                 drop(process_state);
-                wait_for_event(event_bus.clone(), Event::CHILD_PROCESS_QUIT).await;
-                event_bus.lock().clear(Event::CHILD_PROCESS_QUIT);
+                wait_for_event(event_bus, Event::CHILD_PROCESS_QUIT).await;
             }
         }
     }
@@ -73,7 +407,19 @@ impl SystemCall<'_> {
     /// Replaces the current process with a new process loaded from the executable file with a given
     /// name.
     pub fn sys_exec(&self, path: UserPtr<u8>) -> (isize, ControlFlow) {
-        self.thread.process().exec(&path.as_string(), Vec::new());
-        (0, ControlFlow::Continue)
+        let path = match strncpy_from_user(self.thread, path.address(), PATH_MAX) {
+            Ok(path) => path,
+            Err(error) => return (-user_access_errno(error), ControlFlow::Continue),
+        };
+
+        match self.thread.process().exec(&path, Vec::new()) {
+            Ok(()) => (0, ControlFlow::Continue),
+            Err(LoadError::OutOfMemory) => (-ENOMEM, ControlFlow::Continue),
+            Err(LoadError::WritableExecutable) => (-EACCES, ControlFlow::Continue),
+            Err(LoadError::NotFound | LoadError::InterpreterNotFound) => {
+                (-ENOENT, ControlFlow::Continue)
+            }
+            Err(LoadError::InvalidElf) => (-ENOEXEC, ControlFlow::Continue),
+        }
     }
 }
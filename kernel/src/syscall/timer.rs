@@ -1,10 +1,179 @@
 //! The `timer` module provides time-related system calls.
 
-use crate::{executor::ControlFlow, syscall::SystemCall, timer};
+use riscv::register::time;
+
+use crate::{
+    constant::CLOCK_FREQ,
+    executor::ControlFlow,
+    mem::UserPtr,
+    syscall::{
+        errno::{user_access_errno, EINVAL},
+        SystemCall,
+    },
+    timer,
+};
+
+const CLOCK_REALTIME: usize = 0;
+const CLOCK_MONOTONIC: usize = 1;
+const CLOCK_PROCESS_CPUTIME_ID: usize = 2;
+const CLOCK_THREAD_CPUTIME_ID: usize = 3;
+
+const RUSAGE_SELF: isize = 0;
+const RUSAGE_CHILDREN: isize = -1;
+const RUSAGE_THREAD: isize = 1;
+
+/// The number of clock ticks per second in `times`, which is `CLK_TCK` in Linux.
+const CLOCK_TICK_PER_SEC: usize = 100;
+
+/// The `TimeSpec` struct represents a duration in seconds and nanoseconds, which follows the layout
+/// of the `timespec` struct in Linux.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TimeSpec {
+    second: usize,
+    nanosecond: usize,
+}
+
+impl TimeSpec {
+    /// Converts a duration in clock cycles to a [TimeSpec].
+    fn from_cycle(cycle: usize) -> Self {
+        Self {
+            second: cycle / CLOCK_FREQ,
+            nanosecond: cycle % CLOCK_FREQ * 1_000_000_000 / CLOCK_FREQ,
+        }
+    }
+}
+
+/// The `TimeVal` struct represents a duration in seconds and microseconds, which follows the layout
+/// of the `timeval` struct in Linux.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TimeVal {
+    second: usize,
+    microsecond: usize,
+}
+
+impl TimeVal {
+    /// Converts a duration in clock cycles to a [TimeVal].
+    fn from_cycle(cycle: usize) -> Self {
+        Self {
+            second: cycle / CLOCK_FREQ,
+            microsecond: cycle % CLOCK_FREQ * 1_000_000 / CLOCK_FREQ,
+        }
+    }
+}
+
+/// The `ProcessTimes` struct represents the CPU time of a process and its reaped child processes in
+/// clock ticks, which follows the layout of the `tms` struct in Linux.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ProcessTimes {
+    user_time: usize,
+    system_time: usize,
+    children_user_time: usize,
+    children_system_time: usize,
+}
+
+/// The `ResourceUsage` struct represents the resource usage of a process, which follows the layout
+/// of the `rusage` struct in Linux. Only the CPU time is measured, and the other fields are `0`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ResourceUsage {
+    user_time: TimeVal,
+    system_time: TimeVal,
+    other: [usize; 14],
+}
+
+/// Converts a duration in clock cycles to clock ticks.
+fn cycle_to_clock_tick(cycle: usize) -> usize {
+    cycle / (CLOCK_FREQ / CLOCK_TICK_PER_SEC)
+}
 
 impl SystemCall<'_> {
     /// Returns the current system time in milliseconds.
     pub fn sys_get_time(&self) -> (isize, ControlFlow) {
         (timer::get_time() as isize, ControlFlow::Continue)
     }
+
+    /// Writes the time of a clock to `time_spec`. The real-time clock and the monotonic clock both
+    /// count from the boot, while the CPU time clocks measure the CPU time of the current process
+    /// or the current thread.
+    pub fn sys_clock_gettime(
+        &self,
+        clock_id: usize,
+        time_spec: UserPtr<TimeSpec>,
+    ) -> (isize, ControlFlow) {
+        let cycle = match clock_id {
+            CLOCK_REALTIME | CLOCK_MONOTONIC => time::read(),
+            CLOCK_PROCESS_CPUTIME_ID => {
+                let process = self.thread.process();
+                let cpu_time = process.state().lock().cpu_time();
+                cpu_time.total()
+            }
+            CLOCK_THREAD_CPUTIME_ID => self.thread.cpu_time().total(),
+            _ => return (-EINVAL, ControlFlow::Continue),
+        };
+
+        match time_spec.write(self.thread, TimeSpec::from_cycle(cycle)) {
+            Ok(()) => (0, ControlFlow::Continue),
+            Err(error) => (-user_access_errno(error), ControlFlow::Continue),
+        }
+    }
+
+    /// Writes the CPU time of the current process and its reaped child processes to
+    /// `process_times` if it is not null, and returns the time since the boot in clock ticks.
+    pub fn sys_times(&self, process_times: UserPtr<ProcessTimes>) -> (isize, ControlFlow) {
+        if !process_times.is_null() {
+            let process = self.thread.process();
+            let process_state = process.state().lock();
+            let cpu_time = process_state.cpu_time();
+            let children_cpu_time = process_state.children_cpu_time();
+            drop(process_state);
+
+            let result = process_times.write(
+                self.thread,
+                ProcessTimes {
+                    user_time: cycle_to_clock_tick(cpu_time.user_time()),
+                    system_time: cycle_to_clock_tick(cpu_time.system_time()),
+                    children_user_time: cycle_to_clock_tick(children_cpu_time.user_time()),
+                    children_system_time: cycle_to_clock_tick(children_cpu_time.system_time()),
+                },
+            );
+            if let Err(error) = result {
+                return (-user_access_errno(error), ControlFlow::Continue);
+            }
+        }
+        (
+            cycle_to_clock_tick(time::read()) as isize,
+            ControlFlow::Continue,
+        )
+    }
+
+    /// Writes the resource usage of the current process, its reaped child processes, or the
+    /// current thread to `resource_usage`.
+    pub fn sys_getrusage(
+        &self,
+        who: isize,
+        resource_usage: UserPtr<ResourceUsage>,
+    ) -> (isize, ControlFlow) {
+        let cpu_time = match who {
+            RUSAGE_SELF => self.thread.process().state().lock().cpu_time(),
+            RUSAGE_CHILDREN => self.thread.process().state().lock().children_cpu_time(),
+            RUSAGE_THREAD => self.thread.cpu_time(),
+            _ => return (-EINVAL, ControlFlow::Continue),
+        };
+
+        let result = resource_usage.write(
+            self.thread,
+            ResourceUsage {
+                user_time: TimeVal::from_cycle(cpu_time.user_time()),
+                system_time: TimeVal::from_cycle(cpu_time.system_time()),
+                other: [0; 14],
+            },
+        );
+        match result {
+            Ok(()) => (0, ControlFlow::Continue),
+            Err(error) => (-user_access_errno(error), ControlFlow::Continue),
+        }
+    }
 }
@@ -0,0 +1,50 @@
+//! The `cpu_time` module defines the CPU time that the threads and the processes have used.
+
+use core::ops::{Add, AddAssign};
+
+/// The `CpuTime` struct represents the CPU time spent in user mode and in the kernel, in clock
+/// cycles.
+#[derive(Copy, Clone, Default)]
+pub struct CpuTime {
+    user_time: usize,
+    system_time: usize,
+}
+
+impl CpuTime {
+    pub fn new(user_time: usize, system_time: usize) -> Self {
+        Self {
+            user_time,
+            system_time,
+        }
+    }
+
+    pub fn user_time(&self) -> usize {
+        self.user_time
+    }
+
+    pub fn system_time(&self) -> usize {
+        self.system_time
+    }
+
+    /// Returns the sum of the user time and the system time.
+    pub fn total(&self) -> usize {
+        self.user_time + self.system_time
+    }
+}
+
+impl Add for CpuTime {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self::new(
+            self.user_time + other.user_time,
+            self.system_time + other.system_time,
+        )
+    }
+}
+
+impl AddAssign for CpuTime {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
@@ -1,21 +1,36 @@
 //! The `task` module provides types for representing processes and threads.
 
+mod cpu_time;
+mod oom;
 mod pid;
 mod process;
+mod resource;
+mod signal;
+mod swap;
 mod thread;
 mod tid;
 
 use alloc::sync::Arc;
 
+pub use cpu_time::CpuTime;
 use lazy_static::{initialize, lazy_static};
-pub use process::{Process, Status};
-pub use thread::Thread;
+pub use oom::reclaim_for_fault;
+pub use pid::Pid;
+pub use process::{get_process, set_current_pid, Process, Status};
+pub use resource::ResourceLimit;
+pub use signal::Signal;
+pub use swap::{stop_reclaimer, wake_reclaimer};
+pub use thread::{StackGrowth, Thread};
+pub use tid::Tid;
 
 lazy_static! {
-    static ref INIT_PROCESS: Arc<Process> = Process::new("init");
+    static ref INIT_PROCESS: Arc<Process> =
+        Process::new("init").expect("failed to load the init process");
 }
 
-/// Spawns the init process.
+/// Registers the OOM killer, spawns the reclaimer, and spawns the init process.
 pub fn init() {
+    oom::init();
+    swap::start_reclaimer();
     initialize(&INIT_PROCESS);
 }
@@ -0,0 +1,87 @@
+//! The `oom` module provides the OOM killer, which kills a process to release its frames when the
+//! kernel runs out of frames and no page can be swapped out.
+//!
+//! When the kernel itself runs out of frames, the memory of the current process might be in use,
+//! so the current process is never killed. A page fault of a user process that runs out of frames
+//! is handled without holding any lock, where the current process is a candidate as well, so the
+//! process that owns the most frames is killed rather than any smaller one.
+
+use alloc::sync::Arc;
+
+use log::warn;
+
+use crate::{
+    mem,
+    task::{
+        process::{current_pid, PROCESS_MAP},
+        swap,
+        Process,
+        Status,
+    },
+};
+
+/// Registers the reclaim handler of the frame allocator, which swaps out a page of a process, or
+/// kills a process if no page can be swapped out.
+pub fn init() {
+    mem::set_reclaim_handler(reclaim);
+}
+
+fn reclaim() -> bool {
+    swap::swap_out_page(false) || kill_process(false)
+}
+
+/// Reclaims frames for a page fault of the current process that has run out of frames, which must
+/// be called without holding any lock. Swaps out a page of any process, or kills the process that
+/// owns the most user frames, which might be the current process. Returns `true` if the fault
+/// should be retried.
+pub fn reclaim_for_fault() -> bool {
+    swap::swap_out_page(true) || kill_process(true)
+}
+
+/// Kills the process that owns the most user frames and releases its user segments. The segments
+/// of a process whose thread is in the middle of a system call are released once the thread
+/// returns from it, since the system call might still access them after a yield point.
+/// The init process is never selected, and neither is the process whose thread is running on the
+/// CPU unless `include_current` is `true`, because the kernel might be accessing its memory. The
+/// processes whose lock is held are skipped as well, since the kernel might be allocating memory
+/// while holding the lock. Returns `false` if no process can be killed.
+fn kill_process(include_current: bool) -> bool {
+    let Some(process_map) = PROCESS_MAP.try_lock() else {
+        return false;
+    };
+
+    let mut victim: Option<(Arc<Process>, usize)> = None;
+    for process in process_map.values() {
+        if process.pid() == 0 || (Some(process.pid()) == current_pid() && !include_current) {
+            continue;
+        }
+
+        let Some(process_state) = process.state().try_lock() else {
+            continue;
+        };
+        if process_state.status() == Status::Zombie || process_state.is_killed() {
+            continue;
+        }
+
+        let frame_count = process_state.page_set().user_frame_count();
+        if frame_count > victim.as_ref().map_or(0, |(_, frame_count)| *frame_count) {
+            victim = Some((process.clone(), frame_count));
+        }
+    }
+    drop(process_map);
+
+    let Some((victim, frame_count)) = victim else {
+        return false;
+    };
+    let Some(mut victim_state) = victim.state().try_lock() else {
+        return false;
+    };
+
+    victim_state.kill(victim.is_in_system_call());
+    warn!(
+        "out of memory: killed process {} with {} frames",
+        victim.pid(),
+        frame_count
+    );
+    true
+}
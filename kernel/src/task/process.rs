@@ -4,6 +4,7 @@ use alloc::{
     sync::{Arc, Weak},
     vec::Vec,
 };
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use lazy_static::lazy_static;
 use log::info;
@@ -11,20 +12,55 @@ use log::info;
 use crate::{
     executor,
     file,
-    mem::PageSet,
+    mem::{
+        interpreter_name,
+        ElfImage,
+        HeapObject,
+        LoadError,
+        ObjectTag,
+        ObjectType,
+        OutOfMemory,
+        PageSet,
+        UserLayout,
+        VirtualAddress,
+    },
     sync::{Event, EventBus, Mutex},
     task::{
         pid::{self, Pid, PidHandle},
-        thread::Thread,
+        thread::{initialize_user_stack, Thread},
         tid::{Tid, TidAllocator},
+        CpuTime,
+        ResourceLimit,
     },
 };
 
 lazy_static! {
-    static ref PROCESS_MAP: Mutex<BTreeMap<Pid, Arc<Process>>> = Mutex::new(BTreeMap::new());
+    pub(super) static ref PROCESS_MAP: Mutex<BTreeMap<Pid, Arc<Process>>> =
+        Mutex::new(BTreeMap::new());
+}
+
+/// The value of [CURRENT_PID] while a kernel task is running on the CPU.
+const NO_PID: usize = usize::MAX;
+
+/// The PID of the process whose thread is running on the CPU, or [NO_PID] if a kernel task is
+/// running.
+static CURRENT_PID: AtomicUsize = AtomicUsize::new(NO_PID);
+
+/// Returns the PID of the process whose thread is running on the CPU, or `None` if a kernel task
+/// is running.
+pub fn current_pid() -> Option<Pid> {
+    let pid = CURRENT_PID.load(Ordering::Relaxed);
+    (pid != NO_PID).then_some(pid)
+}
+
+/// Records the PID of the process whose thread is about to run on the CPU, or `None` if a kernel
+/// task is about to run.
+pub fn set_current_pid(pid: Option<Pid>) {
+    CURRENT_PID.store(pid.unwrap_or(NO_PID), Ordering::Relaxed);
 }
 
-fn get_process(pid: Pid) -> Option<Arc<Process>> {
+/// Returns the process with a specific [Pid], or `None` if no such process exists.
+pub fn get_process(pid: Pid) -> Option<Arc<Process>> {
     PROCESS_MAP.lock().get(&pid).cloned()
 }
 
@@ -36,6 +72,17 @@ fn remove_process(pid: Pid) {
     PROCESS_MAP.lock().remove(&pid);
 }
 
+/// Loads an executable file into a new [PageSet], together with the interpreter that it requests
+/// with `PT_INTERP`. The kernel has no file system, so the interpreter is looked up among the
+/// embedded executable files by the last component of its path.
+fn load_elf(elf_data: &[u8], user_layout: &UserLayout) -> Result<(PageSet, ElfImage), LoadError> {
+    let interpreter_data = match interpreter_name(elf_data)? {
+        Some(name) => Some(file::get_bin(name).ok_or(LoadError::InterpreterNotFound)?),
+        None => None,
+    };
+    PageSet::from_elf(elf_data, interpreter_data, user_layout)
+}
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum Status {
     Runnable,
@@ -47,37 +94,68 @@ pub struct Process {
 
     state: Mutex<ProcessState>,
     event_bus: Arc<Mutex<EventBus>>,
+    /// The number of threads of the process that are in the middle of a system call.
+    system_call_count: AtomicUsize,
+    _object_tag: ObjectTag<Self>,
+}
+
+impl HeapObject for Process {
+    const OBJECT_TYPE: ObjectType = ObjectType::Process;
 }
 
 pub struct ProcessState {
     status: Status,
     exit_code: usize,
     page_set: PageSet,
+    program_break_start: VirtualAddress,
+    program_break: VirtualAddress,
+    user_layout: UserLayout,
+    killed: bool,
+    stack_limit: ResourceLimit,
     tid_allocator: TidAllocator,
+    exited_thread_cpu_time: CpuTime,
+    children_cpu_time: CpuTime,
     parent: Option<Weak<Process>>,
     child_list: Vec<Arc<Process>>,
     thread_list: Vec<Arc<Thread>>,
 }
 
 impl Process {
-    /// Creates a process with a main thread that runs a specific executable file.
-    pub fn new(bin_name: &str) -> Arc<Self> {
-        let elf_data = file::get_bin(bin_name).unwrap();
-        let (page_set, user_stack_base, entry_point) = PageSet::from_elf(elf_data);
+    /// Creates a process with a main thread that runs a specific executable file. Returns an error
+    /// if the executable file can't be loaded.
+    pub fn new(bin_name: &str) -> Result<Arc<Self>, LoadError> {
+        let elf_data = file::get_bin(bin_name).ok_or(LoadError::NotFound)?;
+        let user_layout = UserLayout::new();
+        let (page_set, elf_image) = load_elf(elf_data, &user_layout)?;
 
         let pid_handle = pid::allocate_pid();
         let process = Arc::new(Self {
             pid_handle,
-            state: Mutex::new(ProcessState::new(page_set, None)),
+            state: Mutex::new(ProcessState::new(
+                page_set,
+                elf_image.program_break(),
+                user_layout,
+                ResourceLimit::user_stack(),
+                None,
+            )),
             event_bus: EventBus::new(),
+            system_call_count: AtomicUsize::new(0),
+            _object_tag: ObjectTag::new(),
         });
 
-        let thread = Arc::new(Thread::new(process.clone(), user_stack_base, true));
-        let thread_state = thread.state().lock();
-        let trap_context = thread_state.kernel_trap_context_mut();
-        trap_context.set_user_register(2, usize::from(thread_state.user_stack_top()));
-        trap_context.set_user_sepc(usize::from(entry_point));
-        drop(thread_state);
+        let thread = Arc::new(
+            Thread::new(process.clone(), user_layout.user_stack_base(), true)
+                .expect("failed to allocate the main thread"),
+        );
+        let user_stack_pointer = initialize_user_stack(
+            process.state().lock().page_set_mut(),
+            thread.user_stack_top(),
+            &elf_image,
+        )
+        .expect("failed to initialize the user stack");
+        let trap_context = thread.state().lock().kernel_trap_context_mut();
+        trap_context.set_user_register(2, usize::from(user_stack_pointer));
+        trap_context.set_user_sepc(usize::from(elf_image.start_address()));
 
         process
             .state()
@@ -86,27 +164,40 @@ impl Process {
             .push(thread.clone());
         insert_process(process.pid(), process.clone());
         executor::spawn_thread(thread);
-        process
+        Ok(process)
     }
 
     /// Forks the current process and create a new child process.
-    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+    /// Returns an error without creating the child process if the frame allocator runs out of
+    /// frames.
+    pub fn fork(self: &Arc<Self>) -> Result<Arc<Self>, OutOfMemory> {
         let pid_handle = pid::allocate_pid();
 
         let mut process_state = self.state().lock();
-        let page_set = PageSet::clone_from(process_state.page_set_mut());
+        let page_set = PageSet::clone_from(process_state.page_set_mut())?;
 
+        let mut child_process_state = ProcessState::new(
+            page_set,
+            process_state.program_break_start(),
+            process_state.user_layout(),
+            process_state.stack_limit(),
+            Some(Arc::downgrade(self)),
+        );
+        child_process_state.set_program_break(process_state.program_break());
         let child_process = Arc::new(Self {
             pid_handle,
-            state: Mutex::new(ProcessState::new(page_set, Some(Arc::downgrade(self)))),
+            state: Mutex::new(child_process_state),
             event_bus: EventBus::new(),
+            system_call_count: AtomicUsize::new(0),
+            _object_tag: ObjectTag::new(),
         });
-        process_state.child_list_mut().push(child_process.clone());
 
         let user_stack_base = process_state.main_thread().user_stack_base();
+        let parent_task_info = process_state.main_thread().task_info().clone();
         drop(process_state);
 
-        let thread = Arc::new(Thread::new(child_process.clone(), user_stack_base, false));
+        let thread = Arc::new(Thread::new(child_process.clone(), user_stack_base, false)?);
+        thread.task_info().inherit(&parent_task_info);
         let trap_context = thread.state().lock().kernel_trap_context_mut();
         trap_context.set_user_register(10, 0);
         child_process
@@ -115,27 +206,42 @@ impl Process {
             .thread_list_mut()
             .push(thread.clone());
 
+        self.state()
+            .lock()
+            .child_list_mut()
+            .push(child_process.clone());
         insert_process(child_process.pid(), child_process.clone());
         executor::spawn_thread(thread);
-        child_process
+        Ok(child_process)
     }
 
     /// Replaces the current process with a new process loaded from the executable file with a given
-    /// name.
-    pub fn exec(self: &Arc<Self>, bin_name: &str, _argument_list: Vec<String>) {
-        let elf_data = file::get_bin(bin_name).unwrap();
-        let (page_set, user_stack_base, entry_point) = PageSet::from_elf(elf_data);
+    /// name. Returns an error and leaves the current process unchanged if the executable file or
+    /// its interpreter is not found, the frame allocator runs out of frames, or the executable file
+    /// violates the W^X policy.
+    pub fn exec(
+        self: &Arc<Self>,
+        bin_name: &str,
+        _argument_list: Vec<String>,
+    ) -> Result<(), LoadError> {
+        let elf_data = file::get_bin(bin_name).ok_or(LoadError::NotFound)?;
+        let user_layout = UserLayout::new();
+        let (mut page_set, elf_image) = load_elf(elf_data, &user_layout)?;
+
+        let thread = self.state().lock().main_thread_mut().clone();
+        let user_stack_pointer =
+            thread.reallocate_resource(&mut page_set, user_layout.user_stack_base(), &elf_image)?;
 
         let mut process_state = self.state().lock();
         process_state.set_page_set(page_set);
-        let thread = process_state.main_thread_mut().clone();
+        process_state.set_program_break_start(elf_image.program_break());
+        process_state.set_user_layout(user_layout);
         drop(process_state);
 
-        thread.reallocate_resource(user_stack_base);
-        let thread_state = thread.state().lock();
-        let trap_context = thread_state.kernel_trap_context_mut();
-        trap_context.set_user_register(2, usize::from(thread_state.user_stack_top()));
-        trap_context.set_user_sepc(usize::from(entry_point));
+        let trap_context = thread.state().lock().kernel_trap_context_mut();
+        trap_context.set_user_register(2, usize::from(user_stack_pointer));
+        trap_context.set_user_sepc(usize::from(elf_image.start_address()));
+        Ok(())
     }
 
     /// Terminates the current thread with the given exit code.
@@ -152,7 +258,7 @@ impl Process {
                     .push(child_process.clone());
             }
         }
-        process_state.thread_list_mut().clear();
+        process_state.clear_thread_list();
         process_state.child_list_mut().clear();
 
         if let Some(parent) = process_state.parent() {
@@ -176,14 +282,43 @@ impl Process {
     pub fn event_bus(&self) -> Arc<Mutex<EventBus>> {
         self.event_bus.clone()
     }
+
+    /// Records that a thread of the process enters a system call.
+    pub fn enter_system_call(&self) {
+        self.system_call_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that a thread of the process returns from a system call.
+    pub fn leave_system_call(&self) {
+        self.system_call_count.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if a thread of the process is in the middle of a system call, which might
+    /// still access the user segments after a yield point.
+    pub fn is_in_system_call(&self) -> bool {
+        self.system_call_count.load(Ordering::Relaxed) != 0
+    }
 }
 
 impl ProcessState {
-    pub fn new(page_set: PageSet, parent: Option<Weak<Process>>) -> Self {
+    pub fn new(
+        page_set: PageSet,
+        program_break_start: VirtualAddress,
+        user_layout: UserLayout,
+        stack_limit: ResourceLimit,
+        parent: Option<Weak<Process>>,
+    ) -> Self {
         Self {
             page_set,
+            program_break_start,
+            program_break: program_break_start,
+            user_layout,
+            killed: false,
+            stack_limit,
             parent,
             tid_allocator: TidAllocator::new(),
+            exited_thread_cpu_time: CpuTime::default(),
+            children_cpu_time: CpuTime::default(),
             child_list: Vec::new(),
             thread_list: Vec::new(),
             exit_code: 0,
@@ -219,6 +354,30 @@ impl ProcessState {
         &mut self.thread_list
     }
 
+    /// Removes the threads of the process, whose CPU time is kept in the process.
+    pub fn clear_thread_list(&mut self) {
+        self.exited_thread_cpu_time = self.cpu_time();
+        self.thread_list.clear();
+    }
+
+    /// Returns the CPU time that the threads of the process have used.
+    pub fn cpu_time(&self) -> CpuTime {
+        self.thread_list
+            .iter()
+            .fold(self.exited_thread_cpu_time, |cpu_time, thread| {
+                cpu_time + thread.cpu_time()
+            })
+    }
+
+    /// Returns the CPU time that the reaped child processes and their descendants have used.
+    pub fn children_cpu_time(&self) -> CpuTime {
+        self.children_cpu_time
+    }
+
+    pub fn add_children_cpu_time(&mut self, cpu_time: CpuTime) {
+        self.children_cpu_time += cpu_time;
+    }
+
     pub fn main_thread(&self) -> &Arc<Thread> {
         &self.thread_list[0]
     }
@@ -239,6 +398,62 @@ impl ProcessState {
         self.page_set = page_set;
     }
 
+    pub fn program_break_start(&self) -> VirtualAddress {
+        self.program_break_start
+    }
+
+    /// Sets the start of the program break, which also resets the program break to the start.
+    pub fn set_program_break_start(&mut self, program_break_start: VirtualAddress) {
+        self.program_break_start = program_break_start;
+        self.program_break = program_break_start;
+    }
+
+    pub fn program_break(&self) -> VirtualAddress {
+        self.program_break
+    }
+
+    pub fn set_program_break(&mut self, program_break: VirtualAddress) {
+        self.program_break = program_break;
+    }
+
+    pub fn user_layout(&self) -> UserLayout {
+        self.user_layout
+    }
+
+    pub fn set_user_layout(&mut self, user_layout: UserLayout) {
+        self.user_layout = user_layout;
+    }
+
+    /// Returns `true` if the process has been killed by the OOM killer.
+    pub fn is_killed(&self) -> bool {
+        self.killed
+    }
+
+    /// Marks the process as killed, and releases its user segments unless `is_in_system_call` is
+    /// `true`, in which case the segments are released by [ProcessState::release_killed] once no
+    /// thread is in the middle of a system call. The threads of the process exit when they are
+    /// scheduled again.
+    pub fn kill(&mut self, is_in_system_call: bool) {
+        self.killed = true;
+        if !is_in_system_call {
+            self.page_set.remove_user_segments();
+        }
+    }
+
+    /// Releases the user segments of a killed process that have been kept for the threads in the
+    /// middle of a system call, which is called once none of them is left.
+    pub fn release_killed(&mut self) {
+        self.page_set.remove_user_segments();
+    }
+
+    pub fn stack_limit(&self) -> ResourceLimit {
+        self.stack_limit
+    }
+
+    pub fn set_stack_limit(&mut self, stack_limit: ResourceLimit) {
+        self.stack_limit = stack_limit;
+    }
+
     pub fn allocate_tid(&mut self) -> Tid {
         self.tid_allocator.allocate()
     }
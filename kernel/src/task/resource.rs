@@ -0,0 +1,32 @@
+//! The `resource` module defines the resource limits of a process.
+
+use crate::constant::USER_STACK_LIMIT;
+
+/// The `ResourceLimit` struct represents the soft limit and the hard limit of a resource, which
+/// follows the layout of the `rlimit` struct in Linux.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ResourceLimit {
+    current: usize,
+    maximum: usize,
+}
+
+impl ResourceLimit {
+    pub fn new(current: usize, maximum: usize) -> Self {
+        Self { current, maximum }
+    }
+
+    /// Returns the default limit of the user stack size, which is the size of the address range
+    /// reserved for the user stack of each thread.
+    pub fn user_stack() -> Self {
+        Self::new(USER_STACK_LIMIT, USER_STACK_LIMIT)
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn maximum(&self) -> usize {
+        self.maximum
+    }
+}
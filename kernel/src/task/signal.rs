@@ -0,0 +1,24 @@
+//! The `signal` module defines the signals that terminate a process.
+
+/// The `Signal` enum represents the signals that the kernel delivers to a process when it fails to
+/// handle a fault on behalf of the process.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Signal {
+    /// The `SIGBUS` signal, which indicates that a page of the process can't be read back from the
+    /// swap area.
+    Bus = 7,
+    /// The `SIGKILL` signal, which indicates that the process is killed because the system runs
+    /// out of memory.
+    Kill = 9,
+    /// The `SIGSEGV` signal, which indicates an invalid memory reference, such as a stack
+    /// overflow.
+    SegmentationFault = 11,
+}
+
+impl Signal {
+    /// Returns the exit code of a process terminated by the signal, which follows the convention of
+    /// the shell.
+    pub fn exit_code(&self) -> usize {
+        128 + *self as usize
+    }
+}
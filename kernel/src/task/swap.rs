@@ -0,0 +1,144 @@
+//! The `swap` module reclaims frames by swapping out the pages of the user processes, which picks
+//! the victims with the clock algorithm.
+//!
+//! The clock hand moves over the pages of each process in the order of the PIDs and the addresses.
+//! A page that has been accessed since the hand passed it gets a second chance, where its accessed
+//! bit is cleared, and the first page that hasn't been accessed is swapped out.
+
+use crate::{
+    constant::{SWAP_HIGH_WATERMARK, SWAP_LOW_WATERMARK},
+    executor::{self, JoinHandle},
+    mem::{free_frame_count, is_swap_enabled, PageNumber},
+    sync::{Mutex, WaitQueue},
+    task::{
+        process::{current_pid, PROCESS_MAP},
+        Status,
+    },
+};
+
+/// The `ClockHand` struct represents the position of the clock hand, which is a page in the
+/// address space of a process.
+struct ClockHand {
+    pid: usize,
+    page_number: usize,
+}
+
+static CLOCK_HAND: Mutex<ClockHand> = Mutex::new(ClockHand {
+    pid: 0,
+    page_number: 0,
+});
+
+/// Swaps out a page that hasn't been accessed recently. The processes whose lock is held are
+/// skipped, since the kernel might be allocating memory while holding the lock, and so is the
+/// process whose thread is running on the CPU unless `include_current` is `true`, since the kernel
+/// might be accessing its memory. Returns `false` if no page can be swapped out.
+pub fn swap_out_page(include_current: bool) -> bool {
+    if !is_swap_enabled() {
+        return false;
+    }
+
+    let Some(process_map) = PROCESS_MAP.try_lock() else {
+        return false;
+    };
+    let Some(mut clock_hand) = CLOCK_HAND.try_lock() else {
+        return false;
+    };
+
+    // The hand passes every process twice, where the first pass might only clear the accessed bits
+    for _ in 0..=2 * process_map.len() {
+        let Some((&pid, process)) = process_map
+            .range(clock_hand.pid..)
+            .next()
+            .or_else(|| process_map.iter().next())
+        else {
+            return false;
+        };
+
+        if pid != clock_hand.pid {
+            clock_hand.pid = pid;
+            clock_hand.page_number = 0;
+        }
+
+        let victim = if Some(pid) == current_pid() && !include_current {
+            None
+        } else {
+            process.state().try_lock().and_then(|mut process_state| {
+                if process_state.status() == Status::Zombie || process_state.is_killed() {
+                    return None;
+                }
+
+                let page_set = process_state.page_set_mut();
+                let victim = page_set.find_victim(PageNumber::from(clock_hand.page_number))?;
+                Some((victim, page_set.swap_out(victim)))
+            })
+        };
+
+        match victim {
+            Some((victim, swapped)) => {
+                clock_hand.page_number = usize::from(victim) + 1;
+                return swapped;
+            }
+            None => {
+                clock_hand.pid = pid + 1;
+                clock_hand.page_number = 0;
+            }
+        }
+    }
+    false
+}
+
+/// Swaps out pages until the number of free frames reaches [SWAP_HIGH_WATERMARK] once it drops
+/// below [SWAP_LOW_WATERMARK]. The kernel must not hold any lock or access the memory of any
+/// process when calling this function.
+fn balance_memory() {
+    if free_frame_count() >= SWAP_LOW_WATERMARK {
+        return;
+    }
+
+    while free_frame_count() < SWAP_HIGH_WATERMARK {
+        if !swap_out_page(true) {
+            break;
+        }
+    }
+}
+
+/// Returns `true` if the number of free frames has dropped below [SWAP_LOW_WATERMARK] and some
+/// pages can be swapped out.
+fn is_under_pressure() -> bool {
+    is_swap_enabled() && free_frame_count() < SWAP_LOW_WATERMARK
+}
+
+/// The queue that the reclaimer sleeps in until the memory runs low.
+static RECLAIMER_QUEUE: WaitQueue = WaitQueue::new();
+
+/// The handle to the reclaimer, which is set while it is running.
+static RECLAIMER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// The `reclaimer` future represents the lifetime of the reclaimer, which balances the memory
+/// whenever it is woken by [wake_reclaimer]. Since the kernel task is polled by the executor, it
+/// holds no lock when swapping out pages.
+async fn reclaimer() {
+    loop {
+        RECLAIMER_QUEUE.wait().await;
+        balance_memory();
+    }
+}
+
+/// Spawns the reclaimer as a kernel task.
+pub fn start_reclaimer() {
+    *RECLAIMER.lock() = Some(executor::spawn_kernel_task("reclaimer", reclaimer()));
+}
+
+/// Cancels the reclaimer, which is dropped the next time the executor runs.
+pub fn stop_reclaimer() {
+    if let Some(reclaimer) = RECLAIMER.lock().take() {
+        reclaimer.cancel();
+    }
+}
+
+/// Wakes the reclaimer if the number of free frames has dropped below [SWAP_LOW_WATERMARK].
+pub fn wake_reclaimer() {
+    if is_under_pressure() {
+        RECLAIMER_QUEUE.notify_one();
+    }
+}
@@ -1,19 +1,127 @@
-use alloc::sync::{Arc, Weak};
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
 
 use crate::{
-    constant::{PAGE_SIZE, TRAP_CONTEXT_BASE, USER_STACK_SIZE},
-    executor::TrapContext,
-    mem::{FrameNumber, MapPermission, PageNumber, VirtualAddress},
+    constant::{PAGE_SIZE, TRAP_CONTEXT_BASE, USER_STACK_LIMIT, USER_STACK_SIZE},
+    executor::{TaskInfo, TrapContext},
+    mem::{
+        ElfImage,
+        FrameNumber,
+        HeapObject,
+        MapPermission,
+        ObjectTag,
+        ObjectType,
+        OutOfMemory,
+        PageFaultError,
+        PageNumber,
+        PageSet,
+        PhysicalAddress,
+        UserAccess,
+        UserAccessError,
+        VirtualAddress,
+    },
+    random,
     sync::Mutex,
-    task::{tid::Tid, Process},
+    task::{tid::Tid, CpuTime, Process},
 };
 
+/// The types of the entries in the auxiliary vector, which follow the definitions in Linux.
+const AT_NULL: usize = 0;
+const AT_PHDR: usize = 3;
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_BASE: usize = 7;
+const AT_ENTRY: usize = 9;
+const AT_RANDOM: usize = 25;
+
+/// The size of a program header in a 64-bit ELF file.
+const PROGRAM_HEADER_SIZE: usize = 56;
+
+/// Returns the top of the user stack of the thread with a specific [Tid].
+/// Each thread reserves a guard page followed by [USER_STACK_LIMIT] bytes for its user stack.
+/// The guard page is never mapped, which separates the user stack from the user stack of the
+/// previous thread.
+fn user_stack_top(user_stack_base: VirtualAddress, tid: Tid) -> VirtualAddress {
+    user_stack_base + (tid + 1) * (PAGE_SIZE + USER_STACK_LIMIT)
+}
+
+/// Writes the initial user stack of a program below `user_stack_top`, and returns the stack
+/// pointer, which points to `argc` followed by the null-terminated `argv` and `envp` and the
+/// auxiliary vector. The `AT_RANDOM` entry points to 16 random bytes at the top of the stack, which
+/// the program can use to seed its random number generator. The other entries describe the
+/// [ElfImage], so that an interpreter can find the program headers and the entry point of the
+/// executable. Returns an error if the frame allocator runs out of frames.
+pub fn initialize_user_stack(
+    page_set: &mut PageSet,
+    user_stack_top: VirtualAddress,
+    elf_image: &ElfImage,
+) -> Result<VirtualAddress, OutOfMemory> {
+    let mut random_bytes = [0; 16];
+    random::fill_random(&mut random_bytes);
+    let random_address = user_stack_top - random_bytes.len();
+    page_set.write_user(random_address, &random_bytes)?;
+
+    // The program is started without arguments or environment variables
+    let word_list = [
+        0,
+        0,
+        0,
+        AT_PHDR,
+        usize::from(elf_image.program_header_address()),
+        AT_PHENT,
+        PROGRAM_HEADER_SIZE,
+        AT_PHNUM,
+        elf_image.program_header_count(),
+        AT_PAGESZ,
+        PAGE_SIZE,
+        AT_BASE,
+        elf_image.interpreter_base().map_or(0, usize::from),
+        AT_ENTRY,
+        usize::from(elf_image.entry_point()),
+        AT_RANDOM,
+        usize::from(random_address),
+        AT_NULL,
+        0,
+    ];
+    let bytes: Vec<u8> = word_list
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+
+    // The stack pointer is aligned to 16 bytes as the RISC-V calling convention requires
+    let user_stack_pointer =
+        VirtualAddress::from((usize::from(random_address) - bytes.len()) & !0xf);
+    page_set.write_user(user_stack_pointer, &bytes)?;
+    Ok(user_stack_pointer)
+}
+
+/// The `StackGrowth` enum represents the result of extending the user stack on a page fault.
+#[derive(PartialEq, Eq)]
+pub enum StackGrowth {
+    /// The user stack has been extended to cover the faulting address.
+    Grown,
+    /// The faulting address is in the guard page or exceeds the `RLIMIT_STACK` of the process.
+    Overflow,
+    /// The faulting address is not in the address range reserved for the user stack.
+    Outside,
+    /// The frame allocator runs out of frames for the user stack.
+    OutOfMemory,
+}
+
 pub struct Thread {
     tid: Tid,
     process: Weak<Process>,
-    user_stack_base: VirtualAddress,
+    task_info: Arc<TaskInfo>,
 
     state: Mutex<ThreadState>,
+    _object_tag: ObjectTag<Self>,
+}
+
+impl HeapObject for Thread {
+    const OBJECT_TYPE: ObjectType = ObjectType::Thread;
 }
 
 impl Thread {
@@ -21,18 +129,17 @@ impl Thread {
         process: Arc<Process>,
         user_stack_base: VirtualAddress,
         allocate_resource: bool,
-    ) -> Self {
+    ) -> Result<Self, OutOfMemory> {
         let mut process_state = process.state().lock();
         let tid = process_state.allocate_tid();
 
-        let user_stack_bottom = user_stack_base + tid * (PAGE_SIZE + USER_STACK_SIZE);
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        let user_stack_top = user_stack_top(user_stack_base, tid);
         if allocate_resource {
             process_state.page_set_mut().insert_frame(
-                user_stack_bottom,
+                user_stack_top - USER_STACK_SIZE,
                 user_stack_top,
                 MapPermission::R | MapPermission::W | MapPermission::U,
-            );
+            )?;
         }
 
         let trap_context_bottom = VirtualAddress::from(TRAP_CONTEXT_BASE) + tid * PAGE_SIZE;
@@ -42,7 +149,7 @@ impl Thread {
                 trap_context_bottom,
                 trap_context_top,
                 MapPermission::R | MapPermission::W,
-            );
+            )?;
         }
         let trap_context_page = PageNumber::from(trap_context_bottom);
         let trap_context_frame = process_state
@@ -51,55 +158,80 @@ impl Thread {
             .unwrap()
             .frame_number();
 
-        Self {
+        Ok(Self {
             tid,
             process: Arc::downgrade(&process),
-            user_stack_base,
+            task_info: Arc::new(TaskInfo::new(Some((process.pid(), tid)))),
             state: Mutex::new(ThreadState::new(
                 trap_context_page,
                 trap_context_frame,
-                user_stack_bottom,
+                user_stack_base,
             )),
-        }
+            _object_tag: ObjectTag::new(),
+        })
     }
 
-    pub fn reallocate_resource(&self, user_stack_base: VirtualAddress) {
-        let process = self.process();
-        let mut process_state = process.state().lock();
-
-        let user_stack_bottom = user_stack_base + self.tid() * (PAGE_SIZE + USER_STACK_SIZE);
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
-        process_state.page_set_mut().insert_frame(
-            user_stack_bottom,
+    /// Allocates the user stack and the trap context of the thread in a new [PageSet], which is
+    /// going to replace the [PageSet] of the process, and returns the stack pointer of the initial
+    /// user stack. The thread is left unchanged if the frame allocator runs out of frames.
+    pub fn reallocate_resource(
+        &self,
+        page_set: &mut PageSet,
+        user_stack_base: VirtualAddress,
+        elf_image: &ElfImage,
+    ) -> Result<VirtualAddress, OutOfMemory> {
+        let user_stack_top = user_stack_top(user_stack_base, self.tid());
+        page_set.insert_frame(
+            user_stack_top - USER_STACK_SIZE,
             user_stack_top,
             MapPermission::R | MapPermission::W | MapPermission::U,
-        );
-
-        let mut thread_state = self.state().lock();
-        thread_state.set_user_stack_bottom(user_stack_bottom);
+        )?;
+        let user_stack_pointer = initialize_user_stack(page_set, user_stack_top, elf_image)?;
 
         let trap_context_bottom = VirtualAddress::from(TRAP_CONTEXT_BASE) + self.tid() * PAGE_SIZE;
         let trap_context_top = trap_context_bottom + PAGE_SIZE;
-        process_state.page_set_mut().insert_frame(
+        page_set.insert_frame(
             trap_context_bottom,
             trap_context_top,
             MapPermission::R | MapPermission::W,
-        );
+        )?;
         let trap_context_page = PageNumber::from(trap_context_bottom);
-        let trap_context_frame = process_state
-            .page_set()
+        let trap_context_frame = page_set
             .translate(trap_context_page)
             .unwrap()
             .frame_number();
+
+        let mut thread_state = self.state().lock();
+        thread_state.set_user_stack_base(user_stack_base);
         thread_state.set_trap_context_frame(trap_context_frame);
+        Ok(user_stack_pointer)
     }
 
     pub fn tid(&self) -> Tid {
         self.tid
     }
 
+    /// Returns the scheduling metadata of the thread, which is shared with the task that runs it.
+    pub fn task_info(&self) -> &Arc<TaskInfo> {
+        &self.task_info
+    }
+
+    /// Returns the CPU time that the thread has used, where the system time is the time that the
+    /// executor has spent polling the thread outside user mode.
+    pub fn cpu_time(&self) -> CpuTime {
+        let user_time = self.task_info.user_time();
+        CpuTime::new(
+            user_time,
+            self.task_info.runtime().saturating_sub(user_time),
+        )
+    }
+
     pub fn user_stack_base(&self) -> VirtualAddress {
-        self.user_stack_base
+        self.state().lock().user_stack_base()
+    }
+
+    pub fn user_stack_top(&self) -> VirtualAddress {
+        user_stack_top(self.user_stack_base(), self.tid())
     }
 
     pub fn process(&self) -> Arc<Process> {
@@ -110,11 +242,13 @@ impl Thread {
         &self.state
     }
 
-    pub fn satp(&self) -> usize {
-        self.process().state().lock().page_set().satp()
+    /// Prepares the [PageSet] of the process to be switched to, and returns the value of the
+    /// `satp` register that points to its page table.
+    pub fn activate(&self) -> usize {
+        self.process().state().lock().page_set_mut().activate()
     }
 
-    pub fn clone_frame(&self, virtual_address: VirtualAddress) -> bool {
+    pub fn clone_frame(&self, virtual_address: VirtualAddress) -> Result<bool, OutOfMemory> {
         self.process()
             .state()
             .lock()
@@ -122,17 +256,86 @@ impl Thread {
             .clone_frame(virtual_address)
     }
 
+    /// Extends the user stack downward to cover a specific [VirtualAddress] when the address is
+    /// below the mapped part of the user stack and within the `RLIMIT_STACK` of the process.
+    pub fn grow_user_stack(&self, virtual_address: VirtualAddress) -> StackGrowth {
+        let user_stack_top = self.user_stack_top();
+        let user_stack_guard = user_stack_top - USER_STACK_LIMIT - PAGE_SIZE;
+        if virtual_address < user_stack_guard || virtual_address >= user_stack_top {
+            return StackGrowth::Outside;
+        }
+
+        let process = self.process();
+        let mut process_state = process.state().lock();
+        let stack_limit = process_state.stack_limit().current().min(USER_STACK_LIMIT);
+        if virtual_address < user_stack_top - stack_limit {
+            return StackGrowth::Overflow;
+        }
+
+        match process_state
+            .page_set_mut()
+            .extend_segment_down(user_stack_top - PAGE_SIZE, virtual_address)
+        {
+            Ok(true) => StackGrowth::Grown,
+            Ok(false) => StackGrowth::Outside,
+            Err(OutOfMemory) => StackGrowth::OutOfMemory,
+        }
+    }
+
+    /// Reads the page that contains a specific [VirtualAddress] back from the swap area if the page
+    /// has been swapped out.
+    pub fn swap_in(&self, virtual_address: VirtualAddress) -> Result<bool, PageFaultError> {
+        self.process()
+            .state()
+            .lock()
+            .page_set_mut()
+            .swap_in(virtual_address)
+    }
+
+    /// Translates a [VirtualAddress] that the kernel accesses on behalf of the thread, which
+    /// resolves copy-on-write pages and extends the user stack as a page fault in user space would.
+    pub fn translate_user(
+        &self,
+        virtual_address: VirtualAddress,
+        access: UserAccess,
+    ) -> Result<PhysicalAddress, UserAccessError> {
+        let translate = || {
+            self.process()
+                .state()
+                .lock()
+                .page_set_mut()
+                .translate_user(virtual_address, access)
+        };
+
+        match translate() {
+            Ok(Some(physical_address)) => return Ok(physical_address),
+            Ok(None) => (),
+            Err(PageFaultError::OutOfMemory) => return Err(UserAccessError::OutOfMemory),
+            Err(PageFaultError::Io) => return Err(UserAccessError::Fault),
+        }
+
+        match self.grow_user_stack(virtual_address) {
+            StackGrowth::Grown => match translate() {
+                Ok(Some(physical_address)) => Ok(physical_address),
+                Ok(None) | Err(PageFaultError::Io) => Err(UserAccessError::Fault),
+                Err(PageFaultError::OutOfMemory) => Err(UserAccessError::OutOfMemory),
+            },
+            StackGrowth::OutOfMemory => Err(UserAccessError::OutOfMemory),
+            StackGrowth::Overflow | StackGrowth::Outside => Err(UserAccessError::Fault),
+        }
+    }
+
     pub fn exit(&self, exit_code: usize) {
         self.process().exit(exit_code);
     }
 
     fn deallocate_user_stack(&self) {
-        let user_stack_bottom = self.user_stack_base + self.tid() * (PAGE_SIZE + USER_STACK_SIZE);
+        let user_stack_top = self.user_stack_top();
         self.process()
             .state()
             .lock()
             .page_set_mut()
-            .remove_segment(user_stack_bottom);
+            .remove_segment(user_stack_top - PAGE_SIZE);
     }
 
     fn deallocate_trap_context(&self) {
@@ -156,28 +359,28 @@ impl Drop for Thread {
 pub struct ThreadState {
     trap_context_page: PageNumber,
     trap_context_frame: FrameNumber,
-    user_stack_bottom: VirtualAddress,
+    user_stack_base: VirtualAddress,
 }
 
 impl ThreadState {
     pub fn new(
         trap_context_page: PageNumber,
         trap_context_frame: FrameNumber,
-        user_stack_bottom: VirtualAddress,
+        user_stack_base: VirtualAddress,
     ) -> Self {
         Self {
             trap_context_page,
             trap_context_frame,
-            user_stack_bottom,
+            user_stack_base,
         }
     }
 
-    pub fn set_user_stack_bottom(&mut self, user_stack_bottom: VirtualAddress) {
-        self.user_stack_bottom = user_stack_bottom;
+    pub fn user_stack_base(&self) -> VirtualAddress {
+        self.user_stack_base
     }
 
-    pub fn user_stack_top(&self) -> VirtualAddress {
-        self.user_stack_bottom + USER_STACK_SIZE
+    pub fn set_user_stack_base(&mut self, user_stack_base: VirtualAddress) {
+        self.user_stack_base = user_stack_base;
     }
 
     pub fn set_trap_context_frame(&mut self, trap_context_frame: FrameNumber) {